@@ -0,0 +1,176 @@
+//! The ACL of the znodes runs create, from `--acl`.
+
+use crate::error::BenchError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use zookeeper::{Acl, Permission};
+
+/// An ACL as `--acl` takes it: `open`, `creator-all`, `read-unsafe` or a comma separated list of
+/// `scheme:id:perms` entries, like `digest:bob:secret:cdrwa,world:anyone:r`. Digest entries take
+/// the password, which they're made of the digest of as the server expects, and perms are
+/// letters of `cdrwa` or `all`.
+#[derive(Clone, Debug)]
+pub struct NodeAcl {
+    /// Name of the ACL if it's one of those with one
+    name: Option<&'static str>,
+    acl: Arc<Vec<Acl>>,
+    client: Arc<Vec<zookeeper_client::Acl>>,
+}
+
+impl NodeAcl {
+    pub fn acl(&self) -> Vec<Acl> {
+        self.acl.to_vec()
+    }
+
+    /// The ACL as the async client takes it.
+    pub fn client_acls(&self) -> zookeeper_client::Acls<'_> {
+        zookeeper_client::Acls::new(&self.client)
+    }
+
+    /// Whether the ACL grants whoever created the znode, which only sessions that authenticated
+    /// can be.
+    pub fn needs_auth(&self) -> bool {
+        self.acl.iter().any(|a| a.scheme == "auth")
+    }
+
+    /// The ACL of the znodes the run creates others below, the prefix among them. The sessions
+    /// go on creating and deleting below those whatever the ACL lets anyone do with the znodes
+    /// they hold, so unless it grants anyone all, they are granted all too, as their creator
+    /// when they authenticate.
+    pub fn parent_acl(&self, authenticated: bool) -> Vec<Acl> {
+        let ours = match authenticated {
+            true => Acl::creator_all()[0].clone(),
+            false => Acl::open_unsafe()[0].clone(),
+        };
+        let mut acl = self.acl();
+        if !acl
+            .iter()
+            .any(|a| *a == ours || *a == Acl::open_unsafe()[0])
+        {
+            acl.push(ours);
+        }
+        acl
+    }
+
+    fn new(name: Option<&'static str>, acl: Vec<Acl>) -> Self {
+        let client = acl
+            .iter()
+            .map(|a| {
+                zookeeper_client::Acl::new(
+                    client_permission(a.perms),
+                    zookeeper_client::AuthId::new(&a.scheme, &a.id),
+                )
+            })
+            .collect();
+        NodeAcl {
+            name,
+            acl: Arc::new(acl),
+            client: Arc::new(client),
+        }
+    }
+}
+
+impl Default for NodeAcl {
+    fn default() -> Self {
+        NodeAcl::new(Some("open"), Acl::open_unsafe().clone())
+    }
+}
+
+impl FromStr for NodeAcl {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "open" => NodeAcl::default(),
+            "creator-all" => NodeAcl::new(Some("creator-all"), Acl::creator_all().clone()),
+            "read-unsafe" => NodeAcl::new(Some("read-unsafe"), Acl::read_unsafe().clone()),
+            _ => NodeAcl::new(None, s.split(',').map(entry).collect::<Result<_, _>>()?),
+        })
+    }
+}
+
+impl fmt::Display for NodeAcl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name {
+            return f.write_str(name);
+        }
+        // With the digests of passwords, which stay out of the options printed
+        let entries: Vec<String> = self
+            .acl
+            .iter()
+            .map(|a| format!("{}:{}:{}", a.scheme, a.id, letters(a.perms)))
+            .collect();
+        f.write_str(&entries.join(","))
+    }
+}
+
+impl Serialize for NodeAcl {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// An entry like `world:anyone:r`, or `digest:bob:secret:cdrwa` with the password of the user.
+fn entry(s: &str) -> Result<Acl, BenchError> {
+    let invalid = || BenchError::InvalidOption(format!("bad ACL entry `{}`", s));
+    let (rest, perms) = s.trim().rsplit_once(':').ok_or_else(invalid)?;
+    let (scheme, id) = rest.split_once(':').ok_or_else(invalid)?;
+    if scheme.is_empty() || id.is_empty() {
+        return Err(invalid());
+    }
+    let id = match scheme {
+        "digest" => {
+            let (user, _) = id.split_once(':').ok_or_else(invalid)?;
+            let hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, id.as_bytes());
+            format!("{}:{}", user, STANDARD.encode(hash.as_ref()))
+        }
+        _ => id.to_string(),
+    };
+    Ok(Acl::new(permission(perms).ok_or_else(invalid)?, scheme, id))
+}
+
+const PERMISSIONS: [(char, Permission); 5] = [
+    ('c', Permission::CREATE),
+    ('d', Permission::DELETE),
+    ('r', Permission::READ),
+    ('w', Permission::WRITE),
+    ('a', Permission::ADMIN),
+];
+
+/// Permissions of letters of `cdrwa`, or `all`, as zkCli.sh takes them.
+fn permission(s: &str) -> Option<Permission> {
+    if s == "all" {
+        return Some(Permission::ALL);
+    }
+    s.chars().try_fold(Permission::NONE, |perms, c| {
+        let (_, p) = PERMISSIONS.iter().find(|(l, _)| *l == c)?;
+        Some(perms | *p)
+    })
+}
+
+fn letters(perms: Permission) -> String {
+    PERMISSIONS
+        .iter()
+        .filter(|(_, p)| perms.can(*p))
+        .map(|(l, _)| l)
+        .collect()
+}
+
+fn client_permission(perms: Permission) -> zookeeper_client::Permission {
+    use zookeeper_client::Permission as P;
+    [
+        (Permission::READ, P::READ),
+        (Permission::WRITE, P::WRITE),
+        (Permission::CREATE, P::CREATE),
+        (Permission::DELETE, P::DELETE),
+        (Permission::ADMIN, P::ADMIN),
+    ]
+    .into_iter()
+    .filter(|(p, _)| perms.can(*p))
+    .fold(P::NONE, |all, (_, p)| all | p)
+}
@@ -0,0 +1,103 @@
+use crate::error::BenchError;
+use crate::output::{flatten, Report};
+use std::fmt;
+use std::str::FromStr;
+
+/// Comparisons an assertion can make, longest first so `<=` isn't read as `<`
+const CMPS: [&str; 4] = ["<=", ">=", "<", ">"];
+
+/// A condition like `p99<20ms` or `tps>5000` the results must meet. The metric is the name of a
+/// result field, which all fields of that name have to satisfy, a dotted path like
+/// `write.latency.p99`, or `tps`/`qps` for the write and read throughput of standard mode.
+/// Durations compare as seconds.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    metric: String,
+    cmp: &'static str,
+    value: f64,
+}
+
+impl FromStr for Assertion {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad assertion `{}`", s));
+        let (at, cmp) = CMPS
+            .iter()
+            .filter_map(|c| s.find(c).map(|at| (at, *c)))
+            .min_by_key(|(at, c)| (*at, usize::MAX - c.len()))
+            .ok_or_else(invalid)?;
+        let metric = s[..at].trim();
+        let value = s[at + cmp.len()..].trim();
+        let value = match value.parse::<f64>() {
+            Ok(v) => v,
+            Err(_) => humantime::parse_duration(value)
+                .map_err(|_| invalid())?
+                .as_secs_f64(),
+        };
+        if metric.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Assertion {
+            metric: metric.to_string(),
+            cmp,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.metric, self.cmp, self.value)
+    }
+}
+
+impl Assertion {
+    fn path(&self) -> &str {
+        match self.metric.as_str() {
+            "tps" => "write.throughput",
+            "qps" => "read.throughput",
+            m => m,
+        }
+    }
+
+    fn holds(&self, v: f64) -> bool {
+        match self.cmp {
+            "<=" => v <= self.value,
+            ">=" => v >= self.value,
+            "<" => v < self.value,
+            _ => v > self.value,
+        }
+    }
+}
+
+/// Logs every assertion's outcome, failing if any was violated or matched no result field.
+pub fn check(assertions: &[Assertion], report: &Report) -> Result<(), anyhow::Error> {
+    let mut columns = Vec::new();
+    flatten("", &serde_json::to_value(report.result)?, &mut columns);
+    let mut violated = 0;
+    for a in assertions {
+        let path = a.path();
+        let values: Vec<(&str, f64)> = columns
+            .iter()
+            .filter(|(k, _)| k == path || k.ends_with(&format!(".{}", path)))
+            .filter_map(|(k, v)| v.parse().ok().map(|v| (k.as_str(), v)))
+            .collect();
+        if values.is_empty() {
+            violated += 1;
+            log::error!("Assertion {} matches no result", a);
+        }
+        for (column, v) in values {
+            if a.holds(v) {
+                log::info!("Assertion {} holds, {}: {}", a, column, v);
+            } else {
+                violated += 1;
+                log::error!("Assertion {} violated, {}: {}", a, column, v);
+            }
+        }
+    }
+    if violated > 0 {
+        return Err(BenchError::AssertionFailed(violated).into());
+    }
+    Ok(())
+}
@@ -1,57 +1,1428 @@
+pub mod backend;
+pub mod builder;
+pub mod cas;
+pub mod chaos;
+pub mod concurrency;
+pub mod consistency;
+pub mod delete;
+pub mod election;
+pub mod fanout;
+pub mod gc;
+pub mod hot;
+pub mod lock;
+pub mod mixed;
+pub mod pipeline;
+pub mod plan;
+pub mod populate;
+pub mod probe;
+pub mod queue;
+pub mod readonly;
+pub mod scenario;
+pub mod script;
+pub mod session;
+pub mod sizes;
+pub mod sweep;
+pub mod workload;
+
+use crate::acl::NodeAcl;
+use crate::bench::backend::Backend;
+use crate::bench::chaos::{Chaos, ChaosStats, Kills};
+use crate::bench::scenario::Scenario;
+use crate::cluster::Cluster;
+use crate::dist::{KeyChooser, ReadDist, ReadOrder, ValueSize};
 use crate::error::BenchError;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use rand::RngCore;
+use crate::events::{EventFormat, Events};
+use crate::metrics::{self, IntervalReporter, Metrics, StatsdSink};
+use crate::payload::{self, PayloadFiles, PayloadPattern};
+use crate::proxy::{self, Handshakes};
+use crate::rng;
+use crate::sasl::Sasl;
+use crate::server::{self, PhaseStats, ServerStats};
+pub use crate::stats::Bucket;
+use crate::stats::{
+    serialize_opt_secs, serialize_secs, ErrorCounts, Latencies, LatencyLog, LatencySummary,
+    Timeline,
+};
+use crate::tls;
+use crate::trace::Tracer;
+use crate::tui::Dashboard;
+use crate::wire::Traffic;
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rand::Rng;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use signal_hook::consts::SIGINT;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
-use zookeeper::{Acl, CreateMode, WatchedEvent, ZkError, ZooKeeper, ZooKeeperExt};
+use workload::{Operation, Workload};
+use zookeeper::{Acl, CreateMode, Stat, WatchedEvent, ZkError, ZkResult, ZooKeeper, ZooKeeperExt};
+
+use crate::cli::{self, RunArgs};
 
-use crate::Cli;
+/// Interval of the reports of `--forever` runs that don't set `--report-interval`
+const SOAK_REPORT_INTERVAL: Duration = Duration::from_secs(60);
 
 struct LoggingWatcher;
 
 impl zookeeper::Watcher for LoggingWatcher {
+    // The client tells state listeners, rather than the watcher, about disconnects and
+    // expiries, see `backend::BlockingSession`
     fn handle(&self, event: WatchedEvent) {
         log::info!("Watcher receive new event: {:?}", event);
-        // TODO: handle session expired and disconnect event
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Create znodes, then read them back
+    Standard,
+    /// Create znodes, as the write phase of the standard mode
+    Write,
+    /// Read the znodes below --read-prefix or the prefix, as the read phase of the standard mode
+    Read,
+    /// One writer updates a znode watched by many sessions
+    WatchFanout,
+    /// Threads contend on distributed locks
+    Lock,
+    /// Candidates repeatedly take over and resign leadership
+    Election,
+    /// Producers and consumers share a distributed queue
+    Queue,
+    /// Threads do versioned read-modify-write on a few shared znodes
+    Cas,
+    /// Threads repeatedly establish and close sessions
+    SessionChurn,
+    /// Populate, then run a random mix of get/set/create/delete
+    Mixed,
+    /// Grow one parent in steps, timing create and getChildren per step
+    ChildSweep,
+    /// Create and read increasingly large payloads until they fail
+    PayloadProbe,
+    /// Build a tree and time deleting it recursively
+    DeleteRecursive,
+    /// All threads read and write the same znode
+    HotZnode,
+    /// Repeat the standard benchmark for each of --threads and find where throughput levels off
+    ConcurrencySweep,
+    /// Run the operations a --script picks
+    Script,
+    /// One writer updates a znode that readers on other sessions poll, with and without sync,
+    /// until they see each update
+    ReadAfterWrite,
+    /// Read existing znodes over sessions that may be read-only, counting failed reads, to
+    /// measure read availability while the ensemble has no quorum
+    ReadOnly,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct BenchOption {
-    hosts: String,
-    timeout: Duration,
+    pub mode: Mode,
+    pub hosts: String,
+    /// Name of the ensemble, if it was given one
+    pub cluster: Option<String>,
+    /// Hosts the client connects to: one for each member under `--pin-sessions` and each identity
+    /// of `--tls-cert-dir`, the local ends of the tunnels under `--tls`
+    #[serde(skip)]
+    connect_strings: Arc<Vec<String>>,
+    /// Members each connect string reaches, in the order sessions try them, only one if they're
+    /// pinned to it
+    #[serde(skip)]
+    members: Arc<Vec<Vec<String>>>,
+    /// Sessions connected so far, which take turns at the connect strings
+    #[serde(skip)]
+    connected: Arc<AtomicUsize>,
+    target_server: Option<String>,
+    pin_sessions: bool,
+    can_be_read_only: bool,
+    #[serde(skip)]
+    handshakes: Option<Arc<Handshakes>>,
+    tls: bool,
+    #[serde(serialize_with = "serialize_secs")]
+    pub session_timeout: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub connect_timeout: Duration,
+    #[serde(serialize_with = "serialize_opt_secs")]
+    op_timeout: Option<Duration>,
     iteration: u32,
-    threads: u32,
+    pub threads: u32,
+    write_threads: Option<u32>,
+    read_threads: Option<u32>,
+    write_iteration: Option<u32>,
+    read_iteration: Option<u32>,
+    /// Thread counts concurrency-sweep mode runs with, left empty in the other modes
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    thread_counts: Vec<u32>,
     ephemeral: bool,
+    no_cleanup: bool,
+    /// Whether creates of znodes that exist are skipped, continuing an earlier run
+    resume: bool,
+    /// Whether the prefix is left as the run leaves it, rather than deleted
+    pub keep_data: bool,
+    node_size: ValueSize,
+    payload_pattern: PayloadPattern,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    node_size_sweep: Vec<usize>,
+    scenario: Option<Scenario>,
+    script: Option<PathBuf>,
+    payload_file: Option<PathBuf>,
+    /// Whether values are written with a checksum and reads check it
+    verify: bool,
+    #[serde(skip)]
+    payload_files: Option<Arc<PayloadFiles>>,
+    /// Bytes of the largest size, which values are cut from
+    #[serde(skip)]
     node_value: Vec<u8>,
-    prefix: String,
+    pub prefix: String,
     node_path_template: String,
+    /// Index of the first znode created, past those earlier phases of a scenario created
+    #[serde(skip)]
+    index_base: u32,
+    // Holds credentials, so it's never written out
+    #[serde(skip)]
     digest: Option<String>,
+    /// Without the password, like the ACL
+    sasl: Option<Sasl>,
+    acl: NodeAcl,
+    watchers: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    late_threshold: Duration,
+    locks: u32,
+    groups: u32,
+    consumers: u32,
+    keys: u32,
+    watch: bool,
+    mix: mixed::Mix,
+    workload: Option<mixed::Workload>,
+    sweep_step: u32,
+    probe_sizes: Vec<usize>,
+    tree_depth: u32,
+    tree_width: u32,
+    hot_writes: u32,
+    hgrm_out: Option<PathBuf>,
+    max_error_rate: f64,
+    rate: Option<f64>,
+    warmup: Option<Warmup>,
+    /// Zero under `--forever`
+    #[serde(serialize_with = "serialize_opt_secs")]
+    duration: Option<Duration>,
+    #[serde(serialize_with = "serialize_opt_secs")]
+    ramp_up: Option<Duration>,
+    #[serde(serialize_with = "serialize_opt_secs")]
+    max_runtime: Option<Duration>,
+    think_time: Option<ThinkTime>,
+    read_dist: ReadDist,
+    read_order: ReadOrder,
+    read_prefix: Option<String>,
+    tree: Option<Tree>,
+    shards: Option<u32>,
+    seed: Option<u64>,
+    #[serde(serialize_with = "serialize_opt_secs")]
+    slow_op_threshold: Option<Duration>,
+    exclude_connect: bool,
+    connections: Option<u32>,
+    backend: Backend,
+    retry: Option<Retry>,
+    chaos: Option<Chaos>,
+    /// Operations `--retry` retried, by the error of the attempt before
+    #[serde(skip)]
+    retries: Arc<Mutex<ErrorCounts>>,
+    /// Requests each worker keeps outstanding through the async client, if it does
+    in_flight: Option<u32>,
+    /// Depths a standard run is repeated with, left empty unless `--in-flight` lists several
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    in_flight_sweep: Vec<u32>,
+    #[serde(skip)]
+    pool: Arc<Pool>,
+    #[serde(skip)]
+    quiet: bool,
+    /// Set once Ctrl-C ends the running phase of a `--forever` run
+    #[serde(skip)]
+    stop: Arc<AtomicBool>,
+    /// Set once SIGINT or SIGTERM ends the whole run, or `--max-runtime` does
+    #[serde(skip)]
+    interrupted: Arc<AtomicBool>,
+    #[serde(skip)]
+    timed_out: Arc<AtomicBool>,
+    /// Values read that `--verify` found weren't what was written, over the whole run
+    #[serde(skip)]
+    mismatches: Arc<AtomicU64>,
+    #[serde(skip)]
+    latency_log: Option<Arc<LatencyLog>>,
+    #[serde(skip)]
+    prometheus_listen: Option<SocketAddr>,
+    #[serde(skip)]
+    metrics: Option<Arc<Metrics>>,
+    #[serde(skip)]
+    statsd: Option<Arc<StatsdSink>>,
+    #[serde(skip)]
+    tracer: Option<Arc<Tracer>>,
+    #[serde(skip)]
+    reporter: Option<Arc<IntervalReporter>>,
+    #[serde(skip)]
+    dashboard: Option<Arc<Dashboard>>,
+    /// Establishment latency of every session created so far
+    #[serde(skip)]
+    sessions: Arc<Mutex<Latencies>>,
+    /// How long workers were without a session each time theirs ended, until a new one was up
+    #[serde(skip)]
+    outages: Arc<Mutex<Latencies>>,
+    #[serde(skip)]
+    kills: Arc<Kills>,
+    #[serde(skip)]
+    server_stats: Option<Arc<ServerStats>>,
+    #[serde(skip)]
+    schedule: Arc<Schedule>,
+    #[serde(skip)]
+    start_line: Arc<StartLine>,
+    #[serde(skip)]
+    pub events: Option<Arc<Events>>,
+    /// `--tag` labels, which the results carry in their metadata
+    #[serde(skip)]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// How long each worker warms up before its operations count, as a duration or an operation count
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Warmup {
+    #[serde(serialize_with = "serialize_secs")]
+    Duration(Duration),
+    Iterations(u32),
+}
+
+impl FromStr for Warmup {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(n) = s.parse() {
+            return Ok(Warmup::Iterations(n));
+        }
+        humantime::parse_duration(s)
+            .map(Warmup::Duration)
+            .map_err(|_| BenchError::InvalidOption(format!("bad warmup `{}`", s)))
+    }
+}
+
+/// Pause of `--think-time` between a worker's operations: fixed, uniformly random within a
+/// range, or exponentially distributed around a mean.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThinkTime {
+    Fixed(Duration),
+    Uniform(Duration, Duration),
+    Exponential(Duration),
+}
+
+impl FromStr for ThinkTime {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad think time `{}`", s));
+        let parse = |d: &str| humantime::parse_duration(d.trim()).map_err(|_| invalid());
+        match s.split_once(':') {
+            None => Ok(ThinkTime::Fixed(parse(s)?)),
+            Some(("uniform", range)) => {
+                let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+                let (min, max) = (parse(min)?, parse(max)?);
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(ThinkTime::Uniform(min, max))
+            }
+            Some(("exponential", mean)) => Ok(ThinkTime::Exponential(parse(mean)?)),
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ThinkTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = humantime::format_duration;
+        match self {
+            ThinkTime::Fixed(t) => write!(f, "{}", d(*t)),
+            ThinkTime::Uniform(min, max) => write!(f, "uniform:{}..{}", d(*min), d(*max)),
+            ThinkTime::Exponential(mean) => write!(f, "exponential:{}", d(*mean)),
+        }
+    }
+}
+
+impl Serialize for ThinkTime {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl ThinkTime {
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            ThinkTime::Fixed(t) => t,
+            ThinkTime::Uniform(min, max) => rng.gen_range(min..=max),
+            ThinkTime::Exponential(mean) => mean.mul_f64(-(1.0 - rng.gen::<f64>()).ln()),
+        }
+    }
+}
+
+/// Wait between the attempts of `--retry`: fixed, or doubling from the first attempt's up to a
+/// ceiling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backoff {
+    Fixed(Duration),
+    Exponential(Duration, Duration),
+}
+
+impl FromStr for Backoff {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad backoff `{}`", s));
+        let parse = |d: &str| humantime::parse_duration(d.trim()).map_err(|_| invalid());
+        match s.split_once(':') {
+            None => Ok(Backoff::Fixed(parse(s)?)),
+            Some(("exp", range)) => {
+                let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+                let (min, max) = (parse(min)?, parse(max)?);
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(Backoff::Exponential(min, max))
+            }
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Backoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = humantime::format_duration;
+        match self {
+            Backoff::Fixed(t) => write!(f, "{}", d(*t)),
+            Backoff::Exponential(min, max) => write!(f, "exp:{}..{}", d(*min), d(*max)),
+        }
+    }
+}
+
+impl Backoff {
+    /// Wait before retry `attempt`, counting from 0.
+    fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(t) => t,
+            Backoff::Exponential(min, max) => min.saturating_mul(1 << attempt.min(31)).min(max),
+        }
+    }
+}
+
+/// Policy of `--retry`: how often an operation failing with a transient error is retried, and
+/// how long to wait in between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Retry {
+    max: u32,
+    backoff: Backoff,
+}
+
+impl FromStr for Retry {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad retry policy `{}`", s));
+        let mut retry = Retry {
+            max: 3,
+            backoff: Backoff::Exponential(Duration::from_millis(10), Duration::from_secs(1)),
+        };
+        for field in s.split(',') {
+            let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+            match key.trim() {
+                "max" => retry.max = value.trim().parse().map_err(|_| invalid())?,
+                "backoff" => retry.backoff = value.trim().parse()?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(retry)
+    }
+}
+
+impl fmt::Display for Retry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max={},backoff={}", self.max, self.backoff)
+    }
+}
+
+impl Serialize for Retry {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl Retry {
+    /// Whether `e` is worth another attempt, as the request may not have reached the server or
+    /// its answer got lost on the way back.
+    fn covers(e: &ZkError) -> bool {
+        matches!(e, ZkError::ConnectionLoss | ZkError::OperationTimeout)
+    }
+}
+
+/// Layout of `--tree`: znodes spread over every level of a tree of parents this deep, each of
+/// which has this many children that are parents too.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tree {
+    depth: u32,
+    fanout: u32,
+}
+
+impl FromStr for Tree {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad tree `{}`", s));
+        let (mut depth, mut fanout) = (None, None);
+        for field in s.split(',') {
+            let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+            let value: u32 = value.trim().parse().map_err(|_| invalid())?;
+            match key.trim() {
+                "depth" => depth = Some(value),
+                "fanout" => fanout = Some(value),
+                _ => return Err(invalid()),
+            }
+        }
+        match (depth, fanout) {
+            (Some(depth), Some(fanout)) if depth > 0 && fanout > 0 => Ok(Tree { depth, fanout }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "depth={},fanout={}", self.depth, self.fanout)
+    }
+}
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl Tree {
+    /// Level of the parent znode `index` goes under, 0 being the prefix itself.
+    fn level(&self, index: u32) -> u32 {
+        index % self.depth
+    }
+
+    /// Path below the prefix of parent `slot` of `level`, its digits in base fanout naming the
+    /// parent to take on each level down.
+    fn parent(&self, level: u32, mut slot: u64) -> String {
+        let mut digits = Vec::with_capacity(level as usize);
+        for _ in 0..level {
+            digits.push(slot % self.fanout as u64);
+            slot /= self.fanout as u64;
+        }
+        digits.iter().rev().map(|d| format!("/d{}", d)).collect()
+    }
+}
+
+/// Path of the znode with `index` the standard mode creates and reads, spread over the levels of
+/// `--tree` or under the parent of its worker's shard if given.
+fn node_path(opt: &BenchOption, index: u32) -> String {
+    if let Some(shards) = opt.shards {
+        let shard = node_owner(opt, index) % shards;
+        return format!("{}/shard-{}/test-node{}", opt.prefix, shard, index);
+    }
+    let Some(tree) = &opt.tree else {
+        return opt.node_path_template.clone() + index.to_string().as_str();
+    };
+    let level = tree.level(index);
+    let slots = (tree.fanout as u64).pow(level);
+    let parent = tree.parent(level, (index / tree.depth) as u64 % slots);
+    format!("{}{}/test-node{}", opt.prefix, parent, index)
+}
+
+/// Pauses a worker for `--think-time` after an operation, if one was given.
+fn think(opt: &BenchOption) {
+    if let Some(t) = &opt.think_time {
+        thread::sleep(t.sample(&mut rng::rng()));
+    }
+}
+
+impl TryFrom<RunArgs> for BenchOption {
+    type Error = anyhow::Error;
+
+    fn try_from(c: RunArgs) -> Result<Self, Self::Error> {
+        let mut clusters = c.connect.clusters()?;
+        if clusters.len() != 1 {
+            return Err(BenchError::InvalidOption(format!(
+                "a run goes against one ensemble at a time, not {}",
+                clusters.len()
+            ))
+            .into());
+        }
+        let Cluster {
+            name: cluster,
+            hosts,
+        } = clusters.remove(0);
+        let (mode, mix, read_dist) = match c.mixed.workload {
+            Some(w) => (Mode::Mixed, w.mix(), w.read_dist()),
+            None => (c.mode.mode, c.mixed.mix, c.keys.read_dist),
+        };
+        let thread_counts = match mode {
+            Mode::ConcurrencySweep => c.load.threads.clone(),
+            _ if c.load.threads.len() > 1 => {
+                return Err(BenchError::InvalidOption(
+                    "only concurrency-sweep mode takes more than one --threads".to_string(),
+                )
+                .into());
+            }
+            _ => Vec::new(),
+        };
+        let per_phase = [
+            c.phases.write_threads,
+            c.phases.read_threads,
+            c.phases.write_iteration,
+            c.phases.read_iteration,
+        ];
+        if per_phase.iter().any(Option::is_some)
+            && (mode != Mode::Standard || c.phases.scenario.is_some())
+        {
+            return Err(BenchError::InvalidOption(
+                "only standard mode without --scenario takes --write-threads, --read-threads, \
+                 --write-iteration and --read-iteration"
+                    .to_string(),
+            )
+            .into());
+        }
+        let pooled = [
+            Mode::Standard,
+            Mode::Write,
+            Mode::Read,
+            Mode::Mixed,
+            Mode::HotZnode,
+            Mode::ConcurrencySweep,
+            Mode::Script,
+            Mode::ReadOnly,
+        ];
+        let connections = match c.load.shared_session {
+            true => Some(1),
+            false => c.load.connections,
+        };
+        if connections.is_some() && !pooled.contains(&mode) {
+            let name = mode.to_possible_value().unwrap();
+            return Err(BenchError::InvalidOption(format!(
+                "{} mode doesn't take --connections or --shared-session",
+                name.get_name()
+            ))
+            .into());
+        }
+        let phased = matches!(mode, Mode::Standard | Mode::Write | Mode::Read);
+        if !c.load.in_flight.is_empty() && !phased {
+            return Err(BenchError::InvalidOption(
+                "only standard, write and read modes take --in-flight".to_string(),
+            )
+            .into());
+        }
+        if c.load.in_flight.len() > 1 && mode != Mode::Standard {
+            return Err(BenchError::InvalidOption(
+                "only standard mode takes more than one --in-flight".to_string(),
+            )
+            .into());
+        }
+        if c.load.backend != Backend::Zookeeper && !(phased || mode == Mode::ReadOnly) {
+            return Err(BenchError::InvalidOption(
+                "only standard, write, read and read-only modes take --backend".to_string(),
+            )
+            .into());
+        }
+        if c.load.op_timeout.is_some() && !(phased || mode == Mode::ReadOnly) {
+            return Err(BenchError::InvalidOption(
+                "only standard, write, read and read-only modes take --op-timeout".to_string(),
+            )
+            .into());
+        }
+        if c.load.chaos.is_some() && !(phased || mode == Mode::ReadOnly) {
+            return Err(BenchError::InvalidOption(
+                "only standard, write, read and read-only modes take --chaos".to_string(),
+            )
+            .into());
+        }
+        let (in_flight, in_flight_sweep) = match c.load.in_flight.as_slice() {
+            [depth] => (Some(*depth), Vec::new()),
+            _ => (None, c.load.in_flight.clone()),
+        };
+        if !c.phases.node_size_sweep.is_empty() && mode != Mode::Standard {
+            return Err(BenchError::InvalidOption(
+                "only standard mode takes --node-size-sweep".to_string(),
+            )
+            .into());
+        }
+        if c.reads.read_order != ReadOrder::Sequential && read_dist != ReadDist::Sequential {
+            return Err(BenchError::InvalidOption(
+                "--read-order only applies to sequential --read-dist".to_string(),
+            )
+            .into());
+        }
+        let scenario = match &c.phases.scenario {
+            Some(_) if mode != Mode::Standard => {
+                return Err(BenchError::InvalidOption(
+                    "only standard mode takes --scenario".to_string(),
+                )
+                .into());
+            }
+            Some(path) => Some(Scenario::load(path)?),
+            None => None,
+        };
+        if c.connect.sasl.is_some() && c.load.backend != Backend::ZookeeperClient {
+            return Err(BenchError::InvalidOption(
+                "--sasl needs --backend zookeeper-client, the zookeeper crate has no SASL handshake"
+                    .to_string(),
+            )
+            .into());
+        }
+        if c.connect.acl.needs_auth() && c.connect.digest.is_none() {
+            return Err(BenchError::InvalidOption(format!(
+                "--acl {} grants the sessions' user, which needs --digest",
+                c.connect.acl
+            ))
+            .into());
+        }
+        if let Some(chroot) = server::chroot(&hosts) {
+            if chroot.ends_with('/') || chroot.contains("//") {
+                return Err(BenchError::InvalidOption(format!("bad chroot `{}`", chroot)).into());
+            }
+        }
+        // What write mode creates is for later runs to read, and the read modes create nothing,
+        // while without cleaning up first the prefix holds more than the run created
+        let prefix = match c.cleanup.unique_prefix {
+            true => gc::unique(&c.connect.prefix),
+            false => c.connect.prefix.clone(),
+        };
+        let no_cleanup = c.cleanup.no_cleanup || c.cleanup.resume;
+        let keep_data = c.cleanup.keep_data
+            || no_cleanup
+            || matches!(mode, Mode::Write | Mode::Read | Mode::ReadOnly);
+        let chroot = server::chroot(&hosts).unwrap_or_default();
+        // Each with the members its sessions reach
+        let ensembles: Vec<(String, Vec<String>)> =
+            match (&c.connect.target_server, c.connect.pin_sessions) {
+                (Some(target), _) => server::servers(target)
+                    .into_iter()
+                    .take(1)
+                    .map(|server| (server.clone() + chroot, vec![server]))
+                    .collect(),
+                (None, true) => server::servers(&hosts)
+                    .into_iter()
+                    .map(|server| (server.clone() + chroot, vec![server]))
+                    .collect(),
+                (None, false) => vec![(hosts.clone(), server::servers(&hosts))],
+            };
+        let (mut connect_strings, members) = match &c.connect.tls_ca {
+            Some(ca) if c.connect.tls => {
+                let identities = match &c.connect.tls_cert_dir {
+                    Some(dir) => {
+                        let identities = tls::identities(dir)?;
+                        log::info!(
+                            "Sessions take turns at {} client certificates from {}",
+                            identities.len(),
+                            dir.display()
+                        );
+                        identities
+                            .into_iter()
+                            .map(|(cert, key)| (Some(cert), Some(key)))
+                            .collect()
+                    }
+                    None => vec![(c.connect.tls_cert.clone(), c.connect.tls_key.clone())],
+                };
+                log::info!("Tunnelling to {} over TLS", hosts);
+                let (mut connect_strings, mut members) = (Vec::new(), Vec::new());
+                for (cert, key) in &identities {
+                    let config = tls::config(ca, cert.as_deref(), key.as_deref())?;
+                    for (hosts, member) in &ensembles {
+                        connect_strings.push(tls::tunnel(hosts, config.clone())?);
+                        members.push(member.clone());
+                    }
+                }
+                (connect_strings, members)
+            }
+            _ => ensembles.into_iter().unzip(),
+        };
+        let can_be_read_only = c.connect.can_be_read_only || mode == Mode::ReadOnly;
+        let handshakes = can_be_read_only.then(|| Arc::new(Handshakes::default()));
+        if let Some(handshakes) = &handshakes {
+            connect_strings = connect_strings
+                .iter()
+                .map(|hosts| proxy::read_only(hosts, handshakes.clone()))
+                .collect::<Result<_, _>>()?;
+        }
+        let payload_files = match &c.values.payload_file {
+            Some(path) => Some(Arc::new(PayloadFiles::load(path)?)),
+            None => None,
+        };
+        let latency_log = match &c.monitor.latency_log {
+            Some(path) => Some(Arc::new(LatencyLog::create(path)?)),
+            None => None,
+        };
+        let mut tags = BTreeMap::new();
+        for (key, value) in &c.output.tags {
+            cli::check_tag_key(key).map_err(BenchError::InvalidOption)?;
+            if tags.insert(key.clone(), value.clone()).is_some() {
+                return Err(BenchError::InvalidOption(format!("--tag {} given twice", key)).into());
+            }
+        }
+        let metrics = c
+            .monitor
+            .prometheus_listen
+            .map(|_| Arc::new(Metrics::new(&tags)));
+        let statsd = match &c.monitor.statsd {
+            Some(addr) => Some(StatsdSink::connect(addr, &c.monitor.statsd_prefix)?),
+            None => None,
+        };
+        let tracer = c
+            .monitor
+            .otlp_endpoint
+            .as_ref()
+            .map(|endpoint| Tracer::new(endpoint, c.monitor.trace_sample_rate));
+        let connect_timeout = c
+            .connect
+            .connect_timeout
+            .unwrap_or(c.connect.session_timeout);
+        let server_stats = c
+            .output
+            .server_stats
+            .then(|| Arc::new(ServerStats::new(&hosts, connect_timeout)));
+        let events = c.output.events.map(|EventFormat::Jsonl| Events::new());
+        let duration = match c.load.forever {
+            true => Some(Duration::ZERO),
+            false => c.load.duration,
+        };
+        let forever = duration.is_some_and(|d| d.is_zero());
+        let report_interval = match forever {
+            true => c.monitor.report_interval.or(Some(SOAK_REPORT_INTERVAL)),
+            false => c.monitor.report_interval,
+        };
+        let reporter = match report_interval {
+            Some(interval) => Some(IntervalReporter::start(
+                interval,
+                c.monitor.report_log.as_deref(),
+                events.clone(),
+            )?),
+            None => None,
+        };
+        Ok(BenchOption {
+            mode,
+            connect_strings: Arc::new(connect_strings),
+            members: Arc::new(members),
+            connected: Arc::new(AtomicUsize::new(0)),
+            target_server: c.connect.target_server,
+            pin_sessions: c.connect.pin_sessions,
+            can_be_read_only,
+            handshakes,
+            tls: c.connect.tls,
+            hosts,
+            cluster,
+            session_timeout: c.connect.session_timeout,
+            connect_timeout,
+            op_timeout: c.load.op_timeout,
+            iteration: c.load.iteration,
+            threads: c.load.threads[0],
+            write_threads: c.phases.write_threads,
+            read_threads: c.phases.read_threads,
+            write_iteration: c.phases.write_iteration,
+            read_iteration: c.phases.read_iteration,
+            thread_counts,
+            ephemeral: c.layout.ephemeral,
+            no_cleanup,
+            resume: c.cleanup.resume,
+            keep_data,
+            node_size: c.values.node_size,
+            payload_pattern: c.values.payload_pattern,
+            node_size_sweep: c.phases.node_size_sweep,
+            scenario,
+            script: c.mode.script,
+            payload_file: c.values.payload_file,
+            verify: c.verify.verify,
+            payload_files,
+            node_value: c.values.payload_pattern.generate(c.values.node_size.max()),
+            node_path_template: format!("{}/test-node", prefix),
+            index_base: 0,
+            prefix,
+            digest: c.connect.digest,
+            sasl: c.connect.sasl,
+            acl: c.connect.acl,
+            watchers: c.fanout.watchers,
+            late_threshold: c.fanout.late_threshold,
+            locks: c.mode.locks,
+            groups: c.mode.groups,
+            consumers: c.mode.consumers,
+            keys: c.mode.keys,
+            watch: c.reads.watch,
+            mix,
+            workload: c.mixed.workload,
+            sweep_step: c.mode.sweep_step,
+            probe_sizes: c.mode.probe_sizes,
+            tree_depth: c.mode.tree_depth,
+            tree_width: c.mode.tree_width,
+            hot_writes: c.mode.hot_writes,
+            hgrm_out: c.output.hgrm_out,
+            max_error_rate: c.load.max_error_rate,
+            rate: c.load.rate,
+            warmup: c.load.warmup,
+            duration,
+            ramp_up: c.load.ramp_up,
+            max_runtime: c.load.max_runtime,
+            think_time: c.load.think_time,
+            read_dist,
+            read_order: c.reads.read_order,
+            read_prefix: c.reads.read_prefix,
+            tree: c.layout.tree,
+            shards: c.layout.shards,
+            seed: c.load.seed,
+            slow_op_threshold: c.monitor.slow_op_threshold,
+            exclude_connect: c.load.exclude_connect,
+            connections,
+            backend: c.load.backend,
+            retry: c.load.retry,
+            chaos: c.load.chaos,
+            retries: Arc::default(),
+            in_flight,
+            in_flight_sweep,
+            pool: Arc::default(),
+            quiet: c.output.quiet,
+            stop: Arc::default(),
+            interrupted: Arc::default(),
+            timed_out: Arc::default(),
+            mismatches: Arc::default(),
+            latency_log,
+            prometheus_listen: c.monitor.prometheus_listen,
+            metrics,
+            statsd,
+            tracer,
+            reporter,
+            dashboard: c.output.tui.then(|| Arc::new(Dashboard::new(mode))),
+            sessions: Arc::default(),
+            outages: Arc::default(),
+            kills: Arc::default(),
+            server_stats,
+            schedule: Arc::default(),
+            start_line: Arc::default(),
+            events,
+            tags,
+        })
+    }
 }
 
-impl From<Cli> for BenchOption {
-    fn from(c: Cli) -> Self {
-        let mut buf = vec![0; c.node_size];
-        rand::thread_rng().fill_bytes(&mut buf);
+impl BenchOption {
+    /// A value to write: the next `--payload-file`, or one of a size drawn from `--node-size`.
+    pub fn value(&self) -> Vec<u8> {
+        if let Some(files) = &self.payload_files {
+            return files.next();
+        }
+        let size = self.node_size.sample(&mut rng::rng());
+        self.node_value[..size].to_vec()
+    }
+
+    /// A value to write to `path`, which `--verify` begins with a checksum.
+    fn value_for(&self, path: &str) -> Vec<u8> {
+        match self.verify {
+            true => payload::seal(path, self.value()),
+            false => self.value(),
+        }
+    }
+
+    /// Checks `data` read from `path` for `--verify`, which worker `tid` counts among its
+    /// `mismatches` unless it's what was written there, warning of the first.
+    fn check_read(&self, tid: u32, path: &str, data: &[u8], mismatches: &mut Option<u64>) {
+        if !self.verify {
+            return;
+        }
+        let seen = mismatches.get_or_insert(0);
+        if payload::verify(path, data) {
+            return;
+        }
+        if *seen == 0 {
+            log::warn!(
+                "Worker #{} read {} bytes from {} that aren't what was written",
+                tid,
+                data.len(),
+                path
+            );
+        }
+        *seen += 1;
+        self.mismatches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Values read that `--verify` found weren't what was written, over the whole run.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Bytes a value takes on average, of `--payload-file` or `--node-size`.
+    fn mean_value_size(&self) -> f64 {
+        match &self.payload_files {
+            Some(files) => files.mean_len(),
+            None => self.node_size.mean(),
+        }
+    }
+
+    /// What signals stop of this run, for whoever catches them.
+    pub fn interrupter(&self) -> Interrupter {
+        Interrupter {
+            interrupted: self.interrupted.clone(),
+            stop: self.stop.clone(),
+            forever: self.duration == Some(Duration::ZERO),
+        }
+    }
+
+    /// The ACL of the znodes others are created below, which the run's sessions can go on
+    /// creating and deleting below whatever `--acl` is.
+    pub(crate) fn parent_acl(&self) -> Vec<Acl> {
+        self.acl.parent_acl(self.digest.is_some())
+    }
+
+    /// Whether a signal or `--max-runtime` interrupted the run, whose results then are partial.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Stops the run as a signal would once `--max-runtime` is up, unless the returned deadline
+    /// was dropped by then. A run that still hasn't ended a session timeout later, like one
+    /// stuck on an unresponsive ensemble, is quit.
+    pub fn start_deadline(&self) -> Option<Deadline> {
+        let max_runtime = self.max_runtime?;
+        let (interrupted, timed_out) = (self.interrupted.clone(), self.timed_out.clone());
+        let grace = self.session_timeout;
+        let (tx, rx) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            if rx.recv_timeout(max_runtime) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+            log::warn!(
+                "Stopping the run, which reached its --max-runtime of {:?}",
+                max_runtime
+            );
+            timed_out.store(true, Ordering::Relaxed);
+            interrupted.store(true, Ordering::Relaxed);
+            if rx.recv_timeout(grace) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+            log::error!("The run didn't stop within {:?}, quitting", grace);
+            process::exit(BenchError::TimedOut(max_runtime).exit_code().into());
+        });
+        Some(Deadline { _end: tx })
+    }
+
+    /// Whether `--max-runtime` ended the run.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    pub fn max_runtime(&self) -> Option<Duration> {
+        self.max_runtime
+    }
+
+    /// The `--seed` workers draw their random numbers from, which the thread running the
+    /// workload draws from too once it's passed to [`rng::reseed`].
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Serves the run's metrics on `--prometheus-listen`, if given, until the process exits.
+    /// Options made later for the same address take the endpoint over rather than binding it
+    /// again.
+    pub fn serve_metrics(&self) -> Result<(), anyhow::Error> {
+        if let (Some(addr), Some(metrics)) = (self.prometheus_listen, &self.metrics) {
+            metrics::serve(addr, metrics.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the `--latency-log` file, pending StatsD metrics and spans, if enabled.
+    pub fn flush_outputs(&self) -> Result<(), anyhow::Error> {
+        if let Some(statsd) = &self.statsd {
+            statsd.flush();
+        }
+        if let Some(tracer) = &self.tracer {
+            tracer.flush();
+        }
+        if let Some(log) = &self.latency_log {
+            log.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Where worker progress bars go: the terminal, nowhere with `--quiet`, or the `--tui`
+    /// dashboard which draws them itself.
+    fn progress(&self) -> MultiProgress {
+        if self.quiet || self.dashboard.is_some() {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        }
+    }
+
+    fn show_workers(&self, workers: Vec<ProgressBar>) {
+        if let Some(d) = &self.dashboard {
+            d.set_workers(workers);
+        }
+    }
+
+    /// Waits for the other workers of the phase to connect and warm up, so that all of them
+    /// start measuring at full concurrency rather than the first ones against an idle ensemble.
+    /// Under `--ramp-up` workers start one after another on purpose, so they don't wait. Returns
+    /// when the worker starts measuring: now if it would have after connecting or warming up,
+    /// else `worker_start`, so that its elapsed time includes its connect as before.
+    fn wait_for_start(&self, tid: u32, worker_start: Instant) -> Instant {
+        if self.ramp_up.is_some() {
+            return worker_start;
+        }
+        self.start_line.wait(tid);
+        match self.exclude_connect || self.warmup.is_some() {
+            true => Instant::now(),
+            false => worker_start,
+        }
+    }
+
+    /// Server counters around each phase, if `--server-stats` captured them.
+    pub fn server_phases(&self) -> Vec<PhaseStats> {
+        self.server_stats
+            .as_ref()
+            .map_or_else(Vec::new, |s| s.take())
+    }
+
+    /// Options of a phase of the standard mode run by `threads` workers and for `iteration`
+    /// operations, where given instead of `--threads` and `--iteration`.
+    fn for_phase(&self, threads: Option<u32>, iteration: Option<u32>) -> BenchOption {
         BenchOption {
-            hosts: c.hosts,
-            timeout: c.timeout,
-            iteration: c.iteration,
-            threads: c.threads,
-            ephemeral: c.ephemeral,
-            node_value: buf,
-            node_path_template: format!("{}/test-node", c.prefix.clone()),
-            prefix: c.prefix,
-            digest: c.digest,
+            threads: threads.unwrap_or(self.threads),
+            iteration: iteration.unwrap_or(self.iteration),
+            ..self.clone()
+        }
+    }
+
+    /// Runs a phase, snapshotting the servers around it for `--server-stats` and marking its
+    /// start and end for `--events`.
+    fn phase<R>(
+        &self,
+        name: &str,
+        f: impl FnOnce() -> Result<R, anyhow::Error>,
+    ) -> Result<R, anyhow::Error> {
+        self.emit("phase_start", json!({ "phase": name }));
+        let start = Instant::now();
+        let result = match &self.server_stats {
+            Some(s) => s.around(name, f),
+            None => f(),
+        };
+        self.emit(
+            "phase_end",
+            json!({
+                "phase": name,
+                "duration": start.elapsed().as_secs_f64(),
+                "ok": result.is_ok(),
+            }),
+        );
+        result
+    }
+
+    fn emit(&self, kind: &str, fields: serde_json::Value) {
+        if let Some(events) = &self.events {
+            events.emit(kind, fields);
         }
     }
+
+    /// Operations `--retry` retried over the whole run, by error, if it was given.
+    pub fn retry_stats(&self) -> Option<ErrorCounts> {
+        self.retry.as_ref()?;
+        Some(self.retries.lock().unwrap().clone())
+    }
+
+    /// Summarizes the sessions the run created and saves them as `sessions.hgrm`.
+    pub fn session_stats(&self) -> Result<SessionStats, anyhow::Error> {
+        let sessions = self.sessions.lock().unwrap();
+        save_hgrm(self, "sessions", &sessions)?;
+        let outages = self.outages.lock().unwrap();
+        Ok(SessionStats {
+            latency: sessions.summary(),
+            histogram: sessions.buckets(),
+            outages: outages.summary(),
+            chaos: self.chaos.as_ref().map(|_| self.kills.stats()),
+        })
+    }
 }
 
-pub struct BenchResult {
+/// Establishment latency of all sessions of a run, whichever the mode.
+#[derive(Serialize)]
+pub struct SessionStats {
+    pub latency: LatencySummary,
+    pub histogram: Vec<Bucket>,
+    /// Time workers spent without a session, once for each that ended and was re-established,
+    /// counting none if none did
+    pub outages: LatencySummary,
+    /// Sessions `--chaos` killed, which don't count among the outages, with `--chaos` even if it
+    /// killed none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chaos: Option<ChaosStats>,
+}
+
+#[derive(Serialize)]
+pub struct PhaseResult {
+    pub ops: u32,
+    #[serde(serialize_with = "serialize_secs")]
     pub elapsed: Duration,
-    pub tps: f32,
-    pub qps: f32,
+    /// Operations per second
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    /// Latency since each operation's intended start under `--rate`, which unlike `latency`
+    /// includes the time it queued behind slower ones
+    pub corrected_latency: Option<LatencySummary>,
+    pub histogram: Vec<Bucket>,
+    /// Operations completed in each second of the phase
+    pub timeline: Vec<u64>,
+    /// Estimated bytes sent and received by all workers
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Bytes per second
+    pub send_bandwidth: f64,
+    pub receive_bandwidth: f64,
+    pub workers: Vec<WorkerResult>,
+    pub errors: ErrorCounts,
+    /// Latency by the levels below the prefix of the znodes, with `--tree`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub depths: BTreeMap<u32, LatencySummary>,
+    /// Results by the member sessions landed on
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub servers: BTreeMap<String, ServerResult>,
+    /// Values read that weren't what was written, if the phase read any with `--verify`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mismatches: Option<u64>,
+    /// Creates `--resume` skipped, of znodes that existed, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing: Option<u64>,
+}
+
+/// What the workers whose sessions landed on one member measured.
+#[derive(Serialize)]
+pub struct ServerResult {
+    pub workers: u32,
+    pub ops: u32,
+    /// Operations per second over the whole phase
+    pub throughput: f64,
+    pub latency: LatencySummary,
+}
+
+#[derive(Serialize)]
+pub struct WorkerResult {
+    /// Member the worker's session landed on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    pub ops: u32,
+    /// Until the worker's session was usable
+    #[serde(serialize_with = "serialize_secs")]
+    pub connect: Duration,
+    /// From the worker's start, including its connect unless `--exclude-connect`, or the end of
+    /// its warmup until its last operation
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    /// Znodes `--resume` found it had no need to create
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing: Option<u32>,
+}
+
+/// What each worker of a standard phase measured.
+struct PhaseWorker {
+    connect: Duration,
+    member: Option<String>,
+    latencies: Latencies,
+    corrected: Latencies,
+    timeline: Timeline,
+    window: Window,
+    traffic: Traffic,
+    errors: ErrorCounts,
+    depths: BTreeMap<u32, Latencies>,
+    /// Values read that `--verify` found weren't what was written, once it checked any
+    mismatches: Option<u64>,
+    /// Creates `--resume` skipped, of znodes that existed
+    existing: u32,
+}
+
+impl PhaseResult {
+    /// Aggregates the workers of a phase and saves its histogram as `<phase>.hgrm`.
+    fn new(
+        opt: &BenchOption,
+        phase: &str,
+        workers: Vec<PhaseWorker>,
+    ) -> Result<Self, anyhow::Error> {
+        let elapsed = Window::span(workers.iter().map(|w| w.window));
+        let mut latencies = Latencies::default();
+        let mut corrected = Latencies::default();
+        let mut timelines = Vec::new();
+        let mut results = Vec::new();
+        let mut errors = ErrorCounts::default();
+        let mut traffic = Traffic::default();
+        let mut depths: BTreeMap<u32, Latencies> = BTreeMap::new();
+        let mut servers: BTreeMap<String, (u32, Latencies)> = BTreeMap::new();
+        let mut mismatches = None;
+        let mut existing = 0;
+        for w in workers {
+            existing += w.existing as u64;
+            mismatches = add_mismatches(mismatches, w.mismatches);
+            latencies.merge(&w.latencies);
+            if let Some(member) = &w.member {
+                let (workers, l) = servers.entry(member.clone()).or_default();
+                *workers += 1;
+                l.merge(&w.latencies);
+            }
+            for (depth, l) in &w.depths {
+                depths.entry(*depth).or_default().merge(l);
+            }
+            traffic.merge(&w.traffic);
+            corrected.merge(&w.corrected);
+            errors.merge(&w.errors);
+            let latency = w.latencies.summary();
+            let worker_elapsed = w.window.end - w.window.start;
+            results.push(WorkerResult {
+                server: w.member,
+                ops: latency.count as u32,
+                connect: w.connect,
+                elapsed: worker_elapsed,
+                throughput: latency.count as f64 / worker_elapsed.as_secs_f64(),
+                latency,
+                existing: (w.existing > 0).then_some(w.existing),
+            });
+            timelines.push(w.timeline);
+        }
+        save_hgrm(opt, phase, &latencies)?;
+        let corrected_latency = match opt.rate {
+            Some(_) => {
+                save_hgrm(opt, &format!("{}-corrected", phase), &corrected)?;
+                Some(corrected.summary())
+            }
+            None => None,
+        };
+
+        let latency = latencies.summary();
+        Ok(PhaseResult {
+            ops: latency.count as u32,
+            elapsed,
+            throughput: latency.count as f64 / elapsed.as_secs_f64(),
+            latency,
+            corrected_latency,
+            histogram: latencies.buckets(),
+            timeline: Timeline::merge(&timelines),
+            bytes_sent: traffic.sent,
+            bytes_received: traffic.received,
+            send_bandwidth: traffic.sent as f64 / elapsed.as_secs_f64(),
+            receive_bandwidth: traffic.received as f64 / elapsed.as_secs_f64(),
+            workers: results,
+            errors,
+            depths: depths.iter().map(|(d, l)| (*d, l.summary())).collect(),
+            servers: servers
+                .into_iter()
+                .map(|(server, (workers, l))| {
+                    let latency = l.summary();
+                    let result = ServerResult {
+                        workers,
+                        ops: latency.count as u32,
+                        throughput: latency.count as f64 / elapsed.as_secs_f64(),
+                        latency,
+                    };
+                    (server, result)
+                })
+                .collect(),
+            mismatches,
+            existing: (existing > 0).then_some(existing),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct BenchResult {
+    pub write: PhaseResult,
+    pub read: PhaseResult,
+}
+
+/// Result of whichever workload `--mode` selected.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum RunResult {
+    Standard(Box<BenchResult>),
+    Write(Box<PhaseResult>),
+    Read(Box<PhaseResult>),
+    WatchFanout(fanout::FanoutResult),
+    Lock(lock::LockResult),
+    Election(election::ElectionResult),
+    Queue(queue::QueueResult),
+    Cas(cas::CasResult),
+    SessionChurn(session::SessionResult),
+    Mixed(mixed::MixedResult),
+    ChildSweep(sweep::SweepResult),
+    PayloadProbe(probe::ProbeResult),
+    DeleteRecursive(delete::DeleteResult),
+    HotZnode(hot::HotResult),
+    ConcurrencySweep(concurrency::ConcurrencyResult),
+    SizeSweep(sizes::SizeSweepResult),
+    Pipeline(pipeline::PipelineResult),
+    Scenario(scenario::ScenarioResult),
+    Script(mixed::MixedResult),
+    ReadOnly(readonly::ReadOnlyResult),
+    ReadAfterWrite(consistency::ConsistencyResult),
+}
+
+/// Runs the workload selected by `opt.mode`, on the `--tui` dashboard if enabled. What it
+/// creates below the prefix stays there for `cleanup` to delete.
+pub fn run(opt: &BenchOption) -> Result<RunResult, anyhow::Error> {
+    let _screen = match &opt.dashboard {
+        Some(d) => Some(d.show()?),
+        None => None,
+    };
+    // Standard mode snapshots its write and read phase each, the others the whole workload
+    if let (Mode::Standard, Some(scenario)) = (opt.mode, &opt.scenario) {
+        return Ok(RunResult::Scenario(scenario::bench(opt, scenario)?));
+    }
+    if opt.mode == Mode::Standard && !opt.node_size_sweep.is_empty() {
+        return Ok(RunResult::SizeSweep(sizes::bench(opt)?));
+    }
+    if opt.mode == Mode::Standard && !opt.in_flight_sweep.is_empty() {
+        return Ok(RunResult::Pipeline(pipeline::bench(opt)?));
+    }
+    if matches!(opt.mode, Mode::Standard | Mode::Write | Mode::Read) {
+        return run_mode(opt);
+    }
+    let name = opt.mode.to_possible_value().unwrap().get_name().to_string();
+    opt.phase(&name, || run_mode(opt))
+}
+
+fn run_mode(opt: &BenchOption) -> Result<RunResult, anyhow::Error> {
+    Ok(match opt.mode {
+        Mode::Standard => RunResult::Standard(Box::new(bench(opt)?)),
+        Mode::Write => RunResult::Write(Box::new(write(opt)?)),
+        Mode::Read => RunResult::Read(Box::new(read(opt)?)),
+        Mode::WatchFanout => RunResult::WatchFanout(fanout::bench(opt)?),
+        Mode::Lock => RunResult::Lock(lock::bench(opt)?),
+        Mode::Election => RunResult::Election(election::bench(opt)?),
+        Mode::Queue => RunResult::Queue(queue::bench(opt)?),
+        Mode::Cas => RunResult::Cas(cas::bench(opt)?),
+        Mode::SessionChurn => RunResult::SessionChurn(session::bench(opt)?),
+        Mode::Mixed => RunResult::Mixed(mixed::bench(opt)?),
+        Mode::ChildSweep => RunResult::ChildSweep(sweep::bench(opt)?),
+        Mode::PayloadProbe => RunResult::PayloadProbe(probe::bench(opt)?),
+        Mode::DeleteRecursive => RunResult::DeleteRecursive(delete::bench(opt)?),
+        Mode::HotZnode => RunResult::HotZnode(hot::bench(opt)?),
+        Mode::ConcurrencySweep => RunResult::ConcurrencySweep(concurrency::bench(opt)?),
+        Mode::Script => RunResult::Script(script::bench(opt)?),
+        Mode::ReadOnly => RunResult::ReadOnly(readonly::bench(opt)?),
+        Mode::ReadAfterWrite => RunResult::ReadAfterWrite(consistency::bench(opt)?),
+    })
 }
 
 fn new_progress_style() -> ProgressStyle {
@@ -60,25 +1431,611 @@ fn new_progress_style() -> ProgressStyle {
         .progress_chars("##-")
 }
 
-fn skip_last<T>(mut iter: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
-    let last = iter.next();
-    iter.scan(last, |state, item| std::mem::replace(state, Some(item)))
+/// How often the bar of all workers catches up with theirs
+const OVERALL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bar above those of the workers adding up their progress, with the throughput over the last
+/// second and, unless they run for a duration, the time left; with dozens of workers theirs
+/// don't tell how the run is going.
+struct Overall {
+    pb: ProgressBar,
+    workers: Vec<ProgressBar>,
+    done: AtomicBool,
 }
 
-fn prepare(opt: &BenchOption) -> Result<(), anyhow::Error> {
-    let zk = ZooKeeper::connect(opt.hosts.as_str(), opt.timeout, LoggingWatcher)?;
+impl Overall {
+    /// A bar on top of `multi` for the `workers`, out of `len` operations if they run that many.
+    fn new(multi: &MultiProgress, workers: &[ProgressBar], len: Option<u64>) -> Self {
+        let pb = match len {
+            Some(len) => multi.insert(0, ProgressBar::new(len)),
+            None => multi.insert(0, ProgressBar::new_spinner()),
+        };
+        let template = match len {
+            Some(_) => "[{elapsed_precise}] {bar:40.green/white} {pos:>7}/{len:7} ETA {eta} {msg}",
+            None => "[{elapsed_precise}] {spinner:.green} {pos:>7} ops {msg}",
+        };
+        pb.set_style(
+            ProgressStyle::with_template(template)
+                .unwrap()
+                .progress_chars("##-"),
+        );
+        pb.set_message("All workers");
+        Overall {
+            pb,
+            workers: workers.to_vec(),
+            done: AtomicBool::new(false),
+        }
+    }
 
-    match &opt.digest {
-        Some(d) => {
-            zk.add_auth("digest", d.to_string().into_bytes())?;
+    /// Keeps the bar up to date until `finish`, on a thread of its own.
+    fn follow(&self) {
+        let mut samples = VecDeque::from([(Instant::now(), 0)]);
+        while !self.done.load(Ordering::Relaxed) {
+            thread::sleep(OVERALL_INTERVAL);
+            let now = Instant::now();
+            let pos = self.position();
+            samples.push_back((now, pos));
+            while now - samples[1].0 >= Duration::from_secs(1) {
+                samples.pop_front();
+            }
+            let (then, before) = samples[0];
+            let rate = (pos - before) as f64 / (now - then).as_secs_f64();
+            self.pb.set_position(pos);
+            self.pb
+                .set_message(format!("All workers, {:.0} ops/s", rate));
+        }
+        self.pb.set_position(self.position());
+        self.pb.finish_with_message("All workers finish");
+    }
+
+    fn position(&self) -> u64 {
+        self.workers.iter().map(ProgressBar::position).sum()
+    }
+
+    fn finish(&self) {
+        self.done.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Writes `<phase>.hgrm` into the `--hgrm-out` directory, if one was given.
+fn save_hgrm(opt: &BenchOption, phase: &str, latencies: &Latencies) -> Result<(), anyhow::Error> {
+    if let Some(dir) = &opt.hgrm_out {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.hgrm", phase));
+        latencies.write_hgrm(&path)?;
+        log::info!("Histogram saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Mismatches of two workers, or of whichever checked reads.
+fn add_mismatches(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        _ => a.or(b),
+    }
+}
+
+/// What an operation returned that identifies the znode state it saw, for `--slow-op-threshold`.
+/// The client doesn't expose the zxid of replies, so the last modifying zxid has to do.
+trait Mzxid {
+    fn mzxid(&self) -> Option<i64> {
+        None
+    }
+}
+
+impl Mzxid for () {}
+impl Mzxid for String {}
+impl Mzxid for ZooKeeper {}
+
+impl Mzxid for backend::Reply {
+    fn mzxid(&self) -> Option<i64> {
+        self.mzxid
+    }
+}
+
+impl Mzxid for Option<i64> {
+    fn mzxid(&self) -> Option<i64> {
+        *self
+    }
+}
+
+impl Mzxid for Stat {
+    fn mzxid(&self) -> Option<i64> {
+        Some(self.mzxid)
+    }
+}
+
+impl Mzxid for (Vec<u8>, Stat) {
+    fn mzxid(&self) -> Option<i64> {
+        Some(self.1.mzxid)
+    }
+}
+
+/// Feeds a finished operation to the slow operation log, `--latency-log` file, interval reports,
+/// dashboard, event stream, Prometheus, StatsD and OTLP, if enabled.
+fn observe_op<T: Mzxid>(
+    opt: &BenchOption,
+    op: &'static str,
+    path: &str,
+    start: Instant,
+    latency: Duration,
+    result: &ZkResult<T>,
+) -> Result<(), anyhow::Error> {
+    let err = result.as_ref().err();
+    if opt.slow_op_threshold.is_some_and(|t| latency > t) {
+        let outcome = match result {
+            Ok(r) => match r.mzxid() {
+                Some(zxid) => format!("mzxid: {:#x}", zxid),
+                None => "OK".to_string(),
+            },
+            Err(e) => format!("{:?}", e),
+        };
+        log::warn!("Slow {} {} took {:?}, {}", op, path, latency, outcome);
+    }
+    if let Some(m) = &opt.metrics {
+        m.observe(op, latency, err);
+    }
+    if let Some(statsd) = &opt.statsd {
+        statsd.observe(op, latency, err);
+    }
+    if let Some(reporter) = &opt.reporter {
+        reporter.observe(op, latency, err);
+    }
+    if let Some(d) = &opt.dashboard {
+        d.observe(op, latency, err);
+    }
+    if let Some(e) = err {
+        opt.emit(
+            "error",
+            json!({ "op": op, "path": path, "error": format!("{:?}", e) }),
+        );
+    }
+    if let Some(tracer) = &opt.tracer {
+        tracer.observe(op, path, start, latency, err);
+    }
+    if let Some(log) = &opt.latency_log {
+        log.write(op, path, start, latency, err)?;
+    }
+    Ok(())
+}
+
+/// When a worker's measured operations started and ended.
+#[derive(Clone, Copy)]
+struct Window {
+    start: Instant,
+    end: Instant,
+}
+
+impl Window {
+    /// From the earliest start to the latest end, which is the phase's elapsed time.
+    fn span(windows: impl Iterator<Item = Window>) -> Duration {
+        let mut span: Option<Window> = None;
+        for w in windows {
+            span = Some(match span {
+                Some(s) => Window {
+                    start: s.start.min(w.start),
+                    end: s.end.max(w.end),
+                },
+                None => w,
+            });
+        }
+        span.map_or(Duration::ZERO, |s| s.end - s.start)
+    }
+}
+
+/// Runs the `--warmup` share of operations, which count nowhere, and returns when measuring
+/// starts. Errors still fail the worker, as they would right after.
+fn warm_up<T>(
+    opt: &BenchOption,
+    mut op: impl FnMut(u32) -> ZkResult<T>,
+) -> Result<Instant, anyhow::Error> {
+    match opt.warmup {
+        Some(Warmup::Iterations(n)) => {
+            for i in 0..n {
+                op(i)?;
+            }
+        }
+        Some(Warmup::Duration(d)) => {
+            let end = Instant::now() + d;
+            let mut i = 0;
+            while Instant::now() < end {
+                op(i)?;
+                i += 1;
+            }
         }
         None => {}
     }
+    Ok(Instant::now())
+}
 
-    match zk.delete_recursive(opt.prefix.as_str()) {
-        Ok(_) => {}
-        Err(e) if e == ZkError::NoNode => {}
-        Err(e) => return Err(e.into()),
+/// A session with how long it took to connect and the member it landed on, if it's known
+type PooledSession = (Arc<ZooKeeper>, Duration, Option<String>);
+
+/// Sessions of `--connections` with how long each took to connect, while a phase runs.
+#[derive(Default)]
+struct Pool(Mutex<Vec<PooledSession>>);
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sessions = self.0.lock().unwrap().len();
+        f.debug_struct("Pool").field("sessions", &sessions).finish()
+    }
+}
+
+impl Pool {
+    /// Replaces the sessions, closing the previous ones once no worker holds them any more.
+    fn fill(&self, sessions: Vec<PooledSession>) {
+        *self.0.lock().unwrap() = sessions;
+    }
+
+    /// The next session, workers taking turns at them.
+    fn take(&self) -> PooledSession {
+        let mut sessions = self.0.lock().unwrap();
+        let next = sessions[0].clone();
+        sessions.rotate_left(1);
+        next
+    }
+}
+
+/// The `--rate` token bucket shared by the workers of a phase: it hands out intended start
+/// times 1/rate apart, each to whichever worker asks first, so a stalled worker doesn't hold
+/// back the offered load.
+#[derive(Debug, Default)]
+struct Schedule {
+    // Unset until the first operation of a phase, so warmups and gaps between phases don't
+    // pile up turns
+    next: Mutex<Option<Instant>>,
+}
+
+impl Schedule {
+    fn reset(&self) {
+        *self.next.lock().unwrap() = None;
+    }
+
+    fn take(&self, interval: Duration) -> Instant {
+        let mut next = self.next.lock().unwrap();
+        let intended = next.unwrap_or_else(Instant::now);
+        *next = Some(intended + interval);
+        intended
+    }
+}
+
+/// Where the workers of a phase wait for each other before they start measuring. A worker
+/// counts as arrived once it exits too, so one that failed to connect doesn't hold up the rest.
+#[derive(Debug, Default)]
+struct StartLine {
+    arrived: Mutex<Vec<bool>>,
+    all: Condvar,
+}
+
+impl StartLine {
+    fn reset(&self, workers: u32) {
+        *self.arrived.lock().unwrap() = vec![false; workers as usize];
+    }
+
+    /// Counts worker `tid` as arrived, without waiting for the others.
+    fn arrive(&self, tid: u32) {
+        drop(self.mark(tid));
+    }
+
+    fn wait(&self, tid: u32) {
+        let arrived = self.mark(tid);
+        drop(
+            self.all
+                .wait_while(arrived, |a| !a.iter().all(|&a| a))
+                .unwrap(),
+        );
+    }
+
+    fn mark(&self, tid: u32) -> MutexGuard<'_, Vec<bool>> {
+        let mut arrived = self.arrived.lock().unwrap();
+        arrived[tid as usize] = true;
+        if arrived.iter().all(|&a| a) {
+            self.all.notify_all();
+        }
+        arrived
+    }
+}
+
+/// Spaces a worker's operations to the turns it takes from the `--rate` schedule. Without a
+/// rate, every operation is intended to start right away.
+struct Pacer<'a> {
+    interval: Option<Duration>,
+    schedule: &'a Schedule,
+}
+
+impl<'a> Pacer<'a> {
+    fn new(opt: &'a BenchOption) -> Self {
+        Pacer {
+            interval: opt.rate.map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            schedule: &opt.schedule,
+        }
+    }
+
+    /// Waits for the next operation's turn and returns when it was intended to start. Workers
+    /// that fall behind catch up without skipping turns, so the delay shows in corrected
+    /// latencies the way it would for clients of a real service.
+    fn wait(&mut self) -> Instant {
+        let Some(interval) = self.interval else {
+            return Instant::now();
+        };
+        let intended = self.schedule.take(interval);
+        if let Some(early) = intended.checked_duration_since(Instant::now()) {
+            thread::sleep(early);
+        }
+        intended
+    }
+}
+
+/// Stops a run on SIGINT or SIGTERM.
+#[derive(Clone)]
+pub struct Interrupter {
+    interrupted: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    forever: bool,
+}
+
+impl Interrupter {
+    /// Stops the workers at their next operation, so that the run ends with what it measured
+    /// until then and later phases run none, or under `--forever` Ctrl-C ends just the running
+    /// phase. Returns whether `signal` had done so already and the phase still hasn't ended, for
+    /// the caller to quit then.
+    pub fn interrupt(&self, signal: i32) -> bool {
+        let flag = match self.forever && signal == SIGINT {
+            true => &self.stop,
+            false => &self.interrupted,
+        };
+        flag.swap(true, Ordering::Relaxed)
+    }
+}
+
+/// Holds off `--max-runtime` until dropped, once the run ended.
+pub struct Deadline {
+    _end: mpsc::Sender<()>,
+}
+
+/// How many operations a worker runs: its share of `--iteration`, or as many as fit into
+/// `--duration` from when it's created, or under `--forever` as many as it runs until stopped.
+#[derive(Clone, Copy)]
+struct Budget<'a> {
+    count: u32,
+    timed: bool,
+    end: Option<Instant>,
+    stop: &'a AtomicBool,
+    interrupted: &'a AtomicBool,
+}
+
+impl<'a> Budget<'a> {
+    fn new(opt: &'a BenchOption, tid: u32) -> Self {
+        Budget {
+            count: share(opt.iteration, opt.threads, tid).len() as u32,
+            timed: opt.duration.is_some(),
+            end: opt
+                .duration
+                .filter(|d| !d.is_zero())
+                .map(|d| Instant::now() + d),
+            stop: &opt.stop,
+            interrupted: &opt.interrupted,
+        }
+    }
+
+    /// Whether the worker goes on after `done` operations.
+    fn allows(&self, done: u32) -> bool {
+        if self.stop.load(Ordering::Relaxed) || self.interrupted.load(Ordering::Relaxed) {
+            return false;
+        }
+        match (self.timed, self.end) {
+            (true, Some(end)) => Instant::now() < end,
+            (true, None) => true,
+            (false, _) => done < self.count,
+        }
+    }
+
+    /// Operations `--max-error-rate` is relative to, which under `--duration` are those run so far.
+    fn planned(&self, done: u32) -> u32 {
+        match self.timed {
+            true => done + 1,
+            false => self.count,
+        }
+    }
+}
+
+/// Worker `i`'s contiguous range of `total` things split between `workers`: equal shares, with
+/// the first `total % workers` workers taking one more, so that none are left over.
+fn share(total: u32, workers: u32, i: u32) -> Range<u32> {
+    let (size, rest) = (total / workers, total % workers);
+    let start = i * size + i.min(rest);
+    start..start + size + (i < rest) as u32
+}
+
+/// Index of the `n`th znode worker `tid` creates. Each worker owns a contiguous range of its
+/// share of `--iteration`, but under `--duration` there's no telling how many it will create,
+/// so workers take turns instead.
+fn node_index(opt: &BenchOption, tid: u32, n: u32) -> u32 {
+    opt.index_base
+        + match opt.duration {
+            Some(_) => n * opt.threads + tid,
+            None => share(opt.iteration, opt.threads, tid).start + n,
+        }
+}
+
+/// Worker that creates the znode with `index`, undoing `node_index`.
+fn node_owner(opt: &BenchOption, index: u32) -> u32 {
+    let index = index - opt.index_base;
+    let (size, rest) = (opt.iteration / opt.threads, opt.iteration % opt.threads);
+    // Indexes of the workers with one more znode than the others
+    let longer = rest * (size + 1);
+    match opt.duration {
+        Some(_) => index % opt.threads,
+        None if index < longer => index / (size + 1),
+        None => rest + (index - longer) / size.max(1),
+    }
+}
+
+/// Runs `op` again under `--retry` while it fails with a transient error and retries are left,
+/// waiting out the backoff in between. Each retry is counted by the error that caused it.
+fn with_retry<T>(opt: &BenchOption, mut op: impl FnMut() -> ZkResult<T>) -> ZkResult<T> {
+    let Some(retry) = &opt.retry else {
+        return op();
+    };
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < retry.max && Retry::covers(&e) => {
+                opt.retries.lock().unwrap().record(&e);
+                thread::sleep(retry.backoff.delay(attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Counts a failed operation, failing the worker once more than `--max-error-rate` of the
+/// `planned` operations failed. The default rate of zero fails on the first error.
+fn tolerate(
+    opt: &BenchOption,
+    errors: &mut ErrorCounts,
+    e: ZkError,
+    planned: u32,
+) -> Result<(), anyhow::Error> {
+    errors.record(&e);
+    if errors.total() as f64 > opt.max_error_rate * planned as f64 {
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn merge_latencies(workers: &[Latencies]) -> Latencies {
+    let mut latencies = Latencies::default();
+    workers.iter().for_each(|l| latencies.merge(l));
+    latencies
+}
+
+fn skip_last<T>(mut iter: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
+    let last = iter.next();
+    iter.scan(last, |state, item| state.replace(item))
+}
+
+/// Creates the chroot of the connect string, which sessions connecting through it can't.
+fn create_chroot(opt: &BenchOption) -> Result<(), anyhow::Error> {
+    let hosts = &opt.connect_strings[0];
+    let chroot = server::chroot(hosts).unwrap_or_default();
+    log::info!("Creating chroot {}", chroot);
+    let zk = ZooKeeper::connect(
+        &hosts[..hosts.len() - chroot.len()],
+        opt.session_timeout,
+        LoggingWatcher,
+    )?;
+    if let Some(d) = &opt.digest {
+        zk.add_auth("digest", d.to_string().into_bytes())?;
+    }
+    zk.ensure_path(chroot).map_err(|e| {
+        BenchError::InvalidOption(format!(
+            "chroot {} doesn't exist and can't be created, {:?}",
+            chroot, e
+        ))
+    })?;
+    Ok(())
+}
+
+pub fn connect(opt: &BenchOption) -> Result<ZooKeeper, anyhow::Error> {
+    Ok(connect_timed(opt)?.0)
+}
+
+type WorkerSession = (Arc<ZooKeeper>, Instant, Duration, Option<String>);
+
+/// Connects a worker, returning when its measured operations start along with how long its
+/// session took to become usable and the member it landed on. Operations start at the connect
+/// unless `--exclude-connect` leaves it out. Under `--connections` the worker takes the next
+/// session of the phase's pool, which connected before any worker started.
+fn connect_worker(opt: &BenchOption) -> Result<WorkerSession, anyhow::Error> {
+    if opt.connections.is_some() {
+        let (zk, elapsed, member) = opt.pool.take();
+        return Ok((zk, Instant::now(), elapsed, member));
+    }
+    let start = Instant::now();
+    let (zk, elapsed, turn) = connect_timed(opt)?;
+    let zk = Arc::new(zk);
+    let member = landing(opt, turn);
+    let worker_start = if opt.exclude_connect {
+        Instant::now()
+    } else {
+        start
+    };
+    Ok((zk, worker_start, elapsed, member))
+}
+
+/// Connects and waits until the session is usable, recording how long that took among the
+/// run's sessions. The client only reports session state to listeners which may subscribe too
+/// late, so the time until a first request completes is used instead, which includes one extra
+/// round trip. Returns which of the connect strings it took along with it, for [`landing`].
+fn connect_timed(opt: &BenchOption) -> Result<(ZooKeeper, Duration, usize), anyhow::Error> {
+    let start = Instant::now();
+    let turn = next_turn(opt);
+    let hosts = &opt.connect_strings[turn];
+    let result = ZooKeeper::connect(hosts.as_str(), opt.session_timeout, LoggingWatcher)
+        .and_then(|zk| usable(opt, zk));
+    let elapsed = start.elapsed();
+    observe_op(opt, "connect", &opt.hosts, start, elapsed, &result)?;
+    let zk = result.map_err(|e| BenchError::ConnectFailed(opt.hosts.clone(), e))?;
+    opt.sessions.lock().unwrap().record(elapsed);
+    if let Some(d) = &opt.digest {
+        zk.add_auth("digest", d.to_string().into_bytes())?;
+    }
+    Ok((zk, elapsed, turn))
+}
+
+/// The member a session that connected through connect string `turn` landed on. Neither client
+/// tells, but both try the members in the order of the connect string when they first connect,
+/// so it's the first of them a connection reaches.
+fn landing(opt: &BenchOption, turn: usize) -> Option<String> {
+    match &opt.members[turn][..] {
+        [member] => Some(member.clone()),
+        members => members
+            .iter()
+            .find(|m| server::reachable(m, opt.connect_timeout))
+            .cloned(),
+    }
+}
+
+/// `zk` once a first request went through. The client keeps reconnecting for as long as the
+/// session timeout, so under a shorter `--connect-timeout` the request is left to a thread of
+/// its own, which closes the session whenever it's done.
+fn usable(opt: &BenchOption, zk: ZooKeeper) -> ZkResult<ZooKeeper> {
+    if opt.connect_timeout >= opt.session_timeout {
+        return zk.exists("/", false).map(|_| zk);
+    }
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(zk.exists("/", false).map(|_| zk));
+    });
+    rx.recv_timeout(opt.connect_timeout)
+        .unwrap_or(Err(ZkError::OperationTimeout))
+}
+
+/// Which of the connect strings the next session connects with, sessions taking turns at them.
+fn next_turn(opt: &BenchOption) -> usize {
+    opt.connected.fetch_add(1, Ordering::Relaxed) % opt.connect_strings.len()
+}
+
+/// Empties the prefix, unless `--no-cleanup`, and creates the parents znodes go under.
+fn prepare(opt: &BenchOption) -> Result<(), anyhow::Error> {
+    let zk = connect(opt)?;
+    // The root always exists, unless it's a chroot nobody created yet
+    if zk.exists("/", false)?.is_none() {
+        create_chroot(opt)?;
+    }
+
+    if opt.no_cleanup {
+        log::info!("Leaving what's below {} in place", opt.prefix);
+    } else {
+        match zk.delete_recursive(opt.prefix.as_str()) {
+            Ok(_) => {}
+            Err(ZkError::NoNode) => {}
+            Err(e) => return Err(e.into()),
+        }
     }
 
     let mut s = String::new();
@@ -89,51 +2046,108 @@ fn prepare(opt: &BenchOption) -> Result<(), anyhow::Error> {
 
         s.push('/');
         s.push_str(p);
+        create_parent(opt, &zk, &s)?;
+    }
 
-        match zk.create(
-            s.as_str(),
-            Vec::new(),
-            Acl::open_unsafe().clone(),
-            CreateMode::Persistent,
-        ) {
-            Ok(_) => {}
-            Err(e) if e == ZkError::NodeExists => {}
-            Err(e) => return Err(e.into()),
+    for shard in 0..opt.shards.unwrap_or(0) {
+        create_parent(opt, &zk, &format!("{}/shard-{}", opt.prefix, shard))?;
+    }
+    if let Some(tree) = &opt.tree {
+        log::info!("Creating the parents of {}", tree);
+        for level in 1..tree.depth {
+            for slot in 0..(tree.fanout as u64).pow(level) {
+                create_parent(
+                    opt,
+                    &zk,
+                    &format!("{}{}", opt.prefix, tree.parent(level, slot)),
+                )?;
+            }
         }
     }
     Ok(())
 }
 
-fn do_bench<T>(opt: &BenchOption, bench_fn: T) -> Result<Duration, anyhow::Error>
+/// Creates an empty persistent znode, unless an earlier run left it behind.
+fn create_parent(opt: &BenchOption, zk: &ZooKeeper, path: &str) -> Result<(), anyhow::Error> {
+    match zk.create(path, Vec::new(), opt.parent_acl(), CreateMode::Persistent) {
+        Ok(_) => Ok(()),
+        Err(ZkError::NodeExists) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn do_bench<T, R>(opt: &BenchOption, bench_fn: T) -> Result<(Duration, Vec<R>), anyhow::Error>
 where
-    T: Fn(u32, ProgressBar, &BenchOption) -> Result<(), anyhow::Error> + Send + Sync + Copy,
+    T: Fn(u32, ProgressBar, &BenchOption) -> Result<R, anyhow::Error> + Send + Sync + Copy,
+    R: Send,
 {
-    let bar = MultiProgress::new();
+    opt.schedule.reset();
+    opt.start_line.reset(opt.threads);
+    opt.stop.store(false, Ordering::Relaxed);
+    if let Some(connections) = opt.connections {
+        let mut pool = Vec::new();
+        for _ in 0..connections {
+            let (zk, elapsed, turn) = connect_timed(opt)?;
+            pool.push((Arc::new(zk), elapsed, landing(opt, turn)));
+        }
+        opt.pool.fill(pool);
+    }
+    let bar = opt.progress();
+    let bars: Vec<ProgressBar> = (0..opt.threads)
+        .map(|tid| {
+            let pb = match opt.duration {
+                Some(_) => bar.add(ProgressBar::new_spinner()),
+                None => bar.add(ProgressBar::new(
+                    share(opt.iteration, opt.threads, tid).len() as u64,
+                )),
+            };
+            pb.set_style(new_progress_style());
+            pb.set_message(format!("Worker #{}", tid));
+            pb
+        })
+        .collect();
+    let len = match opt.duration {
+        Some(_) => None,
+        None => Some(opt.iteration as u64),
+    };
+    let overall = Overall::new(&bar, &bars, len);
+    opt.show_workers(bars.clone());
     let start = Instant::now();
-    let mut is_err = false;
+    let mut failed = None;
+    let mut results = Vec::new();
     thread::scope(|s| {
+        s.spawn(|| overall.follow());
         let mut threads = Vec::new();
-        for tid in 0..opt.threads {
-            let pb = bar.add(ProgressBar::new((opt.iteration / opt.threads) as u64));
-            pb.set_style(new_progress_style());
-            pb.set_message(format!("Worker #{}", tid));
-            threads.push(s.spawn(move || bench_fn(tid, pb, opt)));
+        for (tid, pb) in (0..opt.threads).zip(bars) {
+            threads.push(s.spawn(move || {
+                rng::reseed(opt.seed, u64::from(tid) + 1);
+                if let Some(ramp_up) = opt.ramp_up {
+                    // Workers start evenly spread over the ramp-up, the first right away
+                    pb.set_message("Waiting for ramp-up");
+                    thread::sleep(ramp_up * tid / opt.threads);
+                }
+                let result = bench_fn(tid, pb, opt);
+                opt.start_line.arrive(tid);
+                result
+            }));
         }
         for t in threads {
             match t.join().unwrap() {
-                Ok(_) => {}
+                Ok(r) => results.push(r),
                 Err(e) => {
-                    is_err = true;
                     log::error!("Worker exit, {}", e);
+                    opt.emit("worker_failed", json!({ "error": e.to_string() }));
+                    failed.get_or_insert(e);
                 }
             }
         }
+        overall.finish();
     });
     let elapsed = start.elapsed();
-    if is_err {
-        Err(BenchError::BenchFailed().into())
-    } else {
-        Ok(elapsed)
+    opt.pool.fill(Vec::new());
+    match failed {
+        Some(e) => Err(BenchError::BenchFailed(e).into()),
+        None => Ok((elapsed, results)),
     }
 }
 
@@ -142,54 +2156,260 @@ pub fn bench(opt: &BenchOption) -> Result<BenchResult, anyhow::Error> {
     prepare(opt)?;
 
     log::info!("Running TPS benchmark");
-    let elapsed = do_bench(opt, do_tps_bench)?;
-    let tps = opt.iteration as f32 / elapsed.as_secs_f32();
+    let write_opt = opt.for_phase(opt.write_threads, opt.write_iteration);
+    let write = workload::run(&write_opt, "write", &Writes)?;
+
+    log::info!("Running QPS benchmark");
+    let read_opt = opt.for_phase(opt.read_threads, opt.read_iteration);
+    let read = match &opt.read_prefix {
+        Some(prefix) => read_below(&read_opt, prefix)?,
+        None => {
+            // Znodes `--resume` found are there to read as much as those it created
+            let created = write
+                .workers
+                .iter()
+                .map(|w| w.ops + w.existing.unwrap_or(0))
+                .collect();
+            let keys = ReadKeys::new(&write_opt, created, read_opt.threads);
+            let reads = Reads {
+                keys: &keys,
+                written: &write_opt,
+            };
+            workload::run(&read_opt, "read", &reads)?
+        }
+    };
+
+    Ok(BenchResult { write, read })
+}
+
+/// The write phase of the standard mode on its own.
+pub fn write(opt: &BenchOption) -> Result<PhaseResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    log::info!("Running TPS benchmark");
+    workload::run(opt, "write", &Writes)
+}
 
+/// The read phase of the standard mode on its own, over the znodes below `--read-prefix` or,
+/// as an earlier write run left them, the prefix.
+pub fn read(opt: &BenchOption) -> Result<PhaseResult, anyhow::Error> {
     log::info!("Running QPS benchmark");
-    let elapsed = do_bench(opt, do_qps_bench)?;
-    let qps = opt.iteration as f32 / elapsed.as_secs_f32();
+    read_below(opt, opt.read_prefix.as_ref().unwrap_or(&opt.prefix))
+}
+
+/// A phase of the standard mode, to run on its own with [`run_phase`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Creates the znodes, by `--write-threads` workers if given
+    Write,
+    /// Reads the znodes below `--read-prefix`, or those a write phase that kept its data left
+    /// below the prefix, by `--read-threads` workers if given
+    Read,
+}
+
+/// Runs a single phase of the standard mode, whatever `opt.mode` is. Like `run`, it leaves the
+/// znodes it created behind, for a later phase to read and `cleanup` to delete.
+pub fn run_phase(opt: &BenchOption, phase: Phase) -> Result<PhaseResult, anyhow::Error> {
+    match phase {
+        Phase::Write => write(&opt.for_phase(opt.write_threads, opt.write_iteration)),
+        Phase::Read => read(&opt.for_phase(opt.read_threads, opt.read_iteration)),
+    }
+}
+
+/// Runs a read phase over the znodes already below `prefix`.
+fn read_below(opt: &BenchOption, prefix: &str) -> Result<PhaseResult, anyhow::Error> {
+    let paths = match connect(opt)?.get_children_recursive(prefix) {
+        Ok(paths) => paths,
+        // Such as after a run that didn't --keep-data
+        Err(ZkError::NoNode) => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // The first is the prefix itself
+    let paths = paths.get(1..).unwrap_or_default();
+    if paths.is_empty() {
+        return Err(BenchError::InvalidOption(format!("no znodes below {}", prefix)).into());
+    }
+    log::info!("Reading {} znodes below {}", paths.len(), prefix);
+    let keys = ReadKeys::first(opt, paths.len() as u32);
+    let reads = ListedReads { keys: &keys, paths };
+    workload::run(opt, "read", &reads)
+}
 
-    Ok(BenchResult { elapsed, tps, qps })
+/// Deletes the prefix and everything below it, returning whether it existed.
+pub fn cleanup(opt: &BenchOption) -> Result<bool, anyhow::Error> {
+    match connect(opt)?.delete_recursive(opt.prefix.as_str()) {
+        Ok(()) => Ok(true),
+        Err(ZkError::NoNode) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
 }
 
-fn do_tps_bench(tid: u32, pb: ProgressBar, opt: &BenchOption) -> Result<(), anyhow::Error> {
-    let zk = ZooKeeper::connect(opt.hosts.as_str(), opt.timeout, LoggingWatcher)?;
-    pb.set_message("Connected");
+/// The write phase of the standard mode, where each worker creates its share of the znodes.
+struct Writes;
 
-    let count = opt.iteration / opt.threads;
-    for i in tid * count..(tid + 1) * count {
-        let path = opt.node_path_template.clone() + i.to_string().as_str();
-        let mode = if opt.ephemeral {
+impl Writes {
+    fn mode(opt: &BenchOption) -> CreateMode {
+        if opt.ephemeral {
             CreateMode::Ephemeral
         } else {
             CreateMode::Persistent
+        }
+    }
+}
+
+impl Workload for Writes {
+    type Worker = u32;
+
+    fn worker(&self, _opt: &BenchOption, tid: u32) -> u32 {
+        tid
+    }
+
+    fn next_op(&self, opt: &BenchOption, tid: &mut u32, n: u32) -> Option<Operation> {
+        let path = node_path(opt, node_index(opt, *tid, n));
+        Some(Operation::Create {
+            value: opt.value_for(&path),
+            path,
+            mode: Self::mode(opt),
+        })
+    }
+
+    fn warmup_op(&self, opt: &BenchOption, tid: &mut u32, i: u32) -> Option<Operation> {
+        let path = format!("{}warmup-{}-{}", opt.node_path_template, tid, i);
+        Some(Operation::Create {
+            value: opt.value_for(&path),
+            path,
+            mode: Self::mode(opt),
+        })
+    }
+}
+
+/// The znodes the write phase created, for the read phase to choose from by `--read-dist`.
+struct ReadKeys {
+    /// Indices of the znodes each worker cycles over with sequential reads
+    own: Vec<Vec<u32>>,
+    /// Indices of all znodes, oldest first, for the other distributions
+    all: Vec<u32>,
+    chooser: KeyChooser,
+}
+
+impl ReadKeys {
+    /// The znodes each worker of the write phase `opt` created, by their counts, for `readers`
+    /// workers. Sequential reads split them between the readers as the writers did, or evenly
+    /// if there are more or fewer readers.
+    fn new(opt: &BenchOption, created: Vec<u32>, readers: u32) -> Self {
+        let (mut own, mut all) = (Vec::new(), Vec::new());
+        if opt.read_dist == ReadDist::Sequential {
+            for (tid, &c) in created.iter().enumerate() {
+                own.push((0..c).map(|n| node_index(opt, tid as u32, n)).collect());
+            }
+            if own.len() != readers as usize {
+                let keys: Vec<u32> = own.into_iter().flatten().collect();
+                own = (0..readers)
+                    .map(|tid| {
+                        share(keys.len() as u32, readers, tid)
+                            .map(|i| keys[i as usize])
+                            .collect()
+                    })
+                    .collect();
+            }
+        } else {
+            // Workers create their znodes side by side, so their n-th ones are about as old
+            let most = created.iter().copied().max().unwrap_or(0);
+            for n in 0..most {
+                for (tid, &c) in created.iter().enumerate() {
+                    if n < c {
+                        all.push(node_index(opt, tid as u32, n));
+                    }
+                }
+            }
+        }
+        Self::with(opt, own, all)
+    }
+
+    /// The first `count` znodes, sequential reads splitting them evenly between the workers of
+    /// the read phase `opt`.
+    fn first(opt: &BenchOption, count: u32) -> Self {
+        let (mut own, mut all) = (Vec::new(), Vec::new());
+        if opt.read_dist == ReadDist::Sequential {
+            own = (0..opt.threads)
+                .map(|tid| share(count, opt.threads, tid).collect())
+                .collect();
+        } else {
+            all = (0..count).collect();
+        }
+        Self::with(opt, own, all)
+    }
+
+    fn with(opt: &BenchOption, mut own: Vec<Vec<u32>>, all: Vec<u32>) -> Self {
+        opt.read_order.arrange(&mut own, &mut rng::rng());
+        let chooser = KeyChooser::new(opt.read_dist, all.len());
+        ReadKeys { own, all, chooser }
+    }
+
+    /// Index of the znode of the `n`-th read of worker `tid`, unless there's nothing to read.
+    fn index(&self, opt: &BenchOption, tid: u32, n: u32, rng: &mut impl Rng) -> Option<u32> {
+        let index = match opt.read_dist {
+            ReadDist::Sequential => {
+                let own = self.own.get(tid as usize).filter(|own| !own.is_empty())?;
+                own[n as usize % own.len()]
+            }
+            _ => self.all[self.chooser.choose(rng, self.all.len())?],
         };
-        zk.create(
-            path.as_str(),
-            opt.node_value.to_vec(),
-            Acl::open_unsafe().clone(),
-            mode,
-        )?;
-        pb.inc(1);
-        pb.set_message(format!("Created {}", path))
+        Some(index)
+    }
+}
+
+/// The read phase of the standard mode, where workers read the znodes of `ReadKeys` the phase
+/// `written` created.
+struct Reads<'a> {
+    keys: &'a ReadKeys,
+    written: &'a BenchOption,
+}
+
+impl Workload for Reads<'_> {
+    type Worker = u32;
+
+    fn worker(&self, _opt: &BenchOption, tid: u32) -> u32 {
+        tid
     }
 
-    pb.finish_with_message(format!("Worker #{} finish", tid));
-    Ok(())
+    fn next_op(&self, opt: &BenchOption, tid: &mut u32, n: u32) -> Option<Operation> {
+        let index = self.keys.index(opt, *tid, n, &mut rng::rng())?;
+        Some(Operation::Get {
+            path: node_path(self.written, index),
+            watch: opt.watch,
+        })
+    }
+
+    fn warmup_op(&self, opt: &BenchOption, tid: &mut u32, i: u32) -> Option<Operation> {
+        self.next_op(opt, tid, i)
+    }
+}
+
+/// The read phase of the standard mode with `--read-prefix`, where workers read `paths` that
+/// were there before the run, picking them by `ReadKeys` over their positions.
+struct ListedReads<'a> {
+    keys: &'a ReadKeys,
+    paths: &'a [String],
 }
 
-fn do_qps_bench(tid: u32, pb: ProgressBar, opt: &BenchOption) -> Result<(), anyhow::Error> {
-    let zk = ZooKeeper::connect(opt.hosts.as_str(), opt.timeout, LoggingWatcher)?;
-    pb.set_message("Connected");
+impl Workload for ListedReads<'_> {
+    type Worker = u32;
 
-    let count = opt.iteration / opt.threads;
-    for i in tid * count..(tid + 1) * count {
-        let path = opt.node_path_template.clone() + i.to_string().as_str();
-        zk.get_data(path.as_str(), false)?;
-        pb.inc(1);
-        pb.set_message(format!("get_data() {}", path))
+    fn worker(&self, _opt: &BenchOption, tid: u32) -> u32 {
+        tid
     }
 
-    pb.finish_with_message(format!("Worker #{} finish", tid));
-    Ok(())
+    fn next_op(&self, opt: &BenchOption, tid: &mut u32, n: u32) -> Option<Operation> {
+        let index = self.keys.index(opt, *tid, n, &mut rng::rng())?;
+        Some(Operation::Get {
+            path: self.paths[index as usize].clone(),
+            watch: opt.watch,
+        })
+    }
+
+    fn warmup_op(&self, opt: &BenchOption, tid: &mut u32, i: u32) -> Option<Operation> {
+        self.next_op(opt, tid, i)
+    }
 }
@@ -0,0 +1,536 @@
+use super::workload::Operation;
+use super::{connect, connect_worker, landing, next_turn, observe_op, BenchOption};
+use crate::acl::NodeAcl;
+use crate::error::BenchError;
+use crate::wire::{self, Traffic};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::{self, Runtime};
+use tokio::time;
+use zookeeper::{CreateMode, Subscription, ZkError, ZkResult, ZkState, ZooKeeper};
+use zookeeper_client::{Client, Connector, Error, SessionState};
+
+/// Client library the operations of workloads go through.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// The blocking `zookeeper` crate
+    #[default]
+    Zookeeper,
+    /// The async `zookeeper-client` crate, waited on one operation at a time
+    ZookeeperClient,
+}
+
+/// What an operation got back that workers look at.
+#[derive(Debug, Default)]
+pub struct Reply {
+    /// Last modifying zxid of the znode it saw, if any
+    pub mzxid: Option<i64>,
+    /// Value a get read
+    pub data: Option<Vec<u8>>,
+}
+
+/// A session of one of the client libraries, which workers run their operations on.
+pub trait ZkBackend: Send + Sync {
+    /// Runs the operation, returning what it got back and the bytes it took.
+    fn execute(&self, op: Operation) -> (ZkResult<Reply>, Traffic);
+
+    /// Waits until a session without a connection gets one again or ends, returning when it
+    /// lost the connection if it ended and the worker needs another session. One that hasn't
+    /// reconnected within the session `timeout` is taken for expired.
+    fn ended(&self, timeout: Duration) -> Option<Instant>;
+
+    /// Closes the session, for the ensemble to let go of its ephemeral znodes right away.
+    fn close(self: Arc<Self>);
+}
+
+/// How long closing a session may take before it's left to expire
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a worker looks at the state of its session while waiting for it to settle
+const SETTLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A session's state as far as a worker is concerned: since when it's been without a
+/// connection, and whether it's over.
+#[derive(Debug, Default)]
+struct Liveness {
+    lost: Mutex<Option<Instant>>,
+    ended: AtomicBool,
+}
+
+impl Liveness {
+    fn disconnected(&self) {
+        self.lost.lock().unwrap().get_or_insert_with(Instant::now);
+    }
+
+    fn connected(&self) {
+        if !self.ended.load(Ordering::Relaxed) {
+            *self.lost.lock().unwrap() = None;
+        }
+    }
+
+    fn end(&self) {
+        self.disconnected();
+        self.ended.store(true, Ordering::Relaxed);
+    }
+
+    fn ended(&self, timeout: Duration, refresh: impl Fn(&Self)) -> Option<Instant> {
+        loop {
+            refresh(self);
+            let lost = (*self.lost.lock().unwrap())?;
+            if self.ended.load(Ordering::Relaxed) || lost.elapsed() > timeout {
+                return Some(lost);
+            }
+            thread::sleep(SETTLE_INTERVAL);
+        }
+    }
+}
+
+/// A session of the blocking client along with a listener following its state. The client
+/// closes a session it couldn't reconnect within the session timeout, by when the ensemble
+/// expired it too, and fails whatever is asked of it from then on. One the ensemble refused as
+/// expired when the client reconnected, the client replaces with a new session without telling,
+/// so the listener takes the refusal for the end of it.
+pub struct BlockingSession {
+    zk: Arc<ZooKeeper>,
+    liveness: Arc<Liveness>,
+    subscription: Subscription,
+    op_timeout: Option<Duration>,
+    acl: NodeAcl,
+    /// Thread running the operations under `--op-timeout`, see `execute_within`
+    runner: Mutex<Option<Sender<Request>>>,
+}
+
+/// An operation for the runner of a session, along with where it wants the outcome.
+type Request = (Operation, Sender<(ZkResult<Reply>, Traffic)>);
+
+impl BlockingSession {
+    fn watch(zk: Arc<ZooKeeper>, opt: &BenchOption) -> Self {
+        let liveness = Arc::new(Liveness::default());
+        let subscription = zk.add_listener({
+            let liveness = liveness.clone();
+            let previous = Mutex::new(ZkState::Connected);
+            move |state| {
+                let previous = mem::replace(&mut *previous.lock().unwrap(), state);
+                match state {
+                    ZkState::Connected | ZkState::ConnectedReadOnly => liveness.connected(),
+                    ZkState::Closed | ZkState::AuthFailed => liveness.end(),
+                    // Dropping the connection as it connects is how it takes a refusal
+                    _ if previous == ZkState::Connecting => liveness.end(),
+                    _ => liveness.disconnected(),
+                }
+            }
+        });
+        BlockingSession {
+            zk,
+            liveness,
+            subscription,
+            op_timeout: opt.op_timeout,
+            acl: opt.acl.clone(),
+            runner: Mutex::new(None),
+        }
+    }
+
+    /// Runs `op` on the session's runner thread, failing it with `OperationTimeout` if the
+    /// thread doesn't have it done within `timeout`. The blocking client has no way to give up
+    /// on a call, so a runner stuck in one is left to finish it and the next operation starts
+    /// another.
+    fn execute_within(&self, op: Operation, timeout: Duration) -> (ZkResult<Reply>, Traffic) {
+        let sent = request_bytes(&op);
+        let (reply, outcome) = mpsc::channel();
+        let mut runner = self.runner.lock().unwrap();
+        let requests = runner.get_or_insert_with(|| {
+            let (requests, incoming) = mpsc::channel::<Request>();
+            let (zk, acl) = (self.zk.clone(), self.acl.clone());
+            thread::spawn(move || {
+                for (op, reply) in incoming {
+                    // Nobody waits for the outcome of one that timed out
+                    let _ = reply.send(execute_blocking(&zk, op, &acl));
+                }
+            });
+            requests
+        });
+        requests.send((op, reply)).unwrap();
+        match outcome.recv_timeout(timeout) {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                *runner = None;
+                timed_out(sent)
+            }
+        }
+    }
+}
+
+impl Drop for BlockingSession {
+    fn drop(&mut self) {
+        // Sessions of the pool outlive the workers following them
+        self.zk.remove_listener(self.subscription);
+    }
+}
+
+impl ZkBackend for BlockingSession {
+    fn execute(&self, op: Operation) -> (ZkResult<Reply>, Traffic) {
+        match self.op_timeout {
+            Some(timeout) => self.execute_within(op, timeout),
+            None => execute_blocking(&self.zk, op, &self.acl),
+        }
+    }
+
+    fn ended(&self, timeout: Duration) -> Option<Instant> {
+        self.liveness.ended(timeout, |_| {})
+    }
+
+    fn close(self: Arc<Self>) {
+        // The client closes the session once the last handle on it is gone, which the runner
+        // of `--op-timeout` holds until it's done with what it runs
+        let zk = self.zk.clone();
+        drop(self);
+        let start = Instant::now();
+        while Arc::strong_count(&zk) > 1 && start.elapsed() < CLOSE_TIMEOUT {
+            thread::sleep(SETTLE_INTERVAL);
+        }
+    }
+}
+
+/// Runs `op` on the blocking client, returning what it got back and the bytes it took.
+fn execute_blocking(zk: &ZooKeeper, op: Operation, acl: &NodeAcl) -> (ZkResult<Reply>, Traffic) {
+    let mut traffic = Traffic::default();
+    let result = match op {
+        Operation::Create { path, value, mode } => {
+            let sent = wire::create_request(&path, value.len());
+            let result = zk.create(path.as_str(), value, acl.acl(), mode);
+            let received = match &result {
+                Ok(_) => wire::create_reply(&path),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(sent, received);
+            result.map(|_| Reply::default())
+        }
+        Operation::Get { path, watch } => {
+            let result = zk.get_data(path.as_str(), watch);
+            let received = match &result {
+                Ok((data, _)) => wire::get_data_reply(data.len()),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(wire::get_data_request(&path), received);
+            result.map(|(data, stat)| Reply {
+                mzxid: Some(stat.mzxid),
+                data: Some(data),
+            })
+        }
+        Operation::Set { path, value } => {
+            let sent = wire::set_data_request(&path, value.len());
+            let result = zk.set_data(path.as_str(), value, None);
+            let received = match &result {
+                Ok(_) => wire::set_data_reply(),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(sent, received);
+            result.map(|stat| Reply {
+                mzxid: Some(stat.mzxid),
+                data: None,
+            })
+        }
+        Operation::Delete { path } => {
+            let result = zk.delete(path.as_str(), None);
+            let received = match &result {
+                Ok(_) => wire::delete_reply(),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(wire::delete_request(&path), received);
+            result.map(|_| Reply::default())
+        }
+    };
+    (result, traffic)
+}
+
+/// A `zookeeper-client` session along with the runtime driving it, which keeps a thread of its
+/// own so the session stays alive between operations.
+pub struct AsyncSession {
+    client: Client,
+    runtime: Runtime,
+    liveness: Liveness,
+    op_timeout: Option<Duration>,
+    acl: NodeAcl,
+}
+
+impl AsyncSession {
+    pub fn connect(opt: &BenchOption) -> Result<(Self, Duration, Option<String>), anyhow::Error> {
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let (client, elapsed, member) = runtime.block_on(connect_client(opt))?;
+        let session = AsyncSession {
+            client,
+            runtime,
+            liveness: Liveness::default(),
+            op_timeout: opt.op_timeout,
+            acl: opt.acl.clone(),
+        };
+        Ok((session, elapsed, member))
+    }
+}
+
+impl ZkBackend for AsyncSession {
+    fn execute(&self, op: Operation) -> (ZkResult<Reply>, Traffic) {
+        let client = self.client.clone();
+        self.runtime.block_on(execute_within(
+            op,
+            client,
+            self.op_timeout,
+            self.acl.clone(),
+        ))
+    }
+
+    fn ended(&self, timeout: Duration) -> Option<Instant> {
+        // Its state is at hand, so it's looked at rather than listened to
+        self.liveness
+            .ended(timeout, |liveness| match self.client.state() {
+                SessionState::SyncConnected | SessionState::ConnectedReadOnly => {
+                    liveness.connected()
+                }
+                SessionState::Disconnected => liveness.disconnected(),
+                _ => liveness.end(),
+            })
+    }
+
+    fn close(self: Arc<Self>) {
+        // The client closes the session once the last handle on it is gone, which the runtime
+        // has to stay up for
+        let Ok(AsyncSession {
+            client, runtime, ..
+        }) = Arc::try_unwrap(self)
+        else {
+            return;
+        };
+        let mut watcher = client.state_watcher();
+        drop(client);
+        let closed = async { while !watcher.changed().await.is_terminated() {} };
+        runtime.block_on(async { time::timeout(CLOSE_TIMEOUT, closed).await.ok() });
+    }
+}
+
+type BackendSession = (Arc<dyn ZkBackend>, Instant, Duration, Option<String>);
+
+/// Connects a worker with the `--backend` library, as `connect_worker` does with the blocking
+/// one, which `--connections` sessions always are.
+pub(super) fn connect_backend(opt: &BenchOption) -> Result<BackendSession, anyhow::Error> {
+    match opt.backend {
+        Backend::Zookeeper => {
+            let (zk, worker_start, elapsed, member) = connect_worker(opt)?;
+            let session = BlockingSession::watch(zk, opt);
+            Ok((Arc::new(session), worker_start, elapsed, member))
+        }
+        Backend::ZookeeperClient => {
+            let start = Instant::now();
+            let (session, elapsed, member) = AsyncSession::connect(opt)?;
+            let worker_start = if opt.exclude_connect {
+                Instant::now()
+            } else {
+                start
+            };
+            Ok((Arc::new(session), worker_start, elapsed, member))
+        }
+    }
+}
+
+/// Connects a session in place of one that ended. It's a session of its own even under
+/// `--connections`, as the pool's sessions all ended alike.
+pub(super) fn reconnect_backend(opt: &BenchOption) -> Result<Arc<dyn ZkBackend>, anyhow::Error> {
+    Ok(match opt.backend {
+        Backend::Zookeeper => Arc::new(BlockingSession::watch(Arc::new(connect(opt)?), opt)),
+        Backend::ZookeeperClient => Arc::new(AsyncSession::connect(opt)?.0),
+    })
+}
+
+/// The error the blocking client reports for what the async one failed with, so both are
+/// counted and tolerated alike.
+pub(super) fn zk_error(e: Error) -> ZkError {
+    match e {
+        Error::NoNode => ZkError::NoNode,
+        Error::NoAuth => ZkError::NoAuth,
+        Error::BadVersion => ZkError::BadVersion,
+        Error::NoChildrenForEphemerals => ZkError::NoChildrenForEphemerals,
+        Error::NodeExists => ZkError::NodeExists,
+        Error::NotEmpty => ZkError::NotEmpty,
+        Error::SessionExpired => ZkError::SessionExpired,
+        Error::InvalidAcl => ZkError::InvalidACL,
+        Error::AuthFailed => ZkError::AuthFailed,
+        Error::SessionMoved => ZkError::SessionMoved,
+        Error::NotReadOnly => ZkError::NotReadOnly,
+        Error::NoWatcher => ZkError::NoWatcher,
+        Error::Unimplemented => ZkError::Unimplemented,
+        Error::MarshallingError | Error::UnmarshalError { .. } => ZkError::MarshallingError,
+        Error::ConnectionLoss | Error::ClientClosed => ZkError::ConnectionLoss,
+        Error::Timeout => ZkError::OperationTimeout,
+        Error::BadArguments(_) => ZkError::BadArguments,
+        Error::RuntimeInconsistent => ZkError::RuntimeInconsistency,
+        Error::QuotaExceeded
+        | Error::Throttled
+        | Error::ReconfigDisabled
+        | Error::UnexpectedErrorCode(_) => ZkError::APIError,
+        _ => ZkError::SystemError,
+    }
+}
+
+fn create_mode(mode: CreateMode) -> zookeeper_client::CreateMode {
+    match mode {
+        CreateMode::Persistent => zookeeper_client::CreateMode::Persistent,
+        CreateMode::Ephemeral => zookeeper_client::CreateMode::Ephemeral,
+        CreateMode::PersistentSequential => zookeeper_client::CreateMode::PersistentSequential,
+        CreateMode::EphemeralSequential => zookeeper_client::CreateMode::EphemeralSequential,
+        CreateMode::Container => zookeeper_client::CreateMode::Container,
+    }
+}
+
+/// Runs `op` on the async client, returning what it got back and the bytes it took.
+pub(super) async fn execute_async(
+    op: Operation,
+    client: Client,
+    acl: NodeAcl,
+) -> (ZkResult<Reply>, Traffic) {
+    let mut traffic = Traffic::default();
+    let result = match op {
+        Operation::Create { path, value, mode } => {
+            let sent = wire::create_request(&path, value.len());
+            let options = create_mode(mode).with_acls(acl.client_acls());
+            let result = client.create(&path, &value, &options).await;
+            let received = match &result {
+                Ok(_) => wire::create_reply(&path),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(sent, received);
+            result.map(|_| Reply::default())
+        }
+        Operation::Get { path, watch } => {
+            let result = match watch {
+                true => client
+                    .get_and_watch_data(&path)
+                    .await
+                    .map(|(data, stat, _)| (data, stat)),
+                false => client.get_data(&path).await,
+            };
+            let received = match &result {
+                Ok((data, _)) => wire::get_data_reply(data.len()),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(wire::get_data_request(&path), received);
+            result.map(|(data, stat)| Reply {
+                mzxid: Some(stat.mzxid),
+                data: Some(data),
+            })
+        }
+        Operation::Set { path, value } => {
+            let sent = wire::set_data_request(&path, value.len());
+            let result = client.set_data(&path, &value, None).await;
+            let received = match &result {
+                Ok(_) => wire::set_data_reply(),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(sent, received);
+            result.map(|stat| Reply {
+                mzxid: Some(stat.mzxid),
+                data: None,
+            })
+        }
+        Operation::Delete { path } => {
+            let result = client.delete(&path, None).await;
+            let received = match &result {
+                Ok(_) => wire::delete_reply(),
+                Err(_) => wire::error_reply(),
+            };
+            traffic.record(wire::delete_request(&path), received);
+            result.map(|_| Reply::default())
+        }
+    };
+    (result.map_err(zk_error), traffic)
+}
+
+/// Runs `op` on the async client like `execute_async`, failing it with `OperationTimeout` if it
+/// isn't done within the `timeout`, if any.
+pub(super) async fn execute_within(
+    op: Operation,
+    client: Client,
+    timeout: Option<Duration>,
+    acl: NodeAcl,
+) -> (ZkResult<Reply>, Traffic) {
+    let Some(timeout) = timeout else {
+        return execute_async(op, client, acl).await;
+    };
+    let sent = request_bytes(&op);
+    match time::timeout(timeout, execute_async(op, client, acl)).await {
+        Ok(outcome) => outcome,
+        Err(_) => timed_out(sent),
+    }
+}
+
+/// Bytes of the request of `op`, all an operation that timed out took as far as is known.
+fn request_bytes(op: &Operation) -> u64 {
+    match op {
+        Operation::Create { path, value, .. } => wire::create_request(path, value.len()),
+        Operation::Get { path, .. } => wire::get_data_request(path),
+        Operation::Set { path, value } => wire::set_data_request(path, value.len()),
+        Operation::Delete { path } => wire::delete_request(path),
+    }
+}
+
+fn timed_out(sent: u64) -> (ZkResult<Reply>, Traffic) {
+    let mut traffic = Traffic::default();
+    traffic.record(sent, 0);
+    (Err(ZkError::OperationTimeout), traffic)
+}
+
+/// Connects the async client, recording how long that took among the run's sessions like the
+/// blocking client's connects. Returns the member the session landed on along with it.
+pub(super) async fn connect_client(
+    opt: &BenchOption,
+) -> Result<(Client, Duration, Option<String>), anyhow::Error> {
+    let start = Instant::now();
+    let turn = next_turn(opt);
+    let result = connect_within(opt, connector(opt), &opt.connect_strings[turn]).await;
+    let elapsed = start.elapsed();
+    let outcome = result.as_ref().map(drop).map_err(|e| *e);
+    observe_op(opt, "connect", &opt.hosts, start, elapsed, &outcome)?;
+    let client = result.map_err(|e| BenchError::ConnectFailed(opt.hosts.clone(), e))?;
+    opt.sessions.lock().unwrap().record(elapsed);
+    Ok((client, elapsed, landing(opt, turn)))
+}
+
+/// Session timeout the ensemble grants for the one asked for, as a session of its own
+/// negotiated it. The blocking client keeps what it was granted to itself.
+pub fn negotiated_timeout(opt: &BenchOption) -> Result<Duration, anyhow::Error> {
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = runtime.block_on(connect_within(opt, connector(opt), &opt.connect_strings[0]))?;
+    Ok(client.session_timeout())
+}
+
+/// A connector of sessions that authenticate as the run's, by `--digest` and `--sasl`.
+fn connector(opt: &BenchOption) -> Connector {
+    let mut connector = Client::connector().with_session_timeout(opt.session_timeout);
+    if let Some(d) = &opt.digest {
+        connector = connector.with_auth("digest", d.as_bytes());
+    }
+    if let Some(sasl) = &opt.sasl {
+        connector = connector.with_sasl(sasl.options());
+    }
+    connector
+}
+
+/// Connects the async client within the `--connect-timeout`, which the client's own connection
+/// timeout only bounds each attempt of.
+async fn connect_within(opt: &BenchOption, connector: Connector, hosts: &str) -> ZkResult<Client> {
+    let connector = connector.with_connection_timeout(opt.connect_timeout);
+    match time::timeout(opt.connect_timeout, connector.connect(hosts)).await {
+        Ok(result) => result.map_err(zk_error),
+        Err(_) => Err(ZkError::OperationTimeout),
+    }
+}
@@ -0,0 +1,138 @@
+use super::backend::Backend;
+use super::{BenchOption, Mode};
+use crate::cli::RunArgs;
+use crate::cluster::Cluster;
+use crate::dist::ValueSize;
+use crate::sasl::Sasl;
+use std::time::Duration;
+
+/// Options of a run built in code rather than parsed from a command line, starting from the
+/// defaults `zoobench run` has without the environment variables it reads.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    args: RunArgs,
+}
+
+impl BenchOption {
+    /// Options of a run against `hosts`, optionally with a chroot, from which `build` checks
+    /// and derives them the way it does those of the command line.
+    pub fn builder(hosts: impl Into<String>) -> Builder {
+        let cluster = Cluster {
+            name: None,
+            hosts: hosts.into(),
+        };
+        Builder {
+            args: RunArgs::new(cluster),
+        }
+    }
+}
+
+impl Builder {
+    /// The workload, like `--mode`
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.args.mode.mode = mode;
+        self
+    }
+
+    /// The znode the run creates its own below, like `--prefix`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.args.connect.prefix = prefix.into();
+        self
+    }
+
+    /// How long the ensemble keeps a session without hearing from it, like `--session-timeout`
+    pub fn session_timeout(mut self, timeout: Duration) -> Self {
+        self.args.connect.session_timeout = timeout;
+        self
+    }
+
+    /// Authenticates sessions with `user:password`, like `--digest`
+    pub fn digest(mut self, credentials: impl Into<String>) -> Self {
+        self.args.connect.digest = Some(credentials.into());
+        self
+    }
+
+    /// Authenticates sessions by SASL, like `--sasl`, which needs the zookeeper-client backend
+    pub fn sasl(mut self, sasl: Sasl) -> Self {
+        self.args.connect.sasl = Some(sasl);
+        self
+    }
+
+    /// The client library workers connect with, like `--backend`
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.args.load.backend = backend;
+        self
+    }
+
+    /// Workers, like `--threads`
+    pub fn threads(mut self, threads: u32) -> Self {
+        self.args.load.threads = vec![threads];
+        self
+    }
+
+    /// Total operations, like `--iteration`
+    pub fn iteration(mut self, iteration: u32) -> Self {
+        self.args.load.iteration = iteration;
+        self
+    }
+
+    /// How long phases run instead of for a number of operations, like `--duration`
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.args.load.duration = Some(duration);
+        self
+    }
+
+    /// Operations per second across the workers, like `--rate`
+    pub fn rate(mut self, rate: f64) -> Self {
+        self.args.load.rate = Some(rate);
+        self
+    }
+
+    /// How long an operation may go unanswered before it fails, like `--op-timeout`
+    pub fn op_timeout(mut self, timeout: Duration) -> Self {
+        self.args.load.op_timeout = Some(timeout);
+        self
+    }
+
+    /// The seed workers draw their random numbers from, like `--seed`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.args.load.seed = Some(seed);
+        self
+    }
+
+    /// Size of the values written, like `--node-size`
+    pub fn node_size(mut self, size: ValueSize) -> Self {
+        self.args.values.node_size = size;
+        self
+    }
+
+    /// Checks the values read are those written, like `--verify`
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.args.verify.verify = verify;
+        self
+    }
+
+    /// Leaves the znodes created behind, like `--keep-data`
+    pub fn keep_data(mut self, keep: bool) -> Self {
+        self.args.cleanup.keep_data = keep;
+        self
+    }
+
+    /// Hides the workers' progress bars, like `--quiet`
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.args.output.quiet = quiet;
+        self
+    }
+
+    /// Labels the results with `key=value`, like `--tag`
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.args.output.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// The options, or why they don't make a run, as `zoobench run` would tell of the same
+    /// command line.
+    pub fn build(self) -> Result<BenchOption, anyhow::Error> {
+        BenchOption::try_from(self.args)
+    }
+}
@@ -0,0 +1,118 @@
+use super::{connect, do_bench, prepare, save_hgrm, share, BenchOption};
+use crate::rng;
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use rand::Rng;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use zookeeper::{CreateMode, ZkError};
+
+#[derive(Serialize)]
+pub struct CasResult {
+    pub keys: u32,
+    pub commits: u64,
+    pub retries: u64,
+    pub lost_updates: i64,
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+}
+
+impl CasResult {
+    pub fn success_rate(&self) -> f32 {
+        self.commits as f32 / (self.commits + self.retries) as f32
+    }
+}
+
+#[derive(Default)]
+struct CasWorker {
+    commits: u64,
+    retries: u64,
+    latencies: Latencies,
+}
+
+fn key_path(opt: &BenchOption, id: u32) -> String {
+    format!("{}/cas-{}", opt.prefix, id)
+}
+
+fn decode(data: &[u8]) -> u64 {
+    data.try_into().map(u64::from_be_bytes).unwrap_or_default()
+}
+
+pub fn bench(opt: &BenchOption) -> Result<CasResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    // Every key holds a big endian counter, so the final sum tells whether updates were lost
+    let zk = connect(opt)?;
+    for id in 0..opt.keys {
+        zk.create(
+            key_path(opt, id).as_str(),
+            0u64.to_be_bytes().to_vec(),
+            opt.acl.acl(),
+            CreateMode::Persistent,
+        )?;
+    }
+
+    log::info!(
+        "Running compare-and-set benchmark, {} threads on {} keys",
+        opt.threads,
+        opt.keys
+    );
+    let (elapsed, results) = do_bench(opt, do_cas_bench)?;
+
+    let mut latencies = Latencies::default();
+    let mut commits = 0;
+    let mut retries = 0;
+    for w in &results {
+        latencies.merge(&w.latencies);
+        commits += w.commits;
+        retries += w.retries;
+    }
+
+    save_hgrm(opt, "commit", &latencies)?;
+
+    let mut total = 0;
+    for id in 0..opt.keys {
+        total += decode(&zk.get_data(key_path(opt, id).as_str(), false)?.0);
+    }
+
+    Ok(CasResult {
+        keys: opt.keys,
+        commits,
+        retries,
+        lost_updates: commits as i64 - total as i64,
+        elapsed,
+        latency: latencies.summary(),
+    })
+}
+
+fn do_cas_bench(tid: u32, pb: ProgressBar, opt: &BenchOption) -> Result<CasWorker, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let mut rng = rng::rng();
+    let mut w = CasWorker::default();
+    for _ in share(opt.iteration, opt.threads, tid) {
+        if opt.interrupted() {
+            break;
+        }
+        let path = key_path(opt, rng.gen_range(0..opt.keys));
+        let start = Instant::now();
+        loop {
+            let (data, stat) = zk.get_data(path.as_str(), false)?;
+            let next = (decode(&data) + 1).to_be_bytes().to_vec();
+            match zk.set_data(path.as_str(), next, Some(stat.version)) {
+                Ok(_) => break,
+                Err(ZkError::BadVersion) => w.retries += 1,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        w.latencies.record(start.elapsed());
+        w.commits += 1;
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(w)
+}
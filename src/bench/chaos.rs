@@ -0,0 +1,151 @@
+use crate::error::BenchError;
+use crate::rng;
+use crate::stats::{Latencies, LatencySummary, Timeline};
+use rand::Rng;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Fault `--chaos` injects while the workers run: `session-kill:rate=1/min` closes the session
+/// of a worker about once a minute, at random, for it to connect another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chaos {
+    kills: u32,
+    per: Duration,
+}
+
+impl FromStr for Chaos {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad chaos `{}`", s));
+        let rate = match s.split_once(':') {
+            Some(("session-kill", rate)) => rate.strip_prefix("rate=").ok_or_else(invalid)?,
+            _ => return Err(invalid()),
+        };
+        let (kills, per) = rate.split_once('/').ok_or_else(invalid)?;
+        let kills = kills.trim().parse().map_err(|_| invalid())?;
+        // A bare unit like `min` stands for one of it
+        let per = humantime::parse_duration(per.trim())
+            .or_else(|_| humantime::parse_duration(&format!("1{}", per.trim())))
+            .map_err(|_| invalid())?;
+        if kills == 0 || per.is_zero() {
+            return Err(invalid());
+        }
+        Ok(Chaos { kills, per })
+    }
+}
+
+impl fmt::Display for Chaos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per = humantime::format_duration(self.per);
+        write!(f, "session-kill:rate={}/{}", self.kills, per)
+    }
+}
+
+impl Serialize for Chaos {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl Chaos {
+    /// Whether a kill is due, in which case the calling worker takes it on. Kills come at
+    /// exponentially distributed intervals, so whichever worker gets to one first is as good as
+    /// picked at random.
+    pub(super) fn due(&self, kills: &Kills) -> bool {
+        let mut next = kills.next.lock().unwrap();
+        let now = Instant::now();
+        let interval = (self.per / self.kills).mul_f64(-(1.0 - rng::rng().gen::<f64>()).ln());
+        match *next {
+            Some(due) if due <= now => {
+                *next = Some(now + interval);
+                true
+            }
+            Some(_) => false,
+            None => {
+                *next = Some(now + interval);
+                false
+            }
+        }
+    }
+}
+
+/// What `--chaos` did so far: when the next kill is due, how long each took to recover from,
+/// and every operation the workers completed, second by second, to tell the dip it caused.
+#[derive(Debug, Default)]
+pub(super) struct Kills {
+    next: Mutex<Option<Instant>>,
+    completed: Mutex<Timeline>,
+    kills: Mutex<Vec<Kill>>,
+}
+
+#[derive(Debug)]
+struct Kill {
+    at: Instant,
+    recovery: Duration,
+    /// Ephemeral znodes of the killed session re-created on the new one, and how long it took
+    recreated: Option<(usize, Duration)>,
+}
+
+impl Kills {
+    pub(super) fn completed(&self) {
+        self.completed.lock().unwrap().record();
+    }
+
+    /// Records a kill at `at`, which the worker recovered from once it had a new session with
+    /// the `recreated` ephemeral znodes back on it.
+    pub(super) fn record(&self, at: Instant, recreated: Option<(usize, Duration)>) {
+        self.kills.lock().unwrap().push(Kill {
+            at,
+            recovery: at.elapsed(),
+            recreated,
+        });
+    }
+
+    pub(super) fn stats(&self) -> ChaosStats {
+        let kills = self.kills.lock().unwrap();
+        let completed = self.completed.lock().unwrap();
+        let (mut recovery, mut recreation) = (Latencies::default(), Latencies::default());
+        let (mut dips, mut recreated) = (Vec::new(), 0);
+        for kill in kills.iter() {
+            recovery.record(kill.recovery);
+            if let Some((n, took)) = kill.recreated {
+                recreated += n;
+                recreation.record(took);
+            }
+            // Throughput over the seconds the recovery spanned against the second before
+            let (first, last) = (
+                completed.second(kill.at),
+                completed.second(kill.at + kill.recovery),
+            );
+            let before = first.checked_sub(1).map_or(0, |s| completed.count(s));
+            if before > 0 {
+                let during = (first..=last).map(|s| completed.count(s)).sum::<u64>() as f64
+                    / (last - first + 1) as f64;
+                dips.push((1.0 - during / before as f64).max(0.0));
+            }
+        }
+        ChaosStats {
+            recovery: recovery.summary(),
+            throughput_dip: (!dips.is_empty())
+                .then(|| dips.iter().sum::<f64>() / dips.len() as f64),
+            recreated,
+            recreation: recreation.summary(),
+        }
+    }
+}
+
+/// Sessions `--chaos` killed over the run and what it cost to recover from each.
+#[derive(Serialize)]
+pub struct ChaosStats {
+    /// From closing the session until a new one was up with the ephemeral znodes back
+    pub recovery: LatencySummary,
+    /// Fraction of the run's throughput lost while a kill was recovered from, on average
+    pub throughput_dip: Option<f64>,
+    pub recreated: usize,
+    /// Time taken re-creating the ephemeral znodes of each killed session that had any
+    pub recreation: LatencySummary,
+}
@@ -0,0 +1,74 @@
+use super::{BenchOption, BenchResult};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ConcurrencyStep {
+    pub threads: u32,
+    pub result: BenchResult,
+}
+
+#[derive(Serialize)]
+pub struct ConcurrencyResult {
+    pub steps: Vec<ConcurrencyStep>,
+    /// Thread counts past which more threads stop paying off in throughput, for writes and reads
+    pub write_knee: Option<u32>,
+    pub read_knee: Option<u32>,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<ConcurrencyResult, anyhow::Error> {
+    let mut steps = Vec::new();
+    for &threads in &opt.thread_counts {
+        // The step interrupted keeps what it measured, those after it don't run
+        if opt.interrupted() {
+            break;
+        }
+        log::info!("Running the standard benchmark with {} threads", threads);
+        // Each step keeps its own histograms rather than overwriting the previous one's
+        let step_opt = BenchOption {
+            threads,
+            hgrm_out: opt
+                .hgrm_out
+                .as_ref()
+                .map(|d| d.join(format!("threads-{}", threads))),
+            ..opt.clone()
+        };
+        let result = super::bench(&step_opt)?;
+        steps.push(ConcurrencyStep { threads, result });
+    }
+
+    let write_knee = knee(steps.iter().map(|s| (s.threads, s.result.write.throughput)));
+    let read_knee = knee(steps.iter().map(|s| (s.threads, s.result.read.throughput)));
+    Ok(ConcurrencyResult {
+        steps,
+        write_knee,
+        read_knee,
+    })
+}
+
+/// Thread count of the knee of a throughput curve, by Kneedle: with both axes scaled to 0..1,
+/// thread counts logarithmically as they usually double, the point furthest above the diagonal.
+/// Curves of fewer than three points have none.
+fn knee(points: impl Iterator<Item = (u32, f64)>) -> Option<u32> {
+    let points: Vec<(u32, f64, f64)> = points
+        .map(|(threads, tps)| (threads, (threads as f64).ln(), tps))
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+    let scale = |v: f64, min: f64, max: f64| match max > min {
+        true => (v - min) / (max - min),
+        false => 0.0,
+    };
+    let (min_x, max_x) = (points[0].1, points[points.len() - 1].1);
+    let min_y = points.iter().map(|p| p.2).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|p| p.2).fold(f64::NEG_INFINITY, f64::max);
+    points
+        .iter()
+        .map(|&(threads, x, y)| {
+            let gain = scale(y, min_y, max_y) - scale(x, min_x, max_x);
+            (threads, gain)
+        })
+        .filter(|&(_, gain)| gain > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(threads, _)| threads)
+}
@@ -0,0 +1,266 @@
+use super::backend::{connect_client, zk_error};
+use super::{connect, new_progress_style, prepare, save_hgrm, BenchOption};
+use crate::error::BenchError;
+use crate::stats::{Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Barrier, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+use tokio::runtime;
+use zookeeper::CreateMode;
+
+#[derive(Serialize)]
+pub struct ConsistencyResult {
+    pub readers: u32,
+    pub updates: u32,
+    /// Reads straight after each update
+    pub plain: Staleness,
+    /// Reads each preceded by a sync, which catches the reader's server up with the leader
+    pub synced: Staleness,
+    /// Staleness of plain reads by the member the readers landed on
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub members: BTreeMap<String, LatencySummary>,
+}
+
+/// How long readers took to see updates, from when the writer had each acknowledged until a
+/// read returned it, which includes that read's round trip.
+#[derive(Serialize)]
+pub struct Staleness {
+    pub latency: LatencySummary,
+    pub reads: u64,
+    /// Reads that returned an older version
+    pub stale_reads: u64,
+}
+
+/// The update readers are after, and how many of them saw it so far.
+struct Round {
+    version: i32,
+    written: Instant,
+    seen: u32,
+    over: bool,
+}
+
+struct Shared {
+    round: Mutex<Round>,
+    changed: Condvar,
+    connected: Barrier,
+}
+
+impl Shared {
+    fn end(&self) {
+        self.round.lock().unwrap().over = true;
+        self.changed.notify_all();
+    }
+}
+
+#[derive(Default)]
+struct ReaderStats {
+    plain: Latencies,
+    synced: Latencies,
+    reads: [u64; 2],
+    stale_reads: [u64; 2],
+}
+
+/// One writer updates a znode, and waits for every reader to see each update before the next.
+/// Readers take turns reading with and without a sync, so each member is measured both ways.
+pub fn bench(opt: &BenchOption) -> Result<ConsistencyResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let path = format!("{}/read-after-write", opt.prefix);
+    let zk = connect(opt)?;
+    zk.create(
+        path.as_str(),
+        opt.value(),
+        opt.acl.acl(),
+        CreateMode::Persistent,
+    )?;
+
+    let shared = Shared {
+        round: Mutex::new(Round {
+            version: 0,
+            written: Instant::now(),
+            seen: 0,
+            over: false,
+        }),
+        changed: Condvar::new(),
+        connected: Barrier::new(opt.threads as usize + 1),
+    };
+
+    let bar = opt.progress();
+    let pb = bar.add(ProgressBar::new(opt.iteration as u64));
+    pb.set_style(new_progress_style());
+    pb.set_message("Writer");
+    opt.show_workers(vec![pb.clone()]);
+
+    log::info!(
+        "Running read-after-write benchmark, {} readers",
+        opt.threads
+    );
+    let mut failed = None;
+    let mut results = Vec::new();
+    let mut updates = 0;
+    thread::scope(|s| {
+        let mut threads = Vec::new();
+        for _ in 0..opt.threads {
+            let (path, shared) = (&path, &shared);
+            threads.push(s.spawn(move || {
+                let result = do_read(opt, path, shared);
+                // The writer waits for every reader, so it has to hear of one that gave up
+                shared.end();
+                result
+            }));
+        }
+
+        shared.connected.wait();
+        for _ in 0..opt.iteration {
+            if opt.interrupted() {
+                break;
+            }
+            let stat = match zk.set_data(path.as_str(), opt.value(), None) {
+                Ok(stat) => stat,
+                Err(e) => {
+                    log::error!("Writer exit, {}", e);
+                    failed = Some(e.into());
+                    break;
+                }
+            };
+            let mut round = shared.round.lock().unwrap();
+            *round = Round {
+                version: stat.version,
+                written: Instant::now(),
+                seen: 0,
+                ..*round
+            };
+            shared.changed.notify_all();
+            let round = shared
+                .changed
+                .wait_while(round, |r| r.seen < opt.threads && !r.over)
+                .unwrap();
+            if round.over {
+                break;
+            }
+            updates += 1;
+            pb.inc(1);
+        }
+        shared.end();
+        pb.finish_with_message("Writer finish");
+
+        for (rid, t) in threads.into_iter().enumerate() {
+            match t.join().unwrap() {
+                Ok(r) => results.push(r),
+                Err(e) => {
+                    log::error!("Reader #{} exit, {}", rid, e);
+                    failed.get_or_insert(e);
+                }
+            }
+        }
+    });
+
+    if let Some(e) = failed {
+        return Err(BenchError::BenchFailed(e).into());
+    }
+
+    let (mut plain, mut synced) = (Latencies::default(), Latencies::default());
+    let (mut reads, mut stale_reads) = ([0; 2], [0; 2]);
+    let mut members: BTreeMap<String, Latencies> = BTreeMap::new();
+    for (member, r) in &results {
+        plain.merge(&r.plain);
+        synced.merge(&r.synced);
+        for i in 0..2 {
+            reads[i] += r.reads[i];
+            stale_reads[i] += r.stale_reads[i];
+        }
+        if let Some(member) = member {
+            members.entry(member.clone()).or_default().merge(&r.plain);
+        }
+    }
+
+    save_hgrm(opt, "staleness", &plain)?;
+    save_hgrm(opt, "synced-staleness", &synced)?;
+
+    Ok(ConsistencyResult {
+        readers: opt.threads,
+        updates,
+        plain: Staleness {
+            latency: plain.summary(),
+            reads: reads[0],
+            stale_reads: stale_reads[0],
+        },
+        synced: Staleness {
+            latency: synced.summary(),
+            reads: reads[1],
+            stale_reads: stale_reads[1],
+        },
+        members: members
+            .iter()
+            .map(|(m, l)| (m.clone(), l.summary()))
+            .collect(),
+    })
+}
+
+/// Reads every update until it shows, over a session of the async client, which unlike the
+/// blocking one can sync. Gives up on one not seen within the session timeout.
+fn do_read(
+    opt: &BenchOption,
+    path: &str,
+    shared: &Shared,
+) -> Result<(Option<String>, ReaderStats), anyhow::Error> {
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let connected = runtime.block_on(connect_client(opt));
+    // The writer waits for every reader, so the barrier must be reached even on failure
+    shared.connected.wait();
+    let (client, _, member) = connected?;
+
+    let mut stats = ReaderStats::default();
+    let mut last = 0;
+    loop {
+        let (version, written) = {
+            let round = shared
+                .changed
+                .wait_while(shared.round.lock().unwrap(), |r| {
+                    r.version == last && !r.over
+                })
+                .unwrap();
+            if round.over {
+                break;
+            }
+            (round.version, round.written)
+        };
+
+        let sync = version % 2 == 0;
+        runtime.block_on(async {
+            loop {
+                if sync {
+                    client.sync(path).await.map_err(zk_error)?;
+                }
+                let (_, stat) = client.get_data(path).await.map_err(zk_error)?;
+                stats.reads[sync as usize] += 1;
+                if stat.version >= version {
+                    return Ok(());
+                }
+                stats.stale_reads[sync as usize] += 1;
+                if written.elapsed() > opt.session_timeout {
+                    return Err(anyhow::anyhow!(
+                        "version {} still unseen after {:?}",
+                        version,
+                        opt.session_timeout
+                    ));
+                }
+            }
+        })?;
+        match sync {
+            true => stats.synced.record(written.elapsed()),
+            false => stats.plain.record(written.elapsed()),
+        }
+
+        shared.round.lock().unwrap().seen += 1;
+        shared.changed.notify_all();
+        last = version;
+    }
+    Ok((member, stats))
+}
@@ -0,0 +1,110 @@
+use super::{connect, do_bench, prepare, BenchOption};
+use crate::stats::serialize_secs;
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use zookeeper::{CreateMode, ZooKeeper, ZooKeeperExt};
+
+#[derive(Serialize)]
+pub struct DeleteResult {
+    pub depth: u32,
+    pub width: u32,
+    pub nodes: u64,
+    #[serde(serialize_with = "serialize_secs")]
+    pub build_elapsed: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub delete_elapsed: Duration,
+}
+
+fn tree_size(depth: u32, width: u32) -> u64 {
+    (1..=depth).map(|d| (width as u64).pow(d)).sum()
+}
+
+pub fn bench(opt: &BenchOption) -> Result<DeleteResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let root = format!("{}/tree", opt.prefix);
+    let zk = connect(opt)?;
+    zk.create(
+        root.as_str(),
+        Vec::new(),
+        opt.parent_acl(),
+        CreateMode::Persistent,
+    )?;
+
+    let nodes = tree_size(opt.tree_depth, opt.tree_width);
+    log::info!(
+        "Building tree of depth {} and width {}, {} znodes",
+        opt.tree_depth,
+        opt.tree_width,
+        nodes
+    );
+    let build_opt = BenchOption {
+        iteration: nodes.min(u32::MAX as u64) as u32,
+        ..opt.clone()
+    };
+    let root_ref = root.as_str();
+    let (build_elapsed, _) = do_bench(&build_opt, move |tid, pb, opt| {
+        do_build(tid, pb, opt, root_ref)
+    })?;
+
+    // A tree left half built isn't worth timing the deletion of
+    let delete_elapsed = match opt.interrupted() {
+        true => Duration::ZERO,
+        false => {
+            log::info!("Running delete_recursive benchmark");
+            let start = Instant::now();
+            zk.delete_recursive(root.as_str())?;
+            start.elapsed()
+        }
+    };
+
+    Ok(DeleteResult {
+        depth: opt.tree_depth,
+        width: opt.tree_width,
+        nodes,
+        build_elapsed,
+        delete_elapsed,
+    })
+}
+
+fn do_build(tid: u32, pb: ProgressBar, opt: &BenchOption, root: &str) -> Result<(), anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    // Top level subtrees are dealt round robin across workers
+    for i in (tid..opt.tree_width).step_by(opt.threads as usize) {
+        if opt.interrupted() {
+            break;
+        }
+        build_subtree(&zk, &pb, opt, format!("{}/n{}", root, i), 1)?;
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(())
+}
+
+fn build_subtree(
+    zk: &ZooKeeper,
+    pb: &ProgressBar,
+    opt: &BenchOption,
+    path: String,
+    depth: u32,
+) -> Result<(), anyhow::Error> {
+    let acl = match depth < opt.tree_depth {
+        true => opt.parent_acl(),
+        false => opt.acl.acl(),
+    };
+    zk.create(path.as_str(), opt.value(), acl, CreateMode::Persistent)?;
+    pb.inc(1);
+    if depth < opt.tree_depth {
+        for i in 0..opt.tree_width {
+            if opt.interrupted() {
+                break;
+            }
+            build_subtree(zk, pb, opt, format!("{}/n{}", path, i), depth + 1)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use super::{connect, do_bench, merge_latencies, prepare, save_hgrm, share, BenchOption};
+use crate::recipes::election::Election;
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use zookeeper::CreateMode;
+
+#[derive(Serialize)]
+pub struct ElectionResult {
+    pub groups: u32,
+    pub elections: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+}
+
+struct Group {
+    /// Its share of `--iteration`
+    elections: u32,
+    rounds: AtomicU32,
+    // Nanoseconds since `start` plus one at which the last leader resigned, zero before the first
+    resigned_at: AtomicU64,
+}
+
+fn group_path(opt: &BenchOption, id: u32) -> String {
+    format!("{}/elections/group-{}", opt.prefix, id)
+}
+
+pub fn bench(opt: &BenchOption) -> Result<ElectionResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let zk = connect(opt)?;
+    let parent = format!("{}/elections", opt.prefix);
+    for path in std::iter::once(parent).chain((0..opt.groups).map(|id| group_path(opt, id))) {
+        zk.create(
+            path.as_str(),
+            Vec::new(),
+            opt.parent_acl(),
+            CreateMode::Persistent,
+        )?;
+    }
+
+    let start = Instant::now();
+    let groups: Vec<Group> = (0..opt.groups)
+        .map(|id| Group {
+            elections: share(opt.iteration, opt.groups, id).len() as u32,
+            rounds: AtomicU32::new(0),
+            resigned_at: AtomicU64::new(0),
+        })
+        .collect();
+
+    log::info!(
+        "Running leader election benchmark, {} candidates in {} groups",
+        opt.threads,
+        opt.groups
+    );
+    let groups = &groups;
+    let (elapsed, results) = do_bench(opt, move |tid, pb, opt| {
+        do_election_bench(tid, pb, opt, &groups[(tid % opt.groups) as usize], start)
+    })?;
+
+    let latencies = merge_latencies(&results);
+    save_hgrm(opt, "convergence", &latencies)?;
+    let latency = latencies.summary();
+
+    Ok(ElectionResult {
+        groups: opt.groups,
+        elections: groups
+            .iter()
+            .map(|g| g.rounds.load(Ordering::Relaxed).min(g.elections))
+            .sum(),
+        elapsed,
+        latency,
+    })
+}
+
+fn do_election_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    group: &Group,
+    start: Instant,
+) -> Result<Latencies, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let path = group_path(opt, tid % opt.groups);
+    let election = Election::new(&zk, path.as_str(), opt.session_timeout).with_acl(opt.acl.acl());
+    let mut latencies = Latencies::default();
+    while !opt.interrupted() {
+        let candidate = election.join()?;
+        candidate.wait_for_leadership()?;
+        let elected = start.elapsed();
+
+        // Once the budget is spent or the run interrupted every remaining candidate leads once
+        // more and steps down
+        if group.rounds.fetch_add(1, Ordering::AcqRel) >= group.elections || opt.interrupted() {
+            candidate.resign()?;
+            break;
+        }
+        let resigned_at = group.resigned_at.load(Ordering::Acquire);
+        if resigned_at > 0 {
+            latencies.record(elected.saturating_sub(Duration::from_nanos(resigned_at - 1)));
+        }
+        pb.inc(1);
+
+        group
+            .resigned_at
+            .store(start.elapsed().as_nanos() as u64 + 1, Ordering::Release);
+        candidate.resign()?;
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(latencies)
+}
@@ -0,0 +1,235 @@
+use super::{connect, new_progress_style, prepare, save_hgrm, BenchOption};
+use crate::error::BenchError;
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+use zookeeper::{CreateMode, WatchedEvent, WatchedEventType, ZooKeeper};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Serialize)]
+pub struct FanoutResult {
+    pub watchers: u32,
+    pub updates: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+    pub dropped: u64,
+    pub late: u64,
+    pub slowest_watcher: Option<(u32, LatencySummary)>,
+}
+
+struct Shared {
+    start: Instant,
+    // Nanoseconds since `start` at which the write producing each version was issued
+    written_at: Vec<AtomicU64>,
+    last_version: AtomicU64,
+    done: AtomicBool,
+}
+
+impl Shared {
+    fn latency_of(&self, version: i32, received: Instant) -> Duration {
+        let written = self.written_at[version as usize].load(Ordering::Acquire);
+        received
+            .duration_since(self.start)
+            .saturating_sub(Duration::from_nanos(written))
+    }
+}
+
+#[derive(Default)]
+struct WatcherStats {
+    latencies: Latencies,
+    dropped: u64,
+    late: u64,
+}
+
+fn notifier(tx: Sender<Instant>) -> impl Fn(WatchedEvent) + Send + 'static {
+    move |e: WatchedEvent| {
+        if e.event_type == WatchedEventType::NodeDataChanged {
+            let _ = tx.send(Instant::now());
+        }
+    }
+}
+
+pub fn bench(opt: &BenchOption) -> Result<FanoutResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let path = format!("{}/fanout", opt.prefix);
+    let zk = connect(opt)?;
+    zk.create(
+        path.as_str(),
+        opt.value(),
+        opt.acl.acl(),
+        CreateMode::Persistent,
+    )?;
+
+    let shared = Shared {
+        start: Instant::now(),
+        written_at: (0..=opt.iteration).map(|_| AtomicU64::new(0)).collect(),
+        last_version: AtomicU64::new(0),
+        done: AtomicBool::new(false),
+    };
+    let barrier = Barrier::new(opt.watchers as usize + 1);
+
+    let bar = opt.progress();
+    let writer_pb = bar.add(ProgressBar::new(opt.iteration as u64));
+    writer_pb.set_style(new_progress_style());
+    writer_pb.set_message("Writer");
+    let watcher_pb = bar.add(ProgressBar::new(opt.iteration as u64 * opt.watchers as u64));
+    watcher_pb.set_style(new_progress_style());
+    watcher_pb.set_message(format!("{} watchers", opt.watchers));
+    opt.show_workers(vec![writer_pb.clone(), watcher_pb.clone()]);
+
+    log::info!("Running watch fan-out benchmark, {} watchers", opt.watchers);
+    let mut failed = None;
+    let mut elapsed = Duration::ZERO;
+    let mut results = Vec::new();
+    thread::scope(|s| {
+        let mut threads = Vec::new();
+        for _ in 0..opt.watchers {
+            let (pb, path, shared, barrier) = (watcher_pb.clone(), &path, &shared, &barrier);
+            threads.push(s.spawn(move || do_watch(pb, opt, path, shared, barrier)));
+        }
+
+        match do_write(&zk, &writer_pb, opt, &path, &shared, &barrier) {
+            Ok(d) => elapsed = d,
+            Err(e) => {
+                log::error!("Writer exit, {}", e);
+                failed = Some(e);
+            }
+        }
+        shared.done.store(true, Ordering::Release);
+
+        for (wid, t) in threads.into_iter().enumerate() {
+            match t.join().unwrap() {
+                Ok(r) => results.push((wid as u32, r)),
+                Err(e) => {
+                    log::error!("Watcher #{} exit, {}", wid, e);
+                    failed.get_or_insert(e);
+                }
+            }
+        }
+    });
+    watcher_pb.finish_with_message("Watchers finish");
+
+    if let Some(e) = failed {
+        return Err(BenchError::BenchFailed(e).into());
+    }
+
+    let mut latencies = Latencies::default();
+    let mut dropped = 0;
+    let mut late = 0;
+    let mut slowest_watcher: Option<(u32, LatencySummary)> = None;
+    for (wid, r) in &results {
+        latencies.merge(&r.latencies);
+        dropped += r.dropped;
+        late += r.late;
+        let summary = r.latencies.summary();
+        if slowest_watcher.is_none_or(|(_, s)| summary.p99 > s.p99) {
+            slowest_watcher = Some((*wid, summary));
+        }
+    }
+
+    save_hgrm(opt, "notification", &latencies)?;
+
+    Ok(FanoutResult {
+        watchers: opt.watchers,
+        updates: shared.last_version.load(Ordering::Acquire) as u32,
+        elapsed,
+        latency: latencies.summary(),
+        dropped,
+        late,
+        slowest_watcher,
+    })
+}
+
+fn do_write(
+    zk: &ZooKeeper,
+    pb: &ProgressBar,
+    opt: &BenchOption,
+    path: &str,
+    shared: &Shared,
+    barrier: &Barrier,
+) -> Result<Duration, anyhow::Error> {
+    barrier.wait();
+    pb.set_message("Writing");
+
+    let start = Instant::now();
+    for version in 1..=opt.iteration {
+        if opt.interrupted() {
+            break;
+        }
+        shared.written_at[version as usize]
+            .store(shared.start.elapsed().as_nanos() as u64, Ordering::Release);
+        zk.set_data(path, opt.value(), None)?;
+        shared.last_version.store(version as u64, Ordering::Release);
+        pb.inc(1);
+    }
+    let elapsed = start.elapsed();
+
+    pb.finish_with_message("Writer finish");
+    Ok(elapsed)
+}
+
+fn do_watch(
+    pb: ProgressBar,
+    opt: &BenchOption,
+    path: &str,
+    shared: &Shared,
+    barrier: &Barrier,
+) -> Result<WatcherStats, anyhow::Error> {
+    let (tx, rx) = mpsc::channel();
+    let registered = connect(opt).and_then(|zk| {
+        let (_, stat) = zk.get_data_w(path, notifier(tx.clone()))?;
+        Ok((zk, stat.version))
+    });
+    // The writer waits for every watcher, so the barrier must be reached even on failure
+    barrier.wait();
+    let (zk, mut seen) = registered?;
+
+    let mut stats = WatcherStats::default();
+    let mut deadline = None;
+    // Notifications still on their way when the run is interrupted are neither seen nor dropped
+    while !opt.interrupted() {
+        // Once the writer is done, pending notifications get one session timeout to arrive
+        if shared.done.load(Ordering::Acquire) {
+            let last = shared.last_version.load(Ordering::Acquire);
+            let deadline = *deadline.get_or_insert_with(|| Instant::now() + opt.session_timeout);
+            if seen as u64 >= last {
+                break;
+            }
+            if Instant::now() >= deadline {
+                stats.dropped += last - seen as u64;
+                pb.inc(last - seen as u64);
+                break;
+            }
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(received) => {
+                let expected = seen + 1;
+                let latency = shared.latency_of(expected, received);
+                stats.latencies.record(latency);
+                if latency > opt.late_threshold {
+                    stats.late += 1;
+                }
+
+                let (_, stat) = zk.get_data_w(path, notifier(tx.clone()))?;
+                // Updates that landed before the watch was re-armed never produce a notification
+                stats.dropped += (stat.version - expected) as u64;
+                pb.inc((stat.version - seen) as u64);
+                seen = stat.version;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => unreachable!(),
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,84 @@
+use super::{connect, BenchOption};
+use rand::Rng;
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zookeeper::{ZkError, ZooKeeperExt};
+
+/// The prefix of a `--unique-prefix` run: `prefix` with the run's ID appended, of the second it
+/// started, its process and a random number, like /zoobench-671e2f3a-4242-9c0e51d7, so runs of
+/// the same process or of hosts alike don't share it.
+pub fn unique(prefix: &str) -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // Not of the run's random numbers, which `--seed` repeats
+    let nonce: u32 = rand::thread_rng().gen();
+    format!(
+        "{}-{:x}-{}-{:08x}",
+        prefix,
+        started.as_secs(),
+        process::id(),
+        nonce
+    )
+}
+
+/// Whether `name` is that of a `--unique-prefix` run below `base`, with or without the random
+/// number IDs of earlier versions lacked.
+fn is_run(name: &str, base: &str) -> bool {
+    let Some(id) = name
+        .strip_prefix(base)
+        .and_then(|rest| rest.strip_prefix('-'))
+    else {
+        return false;
+    };
+    let hex = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit());
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    match id.split('-').collect::<Vec<_>>()[..] {
+        [secs, pid] => hex(secs) && digits(pid),
+        [secs, pid, nonce] => hex(secs) && digits(pid) && hex(nonce),
+        _ => false,
+    }
+}
+
+/// Deletes the prefixes `--unique-prefix` runs of the prefix left behind, of those created more
+/// than `older_than` ago, returning them. Their age is that of when the servers created them by
+/// this host's clock, so clocks far apart make prefixes look older or younger than they are. Runs still going are
+/// younger than that unless it's shorter than they run.
+pub fn collect(opt: &BenchOption, older_than: Duration) -> Result<Vec<String>, anyhow::Error> {
+    let zk = connect(opt)?;
+    let (parent, base) = match opt.prefix.rsplit_once('/') {
+        Some(("", base)) => ("/", base),
+        Some((parent, base)) => (parent, base),
+        None => ("/", opt.prefix.as_str()),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut deleted = Vec::new();
+    let mut children = zk.get_children(parent, false)?;
+    children.sort();
+    for child in children.iter().filter(|c| is_run(c, base)) {
+        let path = match parent {
+            "/" => format!("/{}", child),
+            _ => format!("{}/{}", parent, child),
+        };
+        let Some(stat) = zk.exists(&path, false)? else {
+            continue;
+        };
+        let age = now.saturating_sub(Duration::from_millis(stat.ctime as u64));
+        if age <= older_than {
+            log::debug!("Leaving {}, created {} ago", path, ago(age));
+            continue;
+        }
+        log::info!("Deleting {}, created {} ago", path, ago(age));
+        match zk.delete_recursive(&path) {
+            Ok(()) | Err(ZkError::NoNode) => deleted.push(path),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(deleted)
+}
+
+fn ago(age: Duration) -> humantime::FormattedDuration {
+    humantime::format_duration(Duration::from_secs(age.as_secs()))
+}
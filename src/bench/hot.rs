@@ -0,0 +1,179 @@
+use super::mixed::{Op, OpResult};
+use super::{
+    connect, connect_worker, do_bench, observe_op, prepare, think, tolerate, warm_up, with_retry,
+    BenchOption, Budget, Pacer, Window,
+};
+use crate::rng;
+use crate::stats::{serialize_secs, ErrorCounts, Latencies, Timeline};
+use indicatif::ProgressBar;
+use rand::Rng;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use zookeeper::{CreateMode, ZkError};
+
+#[derive(Serialize)]
+pub struct HotResult {
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub ops: Vec<OpResult>,
+    pub conflicts: u64,
+    /// Operations of either type completed in each second
+    pub timeline: Vec<u64>,
+    pub errors: ErrorCounts,
+}
+
+#[derive(Default)]
+struct HotWorker {
+    get: Latencies,
+    set: Latencies,
+    get_corrected: Latencies,
+    set_corrected: Latencies,
+    conflicts: u64,
+    timeline: Timeline,
+    errors: ErrorCounts,
+}
+
+fn hot_path(opt: &BenchOption) -> String {
+    format!("{}/hot", opt.prefix)
+}
+
+pub fn bench(opt: &BenchOption) -> Result<HotResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+    connect(opt)?.create(
+        hot_path(opt).as_str(),
+        opt.value(),
+        opt.acl.acl(),
+        CreateMode::Persistent,
+    )?;
+
+    log::info!(
+        "Running hot znode benchmark, {}% versioned writes",
+        opt.hot_writes
+    );
+    let (_, results) = do_bench(opt, do_hot_bench)?;
+    let elapsed = Window::span(results.iter().map(|(_, window)| *window));
+    let results: Vec<_> = results.into_iter().map(|(w, _)| w).collect();
+
+    let mut total = HotWorker::default();
+    for w in &results {
+        total.get.merge(&w.get);
+        total.set.merge(&w.set);
+        total.get_corrected.merge(&w.get_corrected);
+        total.set_corrected.merge(&w.set_corrected);
+        total.conflicts += w.conflicts;
+        total.errors.merge(&w.errors);
+    }
+    let timelines: Vec<_> = results.into_iter().map(|w| w.timeline).collect();
+
+    let mut ops = Vec::new();
+    for (op, l, c, used) in [
+        (
+            Op::Get,
+            &total.get,
+            &total.get_corrected,
+            opt.hot_writes < 100,
+        ),
+        (
+            Op::Set,
+            &total.set,
+            &total.set_corrected,
+            opt.hot_writes > 0,
+        ),
+    ] {
+        if used {
+            ops.push(OpResult::new(opt, op, l, c)?);
+        }
+    }
+
+    Ok(HotResult {
+        elapsed,
+        ops,
+        conflicts: total.conflicts,
+        timeline: Timeline::merge(&timelines),
+        errors: total.errors,
+    })
+}
+
+fn do_hot_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+) -> Result<(HotWorker, Window), anyhow::Error> {
+    let (zk, mut worker_start, _, _) = connect_worker(opt)?;
+    pb.set_message("Connected");
+
+    let path = hot_path(opt);
+    if opt.warmup.is_some() {
+        pb.set_message("Warming up");
+        worker_start = warm_up(opt, |_| zk.get_data(path.as_str(), false))?;
+    }
+
+    let mut rng = rng::rng();
+    let mut w = HotWorker::default();
+    let mut version = zk.get_data(path.as_str(), false)?.1.version;
+    let worker_start = opt.wait_for_start(tid, worker_start);
+    let mut pacer = Pacer::new(opt);
+    let budget = Budget::new(opt, tid);
+    let mut n = 0;
+    while budget.allows(n) {
+        let intended = pacer.wait();
+        let start = Instant::now();
+        let completed = if rng.gen_range(0..100) < opt.hot_writes {
+            // Writes are conditioned on the last version this worker saw
+            let result = with_retry(opt, || {
+                zk.set_data(path.as_str(), opt.value(), Some(version))
+            });
+            let latency = start.elapsed();
+            observe_op(opt, "set", &path, start, latency, &result)?;
+            match result {
+                Ok(stat) => {
+                    w.set.record(latency);
+                    w.set_corrected.record(start - intended + latency);
+                    version = stat.version;
+                    true
+                }
+                Err(ZkError::BadVersion) => {
+                    w.set.record(latency);
+                    w.set_corrected.record(start - intended + latency);
+                    w.conflicts += 1;
+                    version = zk.get_data(path.as_str(), false)?.1.version;
+                    true
+                }
+                Err(e) => {
+                    tolerate(opt, &mut w.errors, e, budget.planned(n))?;
+                    false
+                }
+            }
+        } else {
+            let result = with_retry(opt, || zk.get_data(path.as_str(), false));
+            let latency = start.elapsed();
+            observe_op(opt, "get", &path, start, latency, &result)?;
+            match result {
+                Ok((_, stat)) => {
+                    w.get.record(latency);
+                    w.get_corrected.record(start - intended + latency);
+                    version = stat.version;
+                    true
+                }
+                Err(e) => {
+                    tolerate(opt, &mut w.errors, e, budget.planned(n))?;
+                    false
+                }
+            }
+        };
+        if completed {
+            w.timeline.record();
+        }
+        n += 1;
+        pb.inc(1);
+        think(opt);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    let window = Window {
+        start: worker_start,
+        end: Instant::now(),
+    };
+    Ok((w, window))
+}
@@ -0,0 +1,76 @@
+use super::{connect, do_bench, merge_latencies, prepare, save_hgrm, share, BenchOption};
+use crate::recipes::lock::Lock;
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use zookeeper::CreateMode;
+
+#[derive(Serialize)]
+pub struct LockResult {
+    pub locks: u32,
+    pub acquisitions: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+}
+
+fn lock_path(opt: &BenchOption, id: u32) -> String {
+    format!("{}/locks/lock-{}", opt.prefix, id)
+}
+
+pub fn bench(opt: &BenchOption) -> Result<LockResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let zk = connect(opt)?;
+    let parent = format!("{}/locks", opt.prefix);
+    for path in std::iter::once(parent).chain((0..opt.locks).map(|id| lock_path(opt, id))) {
+        zk.create(
+            path.as_str(),
+            Vec::new(),
+            opt.parent_acl(),
+            CreateMode::Persistent,
+        )?;
+    }
+
+    log::info!(
+        "Running lock benchmark, {} threads on {} locks",
+        opt.threads,
+        opt.locks
+    );
+    let (elapsed, results) = do_bench(opt, do_lock_bench)?;
+
+    let latencies = merge_latencies(&results);
+    save_hgrm(opt, "acquire", &latencies)?;
+    let latency = latencies.summary();
+
+    Ok(LockResult {
+        locks: opt.locks,
+        acquisitions: latency.count as u32,
+        elapsed,
+        latency,
+    })
+}
+
+fn do_lock_bench(tid: u32, pb: ProgressBar, opt: &BenchOption) -> Result<Latencies, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let path = lock_path(opt, tid % opt.locks);
+    let lock = Lock::new(&zk, path.as_str(), opt.session_timeout).with_acl(opt.acl.acl());
+    let mut latencies = Latencies::default();
+    for _ in share(opt.iteration, opt.threads, tid) {
+        if opt.interrupted() {
+            break;
+        }
+        let start = Instant::now();
+        let guard = lock.acquire()?;
+        latencies.record(start.elapsed());
+        guard.release()?;
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(latencies)
+}
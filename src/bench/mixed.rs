@@ -0,0 +1,361 @@
+use super::{
+    add_mismatches, connect_worker, do_bench, node_path, observe_op, prepare, save_hgrm, share,
+    think, tolerate, warm_up, with_retry, workload, BenchOption, Budget, Pacer, Window, Writes,
+};
+use crate::dist::{KeyChooser, ReadDist};
+use crate::error::BenchError;
+use crate::rng;
+use crate::stats::{serialize_secs, Bucket, ErrorCounts, Latencies, LatencySummary, Timeline};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use zookeeper::CreateMode;
+
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Get,
+    Set,
+    Create,
+    Delete,
+}
+
+impl Op {
+    pub(super) const ALL: [Op; 4] = [Op::Get, Op::Set, Op::Create, Op::Delete];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Get => "get",
+            Op::Set => "set",
+            Op::Create => "create",
+            Op::Delete => "delete",
+        }
+    }
+}
+
+/// Relative weights of each operation, parsed from e.g. `get:70,set:20,create:5,delete:5`
+#[derive(Clone, Debug)]
+pub struct Mix {
+    weights: [u32; Op::ALL.len()],
+}
+
+impl FromStr for Mix {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad mix `{}`", s));
+        let mut weights = [0; Op::ALL.len()];
+        for part in s.split(',') {
+            let (name, weight) = part.split_once(':').ok_or_else(invalid)?;
+            let op = Op::from_str(name.trim(), true).map_err(|_| invalid())?;
+            weights[op as usize] = weight.trim().parse().map_err(|_| invalid())?;
+        }
+        if weights.iter().all(|w| *w == 0) {
+            return Err(invalid());
+        }
+        Ok(Mix { weights })
+    }
+}
+
+impl Mix {
+    /// Share of the operations that write a value, creates and sets.
+    pub(super) fn write_share(&self) -> f64 {
+        let writes = self.weights[Op::Set as usize] + self.weights[Op::Create as usize];
+        writes as f64 / self.weights.iter().sum::<u32>().max(1) as f64
+    }
+}
+
+impl fmt::Display for Mix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        for (op, weight) in Op::ALL.iter().zip(self.weights) {
+            write!(f, "{}{}:{}", sep, op.name(), weight)?;
+            sep = ",";
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Mix {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// Named workloads of `--workload`, after the core workloads of YCSB. There's no ycsb-e as
+/// ZooKeeper has no range scans, nor ycsb-f as there's no read-modify-write in the mix; cas mode
+/// measures that.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Workload {
+    /// Update heavy: half gets, half sets, of zipfian keys
+    YcsbA,
+    /// Read mostly: 95% gets, 5% sets, of zipfian keys
+    YcsbB,
+    /// Read only: gets of zipfian keys
+    YcsbC,
+    /// Read latest: 95% gets of mostly the newest keys, 5% creates
+    YcsbD,
+}
+
+impl Workload {
+    pub fn mix(&self) -> Mix {
+        let weights = match self {
+            Workload::YcsbA => [50, 50, 0, 0],
+            Workload::YcsbB => [95, 5, 0, 0],
+            Workload::YcsbC => [100, 0, 0, 0],
+            Workload::YcsbD => [95, 0, 5, 0],
+        };
+        Mix { weights }
+    }
+
+    pub fn read_dist(&self) -> ReadDist {
+        match self {
+            Workload::YcsbD => ReadDist::Latest(0.99),
+            _ => ReadDist::Zipfian(0.99),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OpResult {
+    pub op: Op,
+    pub latency: LatencySummary,
+    /// Latency since the intended start under `--rate`
+    pub corrected_latency: Option<LatencySummary>,
+    pub histogram: Vec<Bucket>,
+}
+
+impl OpResult {
+    /// Summarizes one operation type and saves its histograms as `<op>.hgrm` and, under
+    /// `--rate`, `<op>-corrected.hgrm`.
+    pub(super) fn new(
+        opt: &BenchOption,
+        op: Op,
+        latencies: &Latencies,
+        corrected: &Latencies,
+    ) -> Result<Self, anyhow::Error> {
+        save_hgrm(opt, op.name(), latencies)?;
+        let corrected_latency = match opt.rate {
+            Some(_) => {
+                save_hgrm(opt, &format!("{}-corrected", op.name()), corrected)?;
+                Some(corrected.summary())
+            }
+            None => None,
+        };
+        Ok(OpResult {
+            op,
+            latency: latencies.summary(),
+            corrected_latency,
+            histogram: latencies.buckets(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct MixedResult {
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub ops: Vec<OpResult>,
+    /// Operations of any type completed in each second
+    pub timeline: Vec<u64>,
+    pub errors: ErrorCounts,
+    /// Values got that weren't what was written, if any were with `--verify`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mismatches: Option<u64>,
+}
+
+pub(super) struct MixedWorker {
+    pub(super) window: Window,
+    pub(super) latencies: [Latencies; Op::ALL.len()],
+    pub(super) corrected: [Latencies; Op::ALL.len()],
+    pub(super) timeline: Timeline,
+    pub(super) errors: ErrorCounts,
+    pub(super) mismatches: Option<u64>,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<MixedResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    log::info!("Populating {} znodes", opt.iteration);
+    // Every worker needs its share of znodes, however long the benchmark then runs
+    let populate = BenchOption {
+        duration: None,
+        ..opt.clone()
+    };
+    workload::measure(&populate, &Writes)?;
+
+    log::info!("Running mixed benchmark");
+    let population: Vec<String> = (0..opt.iteration)
+        .map(|i| node_path(&populate, i))
+        .collect();
+    run(opt, &population, "")
+}
+
+/// Runs the mix over the `population` znodes, split evenly between the workers. `tag` tells
+/// apart the znodes this run creates from those of other runs.
+pub(super) fn run(
+    opt: &BenchOption,
+    population: &[String],
+    tag: &str,
+) -> Result<MixedResult, anyhow::Error> {
+    let (_, results) = do_bench(opt, move |tid, pb, opt| {
+        do_mixed_bench(tid, pb, opt, population, tag)
+    })?;
+    summarize(opt, results, |op, _| opt.mix.weights[op as usize] > 0)
+}
+
+/// Aggregates the workers of a run, reporting the operations `shown` picks out by their
+/// latencies.
+pub(super) fn summarize(
+    opt: &BenchOption,
+    results: Vec<MixedWorker>,
+    shown: impl Fn(Op, &Latencies) -> bool,
+) -> Result<MixedResult, anyhow::Error> {
+    let elapsed = Window::span(results.iter().map(|w| w.window));
+
+    let mut latencies: [Latencies; Op::ALL.len()] = Default::default();
+    let mut corrected: [Latencies; Op::ALL.len()] = Default::default();
+    let mut timelines = Vec::new();
+    let mut errors = ErrorCounts::default();
+    let mut mismatches = None;
+    for worker in results {
+        mismatches = add_mismatches(mismatches, worker.mismatches);
+        for (l, w) in latencies.iter_mut().zip(&worker.latencies) {
+            l.merge(w);
+        }
+        for (l, w) in corrected.iter_mut().zip(&worker.corrected) {
+            l.merge(w);
+        }
+        timelines.push(worker.timeline);
+        errors.merge(&worker.errors);
+    }
+
+    let mut ops = Vec::new();
+    for op in Op::ALL {
+        let i = op as usize;
+        if shown(op, &latencies[i]) {
+            ops.push(OpResult::new(opt, op, &latencies[i], &corrected[i])?);
+        }
+    }
+
+    Ok(MixedResult {
+        elapsed,
+        ops,
+        timeline: Timeline::merge(&timelines),
+        errors,
+        mismatches,
+    })
+}
+
+fn do_mixed_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    population: &[String],
+    tag: &str,
+) -> Result<MixedWorker, anyhow::Error> {
+    let (zk, mut worker_start, _, _) = connect_worker(opt)?;
+    pb.set_message("Connected");
+
+    // Every worker only touches its share of the population and the znodes it created itself
+    let range = share(population.len() as u32, opt.threads, tid);
+    let mut live = population[range.start as usize..range.end as usize].to_vec();
+    let mut created = 0;
+
+    if opt.warmup.is_some() && !live.is_empty() {
+        pb.set_message("Warming up");
+        // Only reads, so the znodes each worker owns stay as populated
+        worker_start = warm_up(opt, |i| {
+            zk.get_data(live[i as usize % live.len()].as_str(), false)
+        })?;
+    }
+
+    let worker_start = opt.wait_for_start(tid, worker_start);
+    let mut timeline = Timeline::default();
+    let mut rng = rng::rng();
+    let dist = WeightedIndex::new(opt.mix.weights)?;
+    // Gets and sets pick keys by `--read-dist`, uniformly if sequential
+    let chooser = KeyChooser::new(opt.read_dist, live.len());
+    let mut latencies: [Latencies; Op::ALL.len()] = Default::default();
+    let mut corrected: [Latencies; Op::ALL.len()] = Default::default();
+    let mut errors = ErrorCounts::default();
+    let mut mismatches = None;
+    let mut pacer = Pacer::new(opt);
+    let budget = Budget::new(opt, tid);
+    let mut n = 0;
+    while budget.allows(n) {
+        let mut op = Op::ALL[dist.sample(&mut rng)];
+        if live.is_empty() && op != Op::Create {
+            op = Op::Create;
+        }
+
+        let path = match op {
+            Op::Get | Op::Set => {
+                let i = chooser.choose(&mut rng, live.len());
+                live[i.unwrap_or_else(|| rng.gen_range(0..live.len()))].clone()
+            }
+            Op::Create => format!("{}m{}{}-{}", opt.node_path_template, tag, tid, created),
+            Op::Delete => live.swap_remove(rng.gen_range(0..live.len())),
+        };
+
+        let intended = pacer.wait();
+        let start = Instant::now();
+        let result = with_retry(opt, || match op {
+            Op::Get => zk.get_data(path.as_str(), false).map(|(data, s)| {
+                opt.check_read(tid, &path, &data, &mut mismatches);
+                Some(s.mzxid)
+            }),
+            Op::Set => zk
+                .set_data(path.as_str(), opt.value_for(&path), None)
+                .map(|s| Some(s.mzxid)),
+            Op::Create => zk
+                .create(
+                    path.as_str(),
+                    opt.value_for(&path),
+                    opt.acl.acl(),
+                    CreateMode::Persistent,
+                )
+                .map(|_| None),
+            Op::Delete => zk.delete(path.as_str(), None).map(|_| None),
+        });
+        let latency = start.elapsed();
+        observe_op(opt, op.name(), &path, start, latency, &result)?;
+        if op == Op::Create {
+            created += 1;
+        }
+        match result {
+            Ok(_) => {
+                latencies[op as usize].record(latency);
+                corrected[op as usize].record(start - intended + latency);
+                timeline.record();
+                if op == Op::Create {
+                    live.push(path);
+                }
+            }
+            Err(e) => tolerate(opt, &mut errors, e, budget.planned(n))?,
+        }
+        n += 1;
+        pb.inc(1);
+        think(opt);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(MixedWorker {
+        window: Window {
+            start: worker_start,
+            end: Instant::now(),
+        },
+        latencies,
+        corrected,
+        timeline,
+        errors,
+        mismatches,
+    })
+}
@@ -0,0 +1,191 @@
+use super::backend::{connect_client, execute_within};
+use super::workload::{self, Operation, Workload};
+use super::{observe_op, tolerate, warm_up, BenchOption, BenchResult, Budget, PhaseWorker, Window};
+use crate::stats::{ErrorCounts, Latencies, Timeline};
+use crate::wire::Traffic;
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Instant;
+
+#[derive(Serialize)]
+pub struct PipelineStep {
+    pub in_flight: u32,
+    pub result: BenchResult,
+}
+
+#[derive(Serialize)]
+pub struct PipelineResult {
+    pub steps: Vec<PipelineStep>,
+}
+
+/// Reruns the standard benchmark with each `--in-flight` depth.
+pub fn bench(opt: &BenchOption) -> Result<PipelineResult, anyhow::Error> {
+    let mut steps = Vec::new();
+    for &in_flight in &opt.in_flight_sweep {
+        log::info!(
+            "Running the standard benchmark with {} requests in flight",
+            in_flight
+        );
+        let step_opt = BenchOption {
+            in_flight: Some(in_flight),
+            hgrm_out: opt
+                .hgrm_out
+                .as_ref()
+                .map(|d| d.join(format!("in-flight-{}", in_flight))),
+            ..opt.clone()
+        };
+        let result = super::bench(&step_opt)?;
+        steps.push(PipelineStep { in_flight, result });
+    }
+    Ok(PipelineResult { steps })
+}
+
+/// The `--in-flight` counterpart of the workload driver: the worker issues operations through
+/// the async client until `in_flight` of them are outstanding, then waits for the oldest. A
+/// session answers in order, so the oldest is always the next to complete. The worker runs
+/// its own single threaded runtime, which starts requests in the order they're issued.
+pub(super) fn work<W: Workload>(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    workload: &W,
+    in_flight: u32,
+) -> Result<PhaseWorker, anyhow::Error> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let start = Instant::now();
+    let (client, connect, member) = runtime.block_on(connect_client(opt))?;
+    let mut worker_start = if opt.exclude_connect {
+        Instant::now()
+    } else {
+        start
+    };
+    pb.set_message("Connected");
+
+    let mut worker = workload.worker(opt, tid);
+    if opt.warmup.is_some() {
+        if let Some(first) = workload.warmup_op(opt, &mut worker, 0) {
+            pb.set_message("Warming up");
+            let mut first = Some(first);
+            worker_start = warm_up(opt, |i| {
+                match first
+                    .take()
+                    .or_else(|| workload.warmup_op(opt, &mut worker, i))
+                {
+                    Some(op) => runtime
+                        .block_on(execute_within(
+                            op,
+                            client.clone(),
+                            opt.op_timeout,
+                            opt.acl.clone(),
+                        ))
+                        .0
+                        .map(drop),
+                    None => Ok(()),
+                }
+            })?;
+        }
+    }
+
+    let worker_start = opt.wait_for_start(tid, worker_start);
+    let mut timeline = Timeline::default();
+    let mut latencies = Latencies::default();
+    let mut errors = ErrorCounts::default();
+    let mut mismatches = None;
+    let mut existing = 0;
+    let mut traffic = Traffic::default();
+    let mut depths: BTreeMap<u32, Latencies> = BTreeMap::new();
+    let budget = Budget::new(opt, tid);
+    runtime.block_on(async {
+        let mut outstanding = VecDeque::new();
+        let (mut issued, mut done, mut exhausted) = (0, 0, false);
+        loop {
+            while !exhausted && outstanding.len() < in_flight as usize && budget.allows(issued) {
+                let Some(op) = workload.next_op(opt, &mut worker, issued) else {
+                    exhausted = true;
+                    break;
+                };
+                let (name, path) = (op.name(), op.path().to_string());
+                let (start, client, timeout) = (Instant::now(), client.clone(), opt.op_timeout);
+                let acl = opt.acl.clone();
+                // Timed as it completes, rather than once the worker gets to it
+                let request = tokio::spawn(async move {
+                    let (result, traffic) = execute_within(op, client, timeout, acl).await;
+                    (result, traffic, start.elapsed())
+                });
+                outstanding.push_back((name, path, start, request));
+                issued += 1;
+            }
+            let Some((name, path, start, request)) = outstanding.pop_front() else {
+                break;
+            };
+            let (result, op_traffic, latency) = request.await?;
+            traffic.merge(&op_traffic);
+            if workload::skipped(opt, name, &result) {
+                existing += 1;
+                if opt.verify {
+                    let get = Operation::Get {
+                        path: path.clone(),
+                        watch: false,
+                    };
+                    let (result, op_traffic) =
+                        execute_within(get, client.clone(), opt.op_timeout, opt.acl.clone()).await;
+                    traffic.merge(&op_traffic);
+                    match result {
+                        Ok(reply) => opt.check_read(
+                            tid,
+                            &path,
+                            &reply.data.unwrap_or_default(),
+                            &mut mismatches,
+                        ),
+                        Err(e) => tolerate(opt, &mut errors, e, budget.planned(done))?,
+                    }
+                }
+                done += 1;
+                pb.inc(1);
+                continue;
+            }
+            observe_op(opt, name, &path, start, latency, &result)?;
+            match result {
+                Ok(reply) => {
+                    latencies.record(latency);
+                    if let Some(data) = &reply.data {
+                        opt.check_read(tid, &path, data, &mut mismatches);
+                    }
+                    timeline.record();
+                    if let Some(depth) = workload::depth(opt, &path) {
+                        depths.entry(depth).or_default().record(latency);
+                    }
+                }
+                Err(e) => tolerate(opt, &mut errors, e, budget.planned(done))?,
+            }
+            done += 1;
+            pb.inc(1);
+            if !opt.quiet {
+                pb.set_message(format!("{} {}", name, path))
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    })?;
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(PhaseWorker {
+        connect,
+        member,
+        // Nothing paces the operations, so none started later than intended
+        corrected: latencies.clone(),
+        latencies,
+        timeline,
+        window: Window {
+            start: worker_start,
+            end: Instant::now(),
+        },
+        traffic,
+        errors,
+        depths,
+        mismatches,
+        existing,
+    })
+}
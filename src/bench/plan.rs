@@ -0,0 +1,222 @@
+use super::{connect_timed, BenchOption, Mode};
+use crate::dist::ValueSize;
+use crate::error::BenchError;
+use crate::stats::{serialize_opt_secs, serialize_secs};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::Duration;
+use zookeeper::{Acl, Permission, ZkError, ZooKeeper};
+
+/// A phase a run would go through, as `--dry-run` plans it.
+#[derive(Serialize)]
+pub struct PlannedPhase {
+    pub name: String,
+    pub threads: u32,
+    /// Operations the phase runs, unless it runs for a duration or the mode decides
+    pub ops: Option<u64>,
+    #[serde(serialize_with = "serialize_opt_secs")]
+    pub duration: Option<Duration>,
+    /// Bytes of values the phase writes, roughly, where it's known
+    pub bytes: Option<u64>,
+}
+
+impl PlannedPhase {
+    /// A phase of the workload driver, where `write_share` of the operations write a value.
+    pub(super) fn new(opt: &BenchOption, name: &str, write_share: f64) -> Self {
+        let ops = match opt.duration {
+            Some(_) => None,
+            None => Some(opt.iteration as u64),
+        };
+        let bytes = match write_share {
+            0.0 => Some(0),
+            _ => ops.map(|ops| (ops as f64 * write_share * opt.mean_value_size()) as u64),
+        };
+        PlannedPhase {
+            name: name.to_string(),
+            threads: opt.threads,
+            ops,
+            duration: opt.duration,
+            bytes,
+        }
+    }
+}
+
+/// What the sessions may do below the paths the run works in.
+#[derive(Serialize)]
+pub struct Access {
+    #[serde(serialize_with = "serialize_secs")]
+    pub connect: Duration,
+    pub paths: Vec<PathAccess>,
+}
+
+/// What the sessions may do below a path, by the ACL of the path or of the nearest ancestor that
+/// exists when the path doesn't yet.
+#[derive(Serialize)]
+pub struct PathAccess {
+    pub path: String,
+    pub acl: Vec<String>,
+    /// What the entries naming the sessions grant them, as far as they can tell
+    pub granted: String,
+    pub needed: String,
+    /// Entries of schemes the sessions can't tell whether they match, like ip and x509
+    pub undecided: usize,
+}
+
+/// The phases `opt` would run, without running them.
+pub fn plan(opt: &BenchOption) -> Vec<PlannedPhase> {
+    let variants: Vec<(String, BenchOption)> = match opt.mode {
+        Mode::Standard if opt.scenario.is_some() => {
+            return opt.scenario.as_ref().unwrap().plan(opt);
+        }
+        Mode::Standard if !opt.node_size_sweep.is_empty() => opt
+            .node_size_sweep
+            .iter()
+            .map(|&size| {
+                let step_opt = BenchOption {
+                    node_size: ValueSize::Fixed(size),
+                    ..opt.clone()
+                };
+                (format!("{} byte values ", size), step_opt)
+            })
+            .collect(),
+        Mode::Standard if !opt.in_flight_sweep.is_empty() => opt
+            .in_flight_sweep
+            .iter()
+            .map(|&depth| (format!("{} in flight ", depth), opt.clone()))
+            .collect(),
+        Mode::Standard => vec![(String::new(), opt.clone())],
+        Mode::ConcurrencySweep => opt
+            .thread_counts
+            .iter()
+            .map(|&threads| {
+                let step_opt = BenchOption {
+                    threads,
+                    ..opt.clone()
+                };
+                (format!("{} threads ", threads), step_opt)
+            })
+            .collect(),
+        Mode::Write => return vec![PlannedPhase::new(opt, "write", 1.0)],
+        Mode::Read => return vec![PlannedPhase::new(opt, "read", 0.0)],
+        Mode::Mixed => {
+            let populate = BenchOption {
+                duration: None,
+                ..opt.clone()
+            };
+            return vec![
+                PlannedPhase::new(&populate, "populate", 1.0),
+                PlannedPhase::new(opt, "mixed", opt.mix.write_share()),
+            ];
+        }
+        mode => {
+            let name = mode.to_possible_value().unwrap().get_name().to_string();
+            return vec![PlannedPhase {
+                name,
+                threads: opt.threads,
+                ops: None,
+                duration: opt.duration,
+                bytes: None,
+            }];
+        }
+    };
+    let mut phases = Vec::new();
+    for (label, step_opt) in variants {
+        let write_opt = step_opt.for_phase(step_opt.write_threads, step_opt.write_iteration);
+        let read_opt = step_opt.for_phase(step_opt.read_threads, step_opt.read_iteration);
+        phases.push(PlannedPhase::new(
+            &write_opt,
+            &(label.clone() + "write"),
+            1.0,
+        ));
+        phases.push(PlannedPhase::new(&read_opt, &(label + "read"), 0.0));
+    }
+    phases
+}
+
+/// Checks what it can without writing anything: that sessions connect and authenticate, that
+/// the ACL of the prefix lets them create, read and delete znodes there, and that of
+/// `--read-prefix` read them, the read modes only reading below the one or the other. Entries
+/// naming the sessions are those for anyone and, with `--digest`, for its user.
+pub fn check(opt: &BenchOption) -> Result<Access, anyhow::Error> {
+    let (zk, connect, _) = connect_timed(opt)?;
+    // A session whose credentials the server refused fails its first operation
+    zk.exists("/", false)?;
+
+    let mut paths = Vec::new();
+    if !matches!(opt.mode, Mode::Read | Mode::ReadOnly) {
+        paths.push(check_path(opt, &zk, &opt.prefix, false)?);
+        if let Some(read_prefix) = &opt.read_prefix {
+            paths.push(check_path(opt, &zk, read_prefix, true)?);
+        }
+    } else {
+        let read_prefix = opt.read_prefix.as_ref().unwrap_or(&opt.prefix);
+        paths.push(check_path(opt, &zk, read_prefix, true)?);
+    }
+    Ok(Access { connect, paths })
+}
+
+/// Checks the ACL of `path` lets the sessions read what's below it or, unless `reads_only`,
+/// create and delete znodes there, or create it below the nearest ancestor that exists.
+fn check_path(
+    opt: &BenchOption,
+    zk: &ZooKeeper,
+    path: &str,
+    reads_only: bool,
+) -> Result<PathAccess, anyhow::Error> {
+    let mut path = path.to_string();
+    let (acl, needed) = loop {
+        match zk.get_acl(&path) {
+            Ok((acl, _)) if reads_only => break (acl, Permission::READ),
+            Ok((acl, _)) if path == opt.prefix => {
+                break (
+                    acl,
+                    Permission::READ | Permission::CREATE | Permission::DELETE,
+                )
+            }
+            Ok((acl, _)) => break (acl, Permission::CREATE),
+            Err(ZkError::NoNode) if reads_only => {
+                return Err(BenchError::InvalidOption(format!("{} doesn't exist", path)).into());
+            }
+            Err(ZkError::NoNode) => {
+                path = match path.rsplit_once('/') {
+                    Some(("", _)) => "/".to_string(),
+                    Some((parent, _)) => parent.to_string(),
+                    None => return Err(ZkError::NoNode.into()),
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let user = opt
+        .digest
+        .as_ref()
+        .and_then(|d| d.split_once(':'))
+        .map(|(user, _)| user);
+    let ours = |a: &&Acl| match a.scheme.as_str() {
+        "world" => a.id == "anyone",
+        "digest" => user.is_some_and(|u| a.id.split_once(':').is_some_and(|(id, _)| id == u)),
+        _ => false,
+    };
+    let granted = acl
+        .iter()
+        .filter(ours)
+        .fold(Permission::NONE, |p, a| p | a.perms);
+    let undecided = acl
+        .iter()
+        .filter(|a| !matches!(a.scheme.as_str(), "world" | "digest"))
+        .count();
+    if !granted.can(needed) && undecided == 0 {
+        return Err(BenchError::InvalidOption(format!(
+            "the ACL of {} grants these sessions {}, short of the {} the run needs",
+            path, granted, needed
+        ))
+        .into());
+    }
+    Ok(PathAccess {
+        path,
+        acl: acl.iter().map(Acl::to_string).collect(),
+        granted: granted.to_string(),
+        needed: needed.to_string(),
+        undecided,
+    })
+}
@@ -0,0 +1,135 @@
+use super::backend::{connect_client, zk_error};
+use super::{new_progress_style, node_index, node_path, prepare, share, BenchOption, Overall};
+use crate::error::BenchError;
+use indicatif::ProgressBar;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime;
+use zookeeper_client::{CreateMode, Error};
+
+/// Bytes of values a multi request carries at most, well within the 1MiB jute.maxbuffer servers
+/// take by default
+const MAX_MULTI_BYTES: usize = 512 * 1024;
+
+pub struct Populated {
+    pub created: u64,
+    /// Znodes `--resume` found it had no need to create
+    pub existing: u64,
+    pub elapsed: Duration,
+}
+
+/// Creates the znodes of the write phase, laid out by `--tree` or `--shards` and with values of
+/// `--node-size`, without measuring them: each worker commits up to `batch` creates at a time in
+/// one multi request, fewer when their values wouldn't fit. A multi succeeds or fails as a
+/// whole, so a znode that exists fails its batch, unless `--resume` creates the batch's znodes
+/// one at a time then, skipping those that exist. SIGINT or SIGTERM stops the workers after
+/// their running batch.
+pub fn run(opt: &BenchOption, batch: u32) -> Result<Populated, anyhow::Error> {
+    if opt.ephemeral {
+        return Err(BenchError::InvalidOption(
+            "populate doesn't take --ephemeral, as its znodes would go with its sessions"
+                .to_string(),
+        )
+        .into());
+    }
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    log::info!(
+        "Creating {} znodes below {} in batches of {}",
+        opt.iteration,
+        opt.prefix,
+        batch
+    );
+    let bar = opt.progress();
+    let bars: Vec<_> = (0..opt.threads)
+        .map(|tid| {
+            let share = share(opt.iteration, opt.threads, tid).len();
+            let pb = bar.add(ProgressBar::new(share as u64));
+            pb.set_style(new_progress_style());
+            pb.set_message(format!("Worker #{}", tid));
+            pb
+        })
+        .collect();
+    let overall = Overall::new(&bar, &bars, Some(opt.iteration as u64));
+    let start = Instant::now();
+    let (created, existing) = thread::scope(|s| {
+        s.spawn(|| overall.follow());
+        let workers: Vec<_> = (0..opt.threads)
+            .zip(bars)
+            .map(|(tid, pb)| s.spawn(move || create(opt, tid, batch, pb)))
+            .collect();
+        let counts = workers
+            .into_iter()
+            .try_fold((0, 0), |(created, existing), w| {
+                let (c, e) = w.join().unwrap()?;
+                Ok::<_, anyhow::Error>((created + c, existing + e))
+            });
+        overall.finish();
+        counts
+    })?;
+    Ok(Populated {
+        created,
+        existing,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Creates a worker's share of the znodes, returning how many it created and how many existed.
+fn create(
+    opt: &BenchOption,
+    tid: u32,
+    batch: u32,
+    pb: ProgressBar,
+) -> Result<(u64, u64), anyhow::Error> {
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let (client, _, _) = runtime.block_on(connect_client(opt))?;
+    let options = CreateMode::Persistent.with_acls(opt.acl.client_acls());
+
+    let share = share(opt.iteration, opt.threads, tid).len() as u32;
+    let (mut n, mut existing, mut mismatches) = (0, 0, None);
+    while n < share && !opt.interrupted() {
+        let mut creates = Vec::new();
+        let mut bytes = 0;
+        while n < share && creates.len() < batch as usize {
+            let path = node_path(opt, node_index(opt, tid, n));
+            let value = opt.value_for(&path);
+            if !creates.is_empty() && bytes + value.len() > MAX_MULTI_BYTES {
+                break;
+            }
+            bytes += value.len();
+            creates.push((path, value));
+            n += 1;
+        }
+
+        let mut multi = client.new_multi_writer();
+        for (path, value) in &creates {
+            multi.add_create(path, value, &options).map_err(zk_error)?;
+        }
+        match runtime.block_on(multi.commit()).map_err(Error::from) {
+            Ok(_) => {}
+            Err(Error::NodeExists) if opt.resume => {
+                for (path, value) in &creates {
+                    match runtime.block_on(client.create(path, value, &options)) {
+                        Ok(_) => {}
+                        Err(Error::NodeExists) => {
+                            existing += 1;
+                            if opt.verify {
+                                let (data, _) =
+                                    runtime.block_on(client.get_data(path)).map_err(zk_error)?;
+                                opt.check_read(tid, path, &data, &mut mismatches);
+                            }
+                        }
+                        Err(e) => return Err(zk_error(e).into()),
+                    }
+                }
+            }
+            Err(e) => return Err(zk_error(e).into()),
+        }
+        pb.inc(creates.len() as u64);
+    }
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok((n as u64 - existing, existing))
+}
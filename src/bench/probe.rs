@@ -0,0 +1,106 @@
+use super::{connect, do_bench, prepare, save_hgrm, share, BenchOption};
+use crate::dist::ValueSize;
+use crate::stats::{Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::time::Instant;
+use zookeeper::CreateMode;
+
+#[derive(Serialize)]
+pub struct ProbeStep {
+    pub size: usize,
+    pub create: LatencySummary,
+    pub get: LatencySummary,
+}
+
+#[derive(Serialize)]
+pub struct ProbeResult {
+    pub steps: Vec<ProbeStep>,
+    /// First payload size that failed and the error it failed with
+    pub failure: Option<(usize, String)>,
+}
+
+#[derive(Default)]
+struct ProbeWorker {
+    create: Latencies,
+    get: Latencies,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<ProbeResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let mut steps = Vec::new();
+    let mut failure = None;
+    for &size in &opt.probe_sizes {
+        if opt.interrupted() {
+            break;
+        }
+        log::info!("Probing {} byte payloads", size);
+        let step_opt = BenchOption {
+            node_size: ValueSize::Fixed(size),
+            node_value: opt.payload_pattern.generate(size),
+            payload_files: None,
+            ..opt.clone()
+        };
+        match do_bench(&step_opt, do_probe_bench) {
+            Ok((_, results)) => {
+                let mut w = ProbeWorker::default();
+                for r in &results {
+                    w.create.merge(&r.create);
+                    w.get.merge(&r.get);
+                }
+                save_hgrm(opt, format!("create-{}", size).as_str(), &w.create)?;
+                save_hgrm(opt, format!("get-{}", size).as_str(), &w.get)?;
+                steps.push(ProbeStep {
+                    size,
+                    create: w.create.summary(),
+                    get: w.get.summary(),
+                });
+            }
+            // Sizes are probed in ascending order, anything larger fails as well
+            Err(e) => {
+                failure = Some((size, e.to_string()));
+                break;
+            }
+        }
+    }
+
+    Ok(ProbeResult { steps, failure })
+}
+
+fn do_probe_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+) -> Result<ProbeWorker, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    // Nodes are deleted right away so large payloads don't pile up on the server
+    let mut w = ProbeWorker::default();
+    for i in share(opt.iteration, opt.threads, tid) {
+        if opt.interrupted() {
+            break;
+        }
+        let path = format!("{}{}-{}", opt.node_path_template, opt.node_value.len(), i);
+        let start = Instant::now();
+        zk.create(
+            path.as_str(),
+            opt.value(),
+            opt.acl.acl(),
+            CreateMode::Persistent,
+        )?;
+        w.create.record(start.elapsed());
+
+        let start = Instant::now();
+        zk.get_data(path.as_str(), false)?;
+        w.get.record(start.elapsed());
+
+        zk.delete(path.as_str(), None)?;
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(w)
+}
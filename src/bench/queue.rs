@@ -0,0 +1,176 @@
+use super::{connect, do_bench, prepare, save_hgrm, share, BenchOption};
+use crate::error::BenchError;
+use crate::recipes::queue::Queue;
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zookeeper::CreateMode;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Serialize)]
+pub struct QueueResult {
+    pub producers: u32,
+    pub consumers: u32,
+    pub items: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub enqueue_elapsed: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub dequeue_elapsed: Duration,
+    pub enqueue_latency: LatencySummary,
+    pub end_to_end_latency: LatencySummary,
+}
+
+struct QueueWorker {
+    producer: bool,
+    elapsed: Duration,
+    latencies: Latencies,
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+pub fn bench(opt: &BenchOption) -> Result<QueueResult, anyhow::Error> {
+    if opt.consumers >= opt.threads {
+        return Err(BenchError::InvalidOption(
+            "--consumers must be less than --threads to leave room for producers".to_string(),
+        )
+        .into());
+    }
+    let producers = opt.threads - opt.consumers;
+    let items = opt.iteration;
+
+    log::info!("Preparing...");
+    prepare(opt)?;
+    let path = format!("{}/queue", opt.prefix);
+    connect(opt)?.create(
+        path.as_str(),
+        Vec::new(),
+        opt.parent_acl(),
+        CreateMode::Persistent,
+    )?;
+
+    log::info!(
+        "Running queue benchmark, {} producers and {} consumers",
+        producers,
+        opt.consumers
+    );
+    let claimed = AtomicU32::new(0);
+    let (path, claimed) = (&path, &claimed);
+    let (_, results) = do_bench(opt, move |tid, pb, opt| {
+        if tid < producers {
+            do_produce(
+                tid,
+                pb,
+                opt,
+                path,
+                share(items, producers, tid).len() as u32,
+            )
+        } else {
+            do_consume(tid, pb, opt, path, claimed, items)
+        }
+    })?;
+
+    let mut enqueue_elapsed = Duration::ZERO;
+    let mut dequeue_elapsed = Duration::ZERO;
+    let mut enqueue = Latencies::default();
+    let mut end_to_end = Latencies::default();
+    for w in &results {
+        if w.producer {
+            enqueue_elapsed = enqueue_elapsed.max(w.elapsed);
+            enqueue.merge(&w.latencies);
+        } else {
+            dequeue_elapsed = dequeue_elapsed.max(w.elapsed);
+            end_to_end.merge(&w.latencies);
+        }
+    }
+
+    save_hgrm(opt, "enqueue", &enqueue)?;
+    save_hgrm(opt, "end-to-end", &end_to_end)?;
+
+    Ok(QueueResult {
+        producers,
+        consumers: opt.consumers,
+        items,
+        enqueue_elapsed,
+        dequeue_elapsed,
+        enqueue_latency: enqueue.summary(),
+        end_to_end_latency: end_to_end.summary(),
+    })
+}
+
+fn do_produce(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    path: &str,
+    count: u32,
+) -> Result<QueueWorker, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let queue = Queue::new(&zk, path).with_acl(opt.acl.acl());
+    let mut latencies = Latencies::default();
+    let start = Instant::now();
+    for _ in 0..count {
+        if opt.interrupted() {
+            break;
+        }
+        // Items carry their enqueue time so consumers can measure end-to-end latency
+        let mut item = now_nanos().to_be_bytes().to_vec();
+        item.extend_from_slice(&opt.value());
+        let op_start = Instant::now();
+        queue.offer(item)?;
+        latencies.record(op_start.elapsed());
+        pb.inc(1);
+    }
+    let elapsed = start.elapsed();
+
+    pb.finish_with_message(format!("Producer #{} finish", tid));
+    Ok(QueueWorker {
+        producer: true,
+        elapsed,
+        latencies,
+    })
+}
+
+fn do_consume(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    path: &str,
+    claimed: &AtomicU32,
+    items: u32,
+) -> Result<QueueWorker, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let queue = Queue::new(&zk, path).with_acl(opt.acl.acl());
+    let mut latencies = Latencies::default();
+    let start = Instant::now();
+    let mut elapsed = Duration::ZERO;
+    while claimed.load(Ordering::Acquire) < items && !opt.interrupted() {
+        let Some(item) = queue.take(POLL_INTERVAL)? else {
+            continue;
+        };
+        let enqueued = u64::from_be_bytes(item[..8].try_into()?);
+        latencies.record(Duration::from_nanos(now_nanos().saturating_sub(enqueued)));
+        claimed.fetch_add(1, Ordering::AcqRel);
+        pb.inc(1);
+        // Waiting for items that another consumer ends up claiming is not dequeue time
+        elapsed = start.elapsed();
+    }
+
+    pb.finish_with_message(format!("Consumer #{} finish", tid));
+    Ok(QueueWorker {
+        producer: false,
+        elapsed,
+        latencies,
+    })
+}
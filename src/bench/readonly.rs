@@ -0,0 +1,46 @@
+use super::{connect, workload, BenchOption, ListedReads, PhaseResult, ReadKeys};
+use crate::error::BenchError;
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use zookeeper::ZooKeeperExt;
+
+#[derive(Serialize)]
+pub struct ReadOnlyResult {
+    pub read: PhaseResult,
+    /// Share of reads that succeeded
+    pub availability: f64,
+    /// Sessions servers accepted, and how many of them read-only
+    pub handshakes: u64,
+    pub read_only_handshakes: u64,
+}
+
+/// Reads the znodes below `--read-prefix`, or the prefix a previous run left behind, over
+/// sessions that servers without quorum may accept read-only. Failed reads are counted rather
+/// than failing workers, as they're what the benchmark measures.
+pub fn bench(opt: &BenchOption) -> Result<ReadOnlyResult, anyhow::Error> {
+    let prefix = opt.read_prefix.as_ref().unwrap_or(&opt.prefix);
+    let paths = connect(opt)?.get_children_recursive(prefix)?;
+    let paths = &paths[1..];
+    if paths.is_empty() {
+        return Err(BenchError::InvalidOption(format!("no znodes below {}", prefix)).into());
+    }
+
+    log::info!("Running read-only benchmark over {} znodes", paths.len());
+    let opt = BenchOption {
+        max_error_rate: 1.0,
+        ..opt.clone()
+    };
+    let keys = ReadKeys::first(&opt, paths.len() as u32);
+    let reads = ListedReads { keys: &keys, paths };
+    let workers = workload::measure(&opt, &reads)?;
+    let read = PhaseResult::new(&opt, "read-only", workers)?;
+
+    let attempts = read.ops as u64 + read.errors.total();
+    let handshakes = opt.handshakes.as_ref().unwrap();
+    Ok(ReadOnlyResult {
+        availability: read.ops as f64 / attempts.max(1) as f64,
+        handshakes: handshakes.total.load(Ordering::Relaxed),
+        read_only_handshakes: handshakes.read_only.load(Ordering::Relaxed),
+        read,
+    })
+}
@@ -0,0 +1,276 @@
+use super::mixed::{self, Mix, MixedResult};
+use super::plan::PlannedPhase;
+use super::{
+    connect, prepare, workload, BenchOption, ListedReads, PhaseResult, ReadKeys, ThinkTime, Writes,
+};
+use crate::dist::{ReadDist, ValueSize};
+use crate::error::BenchError;
+use crate::stats::{serialize_opt_secs, serialize_secs};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use zookeeper::{ZkError, ZooKeeperExt};
+
+/// What a phase of a scenario does.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PhaseKind {
+    /// Create znodes after those of the earlier phases
+    Create,
+    /// Read the znodes created so far
+    Read,
+    /// Run a mix of operations over the znodes created so far
+    Mixed,
+    /// Delete everything under the prefix
+    Cleanup,
+}
+
+/// One phase of a `--scenario`, falling back to the command line for what it leaves out.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseSpec {
+    name: String,
+    kind: PhaseKind,
+    threads: Option<u32>,
+    iterations: Option<u32>,
+    #[serde(
+        default,
+        deserialize_with = "parse_duration",
+        serialize_with = "serialize_opt_secs"
+    )]
+    duration: Option<Duration>,
+    rate: Option<f64>,
+    #[serde(default, deserialize_with = "parse")]
+    mix: Option<Mix>,
+    #[serde(default, deserialize_with = "parse")]
+    think_time: Option<ThinkTime>,
+    #[serde(default, deserialize_with = "parse")]
+    read_dist: Option<ReadDist>,
+    #[serde(default, deserialize_with = "parse")]
+    node_size: Option<ValueSize>,
+}
+
+/// Phases of a `--scenario` file, run in order instead of the write and read phases.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(rename = "phase")]
+    phases: Vec<PhaseSpec>,
+}
+
+fn parse<'de, D, T>(d: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Option::<String>::deserialize(d)?
+        .map(|s| s.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+fn parse_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|s| humantime::parse_duration(&s).map_err(de::Error::custom))
+        .transpose()
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let invalid = |e: &dyn fmt::Display| {
+            BenchError::InvalidOption(format!("bad scenario {}, {}", path.display(), e))
+        };
+        let scenario: Scenario =
+            toml::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(&e))?;
+        if scenario.phases.is_empty() {
+            return Err(invalid(&"it has no phases").into());
+        }
+        for spec in &scenario.phases {
+            if spec.kind == PhaseKind::Create && spec.duration.is_some() {
+                return Err(
+                    invalid(&format!("create phase {} takes no duration", spec.name)).into(),
+                );
+            }
+            if spec.threads == Some(0) {
+                return Err(invalid(&format!("phase {} has no threads", spec.name)).into());
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// The phases `--dry-run` shows, each named after its kind and name.
+    pub fn plan(&self, opt: &BenchOption) -> Vec<PlannedPhase> {
+        self.phases
+            .iter()
+            .map(|spec| {
+                let phase_opt = spec.apply(opt);
+                let kind = format!("{:?}", spec.kind).to_lowercase();
+                let name = format!("{} {}", kind, spec.name);
+                match spec.kind {
+                    PhaseKind::Create => PlannedPhase::new(&phase_opt, &name, 1.0),
+                    PhaseKind::Read => PlannedPhase::new(&phase_opt, &name, 0.0),
+                    PhaseKind::Mixed => {
+                        PlannedPhase::new(&phase_opt, &name, phase_opt.mix.write_share())
+                    }
+                    PhaseKind::Cleanup => PlannedPhase {
+                        name,
+                        threads: 1,
+                        ops: None,
+                        duration: None,
+                        bytes: Some(0),
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+impl PhaseSpec {
+    /// The command line options with those this phase sets instead. A phase runs for its
+    /// duration if it has one, else for its iterations, else as long as the command line says.
+    /// Create phases always run for their iterations, for the next to know where to go on.
+    fn apply(&self, opt: &BenchOption) -> BenchOption {
+        let (iteration, duration) = match (self.duration, self.iterations) {
+            (Some(d), _) => (opt.iteration, Some(d)),
+            (None, Some(n)) => (n, None),
+            (None, None) if self.kind == PhaseKind::Create => (opt.iteration, None),
+            (None, None) => (opt.iteration, opt.duration),
+        };
+        let node_size = self.node_size.unwrap_or(opt.node_size);
+        BenchOption {
+            threads: self.threads.unwrap_or(opt.threads),
+            iteration,
+            duration,
+            rate: self.rate.or(opt.rate),
+            mix: self.mix.clone().unwrap_or_else(|| opt.mix.clone()),
+            think_time: self.think_time.or(opt.think_time),
+            read_dist: self.read_dist.unwrap_or(opt.read_dist),
+            node_size,
+            node_value: match node_size == opt.node_size {
+                true => opt.node_value.clone(),
+                false => opt.payload_pattern.generate(node_size.max()),
+            },
+            // Phases save histograms under names of their own, which may repeat across phases
+            hgrm_out: opt.hgrm_out.as_ref().map(|d| d.join(&self.name)),
+            ..opt.clone()
+        }
+    }
+}
+
+/// What a phase measured, by its kind.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Outcome {
+    Phase(Box<PhaseResult>),
+    Mixed(MixedResult),
+    Cleanup {
+        #[serde(serialize_with = "serialize_secs")]
+        elapsed: Duration,
+    },
+}
+
+#[derive(Serialize)]
+pub struct ScenarioPhase {
+    pub name: String,
+    pub kind: PhaseKind,
+    pub result: Outcome,
+}
+
+#[derive(Serialize)]
+pub struct ScenarioResult {
+    pub phases: Vec<ScenarioPhase>,
+}
+
+pub fn bench(opt: &BenchOption, scenario: &Scenario) -> Result<ScenarioResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    // Znodes the create phases made so far, and the index the next one goes on from
+    let mut population = Vec::new();
+    let mut next_index = 0;
+    let mut phases = Vec::new();
+    for (i, spec) in scenario.phases.iter().enumerate() {
+        let phase_opt = spec.apply(opt);
+        log::info!("Running {:?} phase {}", spec.kind, spec.name);
+        let result = opt.phase(&spec.name, || {
+            Ok(match spec.kind {
+                PhaseKind::Create => {
+                    let create_opt = BenchOption {
+                        index_base: next_index,
+                        ..phase_opt
+                    };
+                    let workers = workload::measure(&create_opt, &Writes)?;
+                    next_index += create_opt.iteration;
+                    // Of those it tried, the creates that failed left no znode to read
+                    population = created(opt)?;
+                    let result = PhaseResult::new(&create_opt, &spec.name, workers)?;
+                    Outcome::Phase(Box::new(result))
+                }
+                PhaseKind::Read => {
+                    let keys = ReadKeys::first(&phase_opt, population.len() as u32);
+                    let reads = ListedReads {
+                        keys: &keys,
+                        paths: &population,
+                    };
+                    let workers = workload::measure(&phase_opt, &reads)?;
+                    let result = PhaseResult::new(&phase_opt, &spec.name, workers)?;
+                    Outcome::Phase(Box::new(result))
+                }
+                // Deletes leave gaps in the population, which later phases count as errors
+                PhaseKind::Mixed => {
+                    Outcome::Mixed(mixed::run(&phase_opt, &population, &format!("{}-", i))?)
+                }
+                PhaseKind::Cleanup => {
+                    let zk = connect(opt)?;
+                    let start = Instant::now();
+                    match zk.delete_recursive(opt.prefix.as_str()) {
+                        Ok(_) | Err(ZkError::NoNode) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                    let elapsed = start.elapsed();
+                    population.clear();
+                    next_index = 0;
+                    if i + 1 < scenario.phases.len() {
+                        prepare(opt)?;
+                    }
+                    Outcome::Cleanup { elapsed }
+                }
+            })
+        })?;
+        phases.push(ScenarioPhase {
+            name: spec.name.clone(),
+            kind: spec.kind,
+            result,
+        });
+    }
+    Ok(ScenarioResult { phases })
+}
+
+/// The znodes the create phases made below the prefix and are still there, oldest first.
+fn created(opt: &BenchOption) -> Result<Vec<String>, anyhow::Error> {
+    let paths = match connect(opt)?.get_children_recursive(opt.prefix.as_str()) {
+        Ok(paths) => paths,
+        Err(ZkError::NoNode) => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // Named like the template with their index, unlike the parents, warmups and mixed creates
+    let stem = opt
+        .node_path_template
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let mut created: Vec<(u32, String)> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.rsplit('/').next()?;
+            let index = name.strip_prefix(stem)?.parse().ok()?;
+            Some((index, path))
+        })
+        .collect();
+    created.sort();
+    Ok(created.into_iter().map(|(_, path)| path).collect())
+}
@@ -0,0 +1,176 @@
+use super::mixed::{self, MixedResult, MixedWorker, Op};
+use super::{
+    connect_worker, do_bench, observe_op, prepare, think, tolerate, with_retry, BenchOption,
+    Budget, Pacer, Window,
+};
+use crate::error::BenchError;
+use crate::rng;
+use crate::stats::{ErrorCounts, Latencies, Timeline};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use rand::Rng;
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::time::Instant;
+use zookeeper::CreateMode;
+
+/// Function of a `--script` that picks every operation
+const OP_FN: &str = "op";
+
+/// One operation as the script returned it, e.g. `#{ op: "create", path: "/a", size: 1024 }`.
+struct ScriptOp {
+    op: Op,
+    path: String,
+    /// Value of sets and creates: the script's `data`, or `size` bytes of the generated value
+    value: Vec<u8>,
+}
+
+impl ScriptOp {
+    fn parse(opt: &BenchOption, map: Map) -> Result<Self, BenchError> {
+        let invalid = |what: &str| BenchError::InvalidOption(format!("script returned {}", what));
+        let field = |name: &str| map.get(name).cloned();
+        let op = field("op")
+            .and_then(|v| v.into_string().ok())
+            .ok_or_else(|| invalid("no op"))?;
+        let op = Op::from_str(&op, true).map_err(|_| invalid(&format!("unknown op `{}`", op)))?;
+        let path = field("path")
+            .and_then(|v| v.into_string().ok())
+            .ok_or_else(|| invalid("no path"))?;
+        let value = match (field("data"), field("size")) {
+            (Some(data), _) => data
+                .into_string()
+                .map_err(|_| invalid("data that isn't a string"))?
+                .into_bytes(),
+            (None, Some(size)) => {
+                let size = size
+                    .as_int()
+                    .map_err(|_| invalid("a size that isn't a number"))?;
+                let size = usize::try_from(size).map_err(|_| invalid("a negative size"))?;
+                if size > opt.node_value.len() {
+                    opt.payload_pattern.generate(size)
+                } else {
+                    opt.node_value[..size].to_vec()
+                }
+            }
+            (None, None) => opt.value(),
+        };
+        Ok(ScriptOp { op, path, value })
+    }
+}
+
+/// The engine scripts run in, with `rand_int(min, max)` drawing from the worker's random numbers
+/// so `--seed` covers scripts too.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.register_fn("rand_int", |min: i64, max: i64| {
+        rng::rng().gen_range(min..=max.max(min))
+    });
+    engine
+}
+
+pub fn bench(opt: &BenchOption) -> Result<MixedResult, anyhow::Error> {
+    let path = opt
+        .script
+        .as_ref()
+        .ok_or_else(|| BenchError::InvalidOption("script mode needs a --script".to_string()))?;
+    let engine = engine();
+    let ast = engine
+        .compile_file(path.clone())
+        .map_err(|e| BenchError::InvalidOption(format!("bad script {}, {}", path.display(), e)))?;
+
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    log::info!("Running script {}", path.display());
+    let (engine, ast) = (&engine, &ast);
+    let (_, results) = do_bench(opt, move |tid, pb, opt| {
+        do_script_bench(tid, pb, opt, engine, ast)
+    })?;
+    mixed::summarize(opt, results, |_, l| l.summary().count > 0)
+}
+
+fn do_script_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    engine: &Engine,
+    ast: &AST,
+) -> Result<MixedWorker, anyhow::Error> {
+    let (zk, worker_start, _, _) = connect_worker(opt)?;
+    pb.set_message("Connected");
+
+    // Constants of the scope are visible inside script functions
+    let mut scope = Scope::new();
+    scope.push_constant("PREFIX", opt.prefix.clone());
+    scope.push_constant("THREADS", opt.threads as i64);
+
+    let worker_start = opt.wait_for_start(tid, worker_start);
+    let mut timeline = Timeline::default();
+    let mut latencies: [Latencies; Op::ALL.len()] = Default::default();
+    let mut corrected: [Latencies; Op::ALL.len()] = Default::default();
+    let mut errors = ErrorCounts::default();
+    let mut pacer = Pacer::new(opt);
+    let budget = Budget::new(opt, tid);
+    let mut n = 0;
+    while budget.allows(n) {
+        let next: Dynamic = engine
+            .call_fn(&mut scope, ast, OP_FN, (tid as i64, n as i64))
+            .map_err(|e| anyhow::anyhow!("script failed in worker #{}, {}", tid, e))?;
+        // Scripts end a worker early by returning nothing
+        if next.is_unit() {
+            break;
+        }
+        let map = next
+            .try_cast::<Map>()
+            .ok_or_else(|| BenchError::InvalidOption("script returned no map".to_string()))?;
+        let ScriptOp { op, path, value } = ScriptOp::parse(opt, map)?;
+
+        let intended = pacer.wait();
+        let start = Instant::now();
+        let result = with_retry(opt, || match op {
+            Op::Get => zk
+                .get_data(path.as_str(), false)
+                .map(|(_, s)| Some(s.mzxid)),
+            Op::Set => zk
+                .set_data(path.as_str(), value.clone(), None)
+                .map(|s| Some(s.mzxid)),
+            Op::Create => zk
+                .create(
+                    path.as_str(),
+                    value.clone(),
+                    opt.acl.acl(),
+                    CreateMode::Persistent,
+                )
+                .map(|_| None),
+            Op::Delete => zk.delete(path.as_str(), None).map(|_| None),
+        });
+        let latency = start.elapsed();
+        observe_op(opt, op.name(), &path, start, latency, &result)?;
+        match result {
+            Ok(_) => {
+                latencies[op as usize].record(latency);
+                corrected[op as usize].record(start - intended + latency);
+                timeline.record();
+            }
+            Err(e) => tolerate(opt, &mut errors, e, budget.planned(n))?,
+        }
+        n += 1;
+        pb.inc(1);
+        think(opt);
+        if !opt.quiet {
+            pb.set_message(format!("{} {}", op.name(), path))
+        }
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(MixedWorker {
+        window: Window {
+            start: worker_start,
+            end: Instant::now(),
+        },
+        latencies,
+        corrected,
+        timeline,
+        errors,
+        mismatches: None,
+    })
+}
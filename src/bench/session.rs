@@ -0,0 +1,49 @@
+use super::{connect_timed, do_bench, merge_latencies, save_hgrm, share, BenchOption};
+use crate::stats::{serialize_secs, Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Serialize)]
+pub struct SessionResult {
+    pub sessions: u32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<SessionResult, anyhow::Error> {
+    log::info!("Running session churn benchmark");
+    let (elapsed, results) = do_bench(opt, do_session_bench)?;
+
+    let latencies = merge_latencies(&results);
+    save_hgrm(opt, "establish", &latencies)?;
+    let latency = latencies.summary();
+
+    Ok(SessionResult {
+        sessions: latency.count as u32,
+        elapsed,
+        latency,
+    })
+}
+
+fn do_session_bench(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+) -> Result<Latencies, anyhow::Error> {
+    let mut latencies = Latencies::default();
+    for _ in share(opt.iteration, opt.threads, tid) {
+        if opt.interrupted() {
+            break;
+        }
+        let (zk, elapsed, _) = connect_timed(opt)?;
+        latencies.record(elapsed);
+        // Dropping the client closes the session
+        drop(zk);
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(latencies)
+}
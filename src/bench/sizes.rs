@@ -0,0 +1,40 @@
+use super::{BenchOption, BenchResult};
+use crate::dist::ValueSize;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct SizeStep {
+    pub node_size: usize,
+    pub result: BenchResult,
+}
+
+#[derive(Serialize)]
+pub struct SizeSweepResult {
+    pub steps: Vec<SizeStep>,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<SizeSweepResult, anyhow::Error> {
+    let mut steps = Vec::new();
+    for &size in &opt.node_size_sweep {
+        // The step interrupted keeps what it measured, those after it don't run
+        if opt.interrupted() {
+            break;
+        }
+        log::info!("Running the standard benchmark with {} byte values", size);
+        let step_opt = BenchOption {
+            node_size: ValueSize::Fixed(size),
+            node_value: opt.payload_pattern.generate(size),
+            hgrm_out: opt
+                .hgrm_out
+                .as_ref()
+                .map(|d| d.join(format!("size-{}", size))),
+            ..opt.clone()
+        };
+        let result = super::bench(&step_opt)?;
+        steps.push(SizeStep {
+            node_size: size,
+            result,
+        });
+    }
+    Ok(SizeSweepResult { steps })
+}
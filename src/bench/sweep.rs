@@ -0,0 +1,117 @@
+use super::{connect, do_bench, merge_latencies, prepare, save_hgrm, share, BenchOption};
+use crate::stats::{Latencies, LatencySummary};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::time::Instant;
+use zookeeper::CreateMode;
+
+const GET_CHILDREN_SAMPLES: u32 = 5;
+
+#[derive(Serialize)]
+pub struct SweepStep {
+    pub children: u32,
+    pub create: LatencySummary,
+    pub get_children: LatencySummary,
+}
+
+#[derive(Serialize)]
+pub struct SweepResult {
+    pub steps: Vec<SweepStep>,
+    /// Error that ended the sweep before reaching the requested child count
+    pub failure: Option<String>,
+}
+
+pub fn bench(opt: &BenchOption) -> Result<SweepResult, anyhow::Error> {
+    log::info!("Preparing...");
+    prepare(opt)?;
+
+    let parent = opt.prefix.as_str();
+    let zk = connect(opt)?;
+    let step_opt = BenchOption {
+        iteration: opt.sweep_step,
+        ..opt.clone()
+    };
+
+    let mut steps = Vec::new();
+    let mut failure = None;
+    let mut children = 0;
+    while children + opt.sweep_step <= opt.iteration && !opt.interrupted() {
+        log::info!("Growing {} from {} children", parent, children);
+        let base = children;
+        let (_, results) = match do_bench(&step_opt, move |tid, pb, opt| {
+            let own = share(opt.sweep_step, opt.threads, tid);
+            do_sweep_create(tid, pb, opt, base + own.start, own.len() as u32)
+        }) {
+            Ok(r) => r,
+            Err(e) => {
+                failure = Some(e.to_string());
+                break;
+            }
+        };
+        let create = merge_latencies(&results);
+        let create_latency = create.summary();
+        // Short of the step if the run was interrupted during it
+        children += create_latency.count as u32;
+        save_hgrm(opt, format!("create-{}", children).as_str(), &create)?;
+
+        let mut get_children = Latencies::default();
+        for _ in 0..GET_CHILDREN_SAMPLES {
+            let start = Instant::now();
+            if let Err(e) = zk.get_children(parent, false) {
+                failure = Some(format!(
+                    "get_children failed at {} children, {:?}",
+                    children, e
+                ));
+                break;
+            }
+            get_children.record(start.elapsed());
+        }
+
+        save_hgrm(
+            opt,
+            format!("get-children-{}", children).as_str(),
+            &get_children,
+        )?;
+        steps.push(SweepStep {
+            children,
+            create: create_latency,
+            get_children: get_children.summary(),
+        });
+        if failure.is_some() {
+            break;
+        }
+    }
+
+    Ok(SweepResult { steps, failure })
+}
+
+fn do_sweep_create(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    first: u32,
+    count: u32,
+) -> Result<Latencies, anyhow::Error> {
+    let zk = connect(opt)?;
+    pb.set_message("Connected");
+
+    let mut latencies = Latencies::default();
+    for i in first..first + count {
+        if opt.interrupted() {
+            break;
+        }
+        let path = opt.node_path_template.clone() + i.to_string().as_str();
+        let start = Instant::now();
+        zk.create(
+            path.as_str(),
+            opt.value(),
+            opt.acl.acl(),
+            CreateMode::Persistent,
+        )?;
+        latencies.record(start.elapsed());
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(latencies)
+}
@@ -0,0 +1,360 @@
+use crate::bench::backend::{connect_backend, reconnect_backend, Reply, ZkBackend};
+use crate::bench::{
+    do_bench, observe_op, pipeline, think, tolerate, warm_up, with_retry, BenchOption, Budget,
+    Pacer, PhaseResult, PhaseWorker, Window,
+};
+use crate::stats::{ErrorCounts, Latencies, Timeline};
+use crate::wire::Traffic;
+use indicatif::ProgressBar;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use zookeeper::{CreateMode, ZkError, ZkResult};
+
+/// Wait between attempts to replace a worker's session while the ensemble is unreachable
+const REESTABLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An operation a workload asks a worker to run next.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    Create {
+        path: String,
+        value: Vec<u8>,
+        mode: CreateMode,
+    },
+    Get {
+        path: String,
+        watch: bool,
+    },
+    Set {
+        path: String,
+        value: Vec<u8>,
+    },
+    Delete {
+        path: String,
+    },
+}
+
+impl Operation {
+    /// Name the operation is reported under
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Create { .. } => "create",
+            Operation::Get { .. } => "get",
+            Operation::Set { .. } => "set",
+            Operation::Delete { .. } => "delete",
+        }
+    }
+
+    /// Whether running the operation again failed with `e` because it took effect before.
+    fn took_effect(&self, e: &ZkError) -> bool {
+        matches!(
+            (self, e),
+            (Operation::Create { .. }, ZkError::NodeExists)
+                | (Operation::Delete { .. }, ZkError::NoNode)
+        )
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            Operation::Create { path, .. }
+            | Operation::Get { path, .. }
+            | Operation::Set { path, .. }
+            | Operation::Delete { path } => path,
+        }
+    }
+}
+
+/// What the workers of a phase do, one operation at a time. `run` takes care of the rest:
+/// connecting the workers, `--ramp-up`, `--warmup`, `--rate`, `--think-time`, budgets, error
+/// tolerance and everything measured and reported about each operation.
+pub trait Workload: Sync {
+    /// What a worker keeps between its operations
+    type Worker;
+
+    /// Runs once before the workers start.
+    fn setup(&self, _opt: &BenchOption) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    /// State of worker `tid` before its first operation.
+    fn worker(&self, opt: &BenchOption, tid: u32) -> Self::Worker;
+
+    /// The `n`th operation of a worker, or none once it has nothing left to do.
+    fn next_op(&self, opt: &BenchOption, worker: &mut Self::Worker, n: u32) -> Option<Operation>;
+
+    /// The `i`th operation of a worker's `--warmup`. Workers don't warm up if the first is none.
+    fn warmup_op(
+        &self,
+        _opt: &BenchOption,
+        _worker: &mut Self::Worker,
+        _i: u32,
+    ) -> Option<Operation> {
+        None
+    }
+
+    /// Runs once after all workers finished.
+    fn teardown(&self, _opt: &BenchOption) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Runs `workload` as phase `phase`, the way the standard mode runs its write and read phases.
+pub fn run<W: Workload>(
+    opt: &BenchOption,
+    phase: &str,
+    workload: &W,
+) -> Result<PhaseResult, anyhow::Error> {
+    let workers = opt.phase(phase, || measure(opt, workload))?;
+    PhaseResult::new(opt, phase, workers)
+}
+
+/// Runs `workload` on every worker, for callers keeping track of phases themselves.
+pub(super) fn measure<W: Workload>(
+    opt: &BenchOption,
+    workload: &W,
+) -> Result<Vec<PhaseWorker>, anyhow::Error> {
+    workload.setup(opt)?;
+    let (_, workers) = do_bench(opt, move |tid, pb, opt| match opt.in_flight {
+        Some(in_flight) => pipeline::work(tid, pb, opt, workload, in_flight),
+        None => work(tid, pb, opt, workload),
+    })?;
+    workload.teardown(opt)?;
+    Ok(workers)
+}
+
+/// Whether `result` of a `name` operation is a create `--resume` skips, of a znode that exists
+/// already, presumably from the run it resumes.
+pub(super) fn skipped(opt: &BenchOption, name: &str, result: &ZkResult<Reply>) -> bool {
+    opt.resume && name == "create" && matches!(result, Err(ZkError::NodeExists))
+}
+
+/// Levels below the prefix of the znode at `path`, with `--tree`.
+pub(super) fn depth(opt: &BenchOption, path: &str) -> Option<u32> {
+    opt.tree?;
+    let below = path.strip_prefix(opt.prefix.as_str())?;
+    Some(below.matches('/').count() as u32)
+}
+
+fn work<W: Workload>(
+    tid: u32,
+    pb: ProgressBar,
+    opt: &BenchOption,
+    workload: &W,
+) -> Result<PhaseWorker, anyhow::Error> {
+    let (mut session, mut worker_start, connect, member) = connect_backend(opt)?;
+    pb.set_message("Connected");
+
+    let mut worker = workload.worker(opt, tid);
+    if opt.warmup.is_some() {
+        if let Some(first) = workload.warmup_op(opt, &mut worker, 0) {
+            pb.set_message("Warming up");
+            let mut first = Some(first);
+            worker_start = warm_up(opt, |i| {
+                match first
+                    .take()
+                    .or_else(|| workload.warmup_op(opt, &mut worker, i))
+                {
+                    Some(op) => session.execute(op).0.map(drop),
+                    None => Ok(()),
+                }
+            })?;
+        }
+    }
+
+    let worker_start = opt.wait_for_start(tid, worker_start);
+    let mut timeline = Timeline::default();
+    let mut latencies = Latencies::default();
+    let mut corrected = Latencies::default();
+    let mut errors = ErrorCounts::default();
+    let mut mismatches = None;
+    let mut existing = 0;
+    let mut traffic = Traffic::default();
+    let mut depths: BTreeMap<u32, Latencies> = BTreeMap::new();
+    let mut pacer = Pacer::new(opt);
+    let budget = Budget::new(opt, tid);
+    // Ephemeral znodes the session created, for `--chaos` to re-create once it killed it
+    let mut owned = Vec::new();
+    let mut n = 0;
+    while budget.allows(n) {
+        let Some(op) = workload.next_op(opt, &mut worker, n) else {
+            break;
+        };
+        if opt.chaos.is_some_and(|chaos| chaos.due(&opt.kills)) {
+            let Some(new) = kill(opt, tid, session, &owned, &mut errors, &budget, n)? else {
+                break;
+            };
+            session = new;
+        }
+        let (name, path) = (op.name(), op.path().to_string());
+        let intended = pacer.wait();
+        let start = Instant::now();
+        let result = with_retry(opt, || {
+            let (result, op_traffic) = session.execute(op.clone());
+            traffic.merge(&op_traffic);
+            result
+        });
+        let latency = start.elapsed();
+        if skipped(opt, name, &result) {
+            existing += 1;
+            if opt.verify {
+                let get = Operation::Get {
+                    path: path.clone(),
+                    watch: false,
+                };
+                let (result, op_traffic) = session.execute(get);
+                traffic.merge(&op_traffic);
+                match result {
+                    Ok(reply) => {
+                        opt.check_read(tid, &path, &reply.data.unwrap_or_default(), &mut mismatches)
+                    }
+                    Err(e) => tolerate(opt, &mut errors, e, budget.planned(n))?,
+                }
+            }
+            n += 1;
+            pb.inc(1);
+            continue;
+        }
+        observe_op(opt, name, &path, start, latency, &result)?;
+        match result {
+            Ok(reply) => {
+                latencies.record(latency);
+                if let Some(data) = &reply.data {
+                    opt.check_read(tid, &path, data, &mut mismatches);
+                }
+                corrected.record(start - intended + latency);
+                timeline.record();
+                if opt.chaos.is_some() {
+                    opt.kills.completed();
+                    own(&mut owned, &op);
+                }
+                if let Some(depth) = depth(opt, &path) {
+                    depths.entry(depth).or_default().record(latency);
+                }
+            }
+            Err(e) => match session.ended(opt.session_timeout) {
+                Some(lost) => {
+                    let Some(new) = reestablish(opt, tid, lost, &budget, n) else {
+                        break;
+                    };
+                    session = new;
+                    owned.clear();
+                    // Run again, unmeasured as it waited out the outage, unless it turns out
+                    // to have taken effect before the session ended
+                    let (result, op_traffic) = session.execute(op.clone());
+                    traffic.merge(&op_traffic);
+                    match result {
+                        Err(e) if !op.took_effect(&e) => {
+                            tolerate(opt, &mut errors, e, budget.planned(n))?
+                        }
+                        _ => {}
+                    }
+                }
+                None => tolerate(opt, &mut errors, e, budget.planned(n))?,
+            },
+        }
+        n += 1;
+        pb.inc(1);
+        think(opt);
+        if !opt.quiet {
+            pb.set_message(format!("{} {}", name, path))
+        }
+    }
+
+    pb.finish_with_message(format!("Worker #{} finish", tid));
+    Ok(PhaseWorker {
+        connect,
+        member,
+        latencies,
+        corrected,
+        timeline,
+        window: Window {
+            start: worker_start,
+            end: Instant::now(),
+        },
+        traffic,
+        errors,
+        depths,
+        mismatches,
+        existing,
+    })
+}
+
+/// A new session for worker `tid` in place of one that ended, so the run goes on through the
+/// expiry. Connecting is retried until it works or the worker's budget runs out, and the time
+/// since the session was `lost` is counted among the run's outages either way.
+fn reestablish(
+    opt: &BenchOption,
+    tid: u32,
+    lost: Instant,
+    budget: &Budget,
+    done: u32,
+) -> Option<Arc<dyn ZkBackend>> {
+    log::warn!("Worker #{} lost its session, re-establishing it", tid);
+    let session = replace(opt, tid, budget, done);
+    let outage = lost.elapsed();
+    opt.outages.lock().unwrap().record(outage);
+    log::info!("Worker #{} was without a session for {:?}", tid, outage);
+    session
+}
+
+/// Connects a session for worker `tid`, retrying until it works or the budget runs out.
+fn replace(opt: &BenchOption, tid: u32, budget: &Budget, done: u32) -> Option<Arc<dyn ZkBackend>> {
+    loop {
+        match reconnect_backend(opt) {
+            Ok(session) => return Some(session),
+            Err(e) if budget.allows(done) => {
+                log::warn!("Worker #{} can't re-establish its session, {}", tid, e);
+                thread::sleep(REESTABLISH_INTERVAL);
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Keeps track of the ephemeral znodes a session created and didn't delete since.
+fn own(owned: &mut Vec<Operation>, op: &Operation) {
+    match op {
+        Operation::Create {
+            mode: CreateMode::Ephemeral,
+            ..
+        } => owned.push(op.clone()),
+        Operation::Delete { path } if !owned.is_empty() => owned.retain(|o| o.path() != path),
+        _ => {}
+    }
+}
+
+/// Kills the session of worker `tid` for `--chaos` and connects another, re-creating the
+/// ephemeral znodes the old one `owned` as a client relying on them would. Failures to
+/// re-create them count as the worker's errors.
+fn kill(
+    opt: &BenchOption,
+    tid: u32,
+    session: Arc<dyn ZkBackend>,
+    owned: &[Operation],
+    errors: &mut ErrorCounts,
+    budget: &Budget,
+    done: u32,
+) -> Result<Option<Arc<dyn ZkBackend>>, anyhow::Error> {
+    log::warn!("Killing the session of worker #{}", tid);
+    let at = Instant::now();
+    session.close();
+    let Some(session) = replace(opt, tid, budget, done) else {
+        return Ok(None);
+    };
+    let recreated = if owned.is_empty() {
+        None
+    } else {
+        let start = Instant::now();
+        for op in owned {
+            if let Err(e) = session.execute(op.clone()).0 {
+                tolerate(opt, errors, e, budget.planned(done))?;
+            }
+        }
+        Some((owned.len(), start.elapsed()))
+    };
+    opt.kills.record(at, recreated);
+    log::info!("Worker #{} recovered in {:?}", tid, at.elapsed());
+    Ok(Some(session))
+}
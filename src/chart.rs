@@ -0,0 +1,38 @@
+use crate::stats::Bucket;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Longer timelines are averaged down to this many characters
+const SPARKLINE_WIDTH: usize = 120;
+const BAR_WIDTH: usize = 40;
+
+/// Renders per-second counts as a sparkline scaled from zero to the busiest second.
+pub fn sparkline(timeline: &[u64]) -> String {
+    let chunk = timeline.len().div_ceil(SPARKLINE_WIDTH).max(1);
+    let points: Vec<u64> = timeline
+        .chunks(chunk)
+        .map(|c| c.iter().sum::<u64>() / c.len() as u64)
+        .collect();
+    let max = points.iter().copied().max().unwrap_or(0).max(1);
+    points
+        .iter()
+        .map(|p| SPARKS[(p * (SPARKS.len() as u64 - 1) / max) as usize])
+        .collect()
+}
+
+/// Renders latency buckets as one horizontal bar per bucket.
+pub fn histogram(buckets: &[Bucket]) -> Vec<String> {
+    let max = buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    buckets
+        .iter()
+        .map(|b| {
+            let bar = "█".repeat((b.count * BAR_WIDTH as u64).div_ceil(max) as usize);
+            format!(
+                "<= {:>10} |{:<width$}| {}",
+                format!("{:?}", b.le),
+                bar,
+                b.count,
+                width = BAR_WIDTH
+            )
+        })
+        .collect()
+}
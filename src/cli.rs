@@ -0,0 +1,1004 @@
+use crate::acl;
+use crate::assertion;
+use crate::bench::backend::Backend;
+use crate::bench::chaos::Chaos;
+use crate::bench::{self, Mode};
+use crate::cluster;
+use crate::dist;
+use crate::error::BenchError;
+use crate::events;
+use crate::payload;
+use crate::report;
+use crate::sasl;
+use crate::server;
+use bytesize::ByteSize;
+use clap::{
+    ArgAction, Args, Command as ClapCommand, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    /// TOML file of options, by their long names, for those the command line leaves out
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// File to append the log to, including what --quiet and --tui keep off the terminal
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Log more: -v debug messages, -vv trace messages and those of the client libraries
+    #[arg(long, short, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run any --mode with every option, by default the standard one creating znodes then
+    /// reading them back
+    Run(Box<RunArgs>),
+    /// Create znodes, as the write phase of the standard mode
+    Write(Box<WriteArgs>),
+    /// Read the znodes already below the prefix, e.g. ones an earlier write left behind
+    Read(Box<ReadArgs>),
+    /// Populate, then run a random mix of get/set/create/delete
+    Mixed(Box<MixedArgs>),
+    /// One writer updates a znode watched by many sessions
+    Watch(Box<WatchArgs>),
+    /// Create znodes below the prefix in batches without measuring them, to set up a dataset
+    Populate(Box<PopulateArgs>),
+    /// Delete the prefix and everything below it
+    Cleanup(Box<CleanupArgs>),
+    /// Delete what --unique-prefix runs of the prefix left behind once they're old enough
+    Gc(Box<GcArgs>),
+    /// Compare the `--format json` results of two earlier runs, exiting with 3 on a regression
+    Compare(CompareArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub mode: ModeOptions,
+    #[command(flatten)]
+    pub load: LoadOptions,
+    #[command(flatten)]
+    pub values: ValueOptions,
+    #[command(flatten)]
+    pub verify: VerifyOptions,
+    #[command(flatten)]
+    pub layout: LayoutOptions,
+    #[command(flatten)]
+    pub cleanup: CleanupOptions,
+    #[command(flatten)]
+    pub phases: PhaseOptions,
+    #[command(flatten)]
+    pub reads: ReadOptions,
+    #[command(flatten)]
+    pub keys: KeyOptions,
+    #[command(flatten)]
+    pub mixed: MixOptions,
+    #[command(flatten)]
+    pub fanout: FanoutOptions,
+    #[command(flatten)]
+    pub output: OutputOptions,
+    #[command(flatten)]
+    pub monitor: MonitorOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct WriteArgs {
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub load: LoadOptions,
+    #[command(flatten)]
+    pub values: ValueOptions,
+    #[command(flatten)]
+    pub verify: VerifyOptions,
+    #[command(flatten)]
+    pub layout: LayoutOptions,
+    #[command(flatten)]
+    pub cleanup: CleanupOptions,
+    #[command(flatten)]
+    pub output: OutputOptions,
+    #[command(flatten)]
+    pub monitor: MonitorOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct ReadArgs {
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub load: LoadOptions,
+    #[command(flatten)]
+    pub reads: ReadOptions,
+    #[command(flatten)]
+    pub keys: KeyOptions,
+    #[command(flatten)]
+    pub verify: VerifyOptions,
+    #[command(flatten)]
+    pub output: OutputOptions,
+    #[command(flatten)]
+    pub monitor: MonitorOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct MixedArgs {
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub load: LoadOptions,
+    #[command(flatten)]
+    pub values: ValueOptions,
+    #[command(flatten)]
+    pub verify: VerifyOptions,
+    #[command(flatten)]
+    pub keys: KeyOptions,
+    #[command(flatten)]
+    pub mixed: MixOptions,
+    #[command(flatten)]
+    pub cleanup: CleanupOptions,
+    #[command(flatten)]
+    pub output: OutputOptions,
+    #[command(flatten)]
+    pub monitor: MonitorOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Number of updates the writer makes
+    #[arg(long, short = 'n', default_value_t = 1000)]
+    pub updates: u32,
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub fanout: FanoutOptions,
+    #[command(flatten)]
+    pub values: ValueOptions,
+    #[command(flatten)]
+    pub cleanup: CleanupOptions,
+    #[command(flatten)]
+    pub output: OutputOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct PopulateArgs {
+    /// Number of znodes to create
+    #[arg(long, short = 'n', default_value_t = 1000)]
+    pub count: u32,
+    /// Number of threads creating them
+    #[arg(long, short = 'j', value_parser = clap::value_parser!(u32).range(1..), default_value_t = 8)]
+    pub threads: u32,
+    /// Creates each thread commits at once in a multi request, fewer where their values would
+    /// add up to more than 512KiB
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 100)]
+    pub batch: u32,
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+    #[command(flatten)]
+    pub values: ValueOptions,
+    #[command(flatten)]
+    pub verify: VerifyOptions,
+    #[command(flatten)]
+    pub layout: LayoutOptions,
+    #[command(flatten)]
+    pub cleanup: CleanupOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct CleanupArgs {
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct GcArgs {
+    /// Age of the prefixes to delete, by when they were created, which should be longer than
+    /// any run still going takes
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "1d")]
+    pub older_than: Duration,
+    #[command(flatten)]
+    pub connect: ConnectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// `--format json` output of the run to compare against
+    pub baseline: PathBuf,
+
+    /// `--format json` output of the run to compare with it
+    pub current: PathBuf,
+
+    /// Exit with 3 when throughput or a percentile got worse than the baseline by more than this
+    /// fraction
+    #[arg(long, value_parser = parse_fraction, default_value_t = 0.1)]
+    pub regression_threshold: f64,
+}
+
+/// How sessions connect to the ensemble
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Connection")]
+pub struct ConnectOptions {
+    /// ZooKeeper hosts, optionally followed by a chroot the prefix is relative to, like
+    /// zk1:2181,zk2:2181/app/ns; given several ensembles, each optionally named like
+    /// old=zk1:2181, commands go through them in turn, and benchmarks compare their results side
+    /// by side
+    #[arg(num_args = 1..)]
+    pub hosts: Vec<cluster::Cluster>,
+
+    /// File of more ensembles to run against in turn, one per line as hosts are given, lines
+    /// starting with # ignored
+    #[arg(long)]
+    pub clusters_file: Option<PathBuf>,
+
+    /// Session timeout to ask for, which servers bound to between 2 and 20 ticks; the negotiated
+    /// one is reported
+    #[arg(
+        long,
+        short = 't',
+        visible_alias = "timeout",
+        value_parser = parse_duration,
+        default_value = "10"
+    )]
+    pub session_timeout: Duration,
+
+    /// How long establishing a session, or a connection for four letter words, may take before
+    /// it fails [default: the session timeout]
+    #[arg(long, value_parser = parse_duration)]
+    pub connect_timeout: Option<Duration>,
+
+    /// Test prefix
+    #[arg(long, short, default_value = "/zoobench")]
+    pub prefix: String,
+
+    /// Credentials like user:password the sessions authenticate with by digest
+    #[arg(long, short, env = "ZOOBENCH_DIGEST", hide_env_values = true)]
+    pub digest: Option<String>,
+
+    /// SASL mechanism the sessions authenticate with on the zookeeper-client backend:
+    /// digest-md5:user:password, or gssapi optionally followed by the service name, like
+    /// gssapi:zookeeper, in builds with the kerberos feature
+    #[arg(long, env = "ZOOBENCH_SASL", hide_env_values = true)]
+    pub sasl: Option<sasl::Sasl>,
+
+    /// ACL of the znodes the run creates: open, creator-all for the --digest user, read-unsafe,
+    /// or entries like digest:bob:secret:cdrwa,world:anyone:r, of scheme, id and permissions
+    /// out of cdrwa or all, where digest ids take the user's password
+    #[arg(long, default_value = "open")]
+    pub acl: acl::NodeAcl,
+
+    /// Connect every session to this member of the ensemble only, like the leader or an
+    /// observer, rather than to any of the hosts
+    #[arg(long)]
+    pub target_server: Option<String>,
+
+    /// Connect each session to one member of the ensemble only, sessions taking turns at the
+    /// hosts, rather than to the first of them that takes it
+    #[arg(long, default_value_t = false, conflicts_with = "target_server")]
+    pub pin_sessions: bool,
+
+    /// Connect to the secure client ports of the hosts over TLS, through local tunnels as the
+    /// client only speaks plain text
+    #[arg(long, default_value_t = false, requires = "tls_ca")]
+    pub tls: bool,
+
+    /// PEM file of the CA certificates to verify the servers' certificates with
+    #[arg(long, requires = "tls")]
+    pub tls_ca: Option<PathBuf>,
+
+    /// PEM file of the client certificate chain, for servers that authenticate clients
+    #[arg(long, requires_all = ["tls", "tls_key"])]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM file of the client certificate's private key
+    #[arg(long, requires_all = ["tls", "tls_cert"])]
+    pub tls_key: Option<PathBuf>,
+
+    /// Directory of client certificates as <name>.pem with their keys as <name>.key, which
+    /// sessions take turns at so each worker authenticates as a distinct identity
+    #[arg(long, requires = "tls", conflicts_with_all = ["tls_cert", "tls_key"])]
+    pub tls_cert_dir: Option<PathBuf>,
+
+    /// Let servers that lost quorum accept the sessions read-only, as read-only mode does
+    #[arg(long, default_value_t = false)]
+    pub can_be_read_only: bool,
+
+    /// Ask each server ruok and srvr before starting and log its version and role; with warn a
+    /// member that's unreachable or not serving requests is only logged, with require the run is
+    /// refused and exits with 5
+    #[arg(long, value_enum)]
+    pub preflight: Option<server::Preflight>,
+}
+
+/// Which benchmark runs, and the options of modes without a command of their own
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Modes")]
+pub struct ModeOptions {
+    /// Benchmark mode
+    #[arg(
+        long,
+        short,
+        value_enum,
+        default_value_t = Mode::Standard,
+        conflicts_with = "workload"
+    )]
+    pub mode: Mode,
+
+    /// Rhai script defining fn op(worker, n), which returns the next operation as a map like
+    /// #{ op: "create", path: PREFIX + "/a", size: 1024 }, or data: "..." for the value, or
+    /// nothing to stop the worker; rand_int(min, max) draws a number (script mode)
+    #[arg(long, required_if_eq("mode", "script"))]
+    pub script: Option<PathBuf>,
+
+    /// Number of distinct locks threads contend on in lock mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 1)]
+    pub locks: u32,
+
+    /// Number of election groups candidates are spread over in election mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 1)]
+    pub groups: u32,
+
+    /// Number of threads consuming in queue mode, the rest produce
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 4)]
+    pub consumers: u32,
+
+    /// Number of znodes threads contend on in cas mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 1)]
+    pub keys: u32,
+
+    /// Children added to the parent per step in child-sweep mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 10000)]
+    pub sweep_step: u32,
+
+    /// Ascending payload sizes tried in payload-probe mode
+    #[arg(
+        long,
+        value_parser = parse_human_bytes,
+        value_delimiter = ',',
+        default_value = "512KiB,768KiB,896KiB,960KiB,1000KiB,1020KiB,1023KiB,1MiB"
+    )]
+    pub probe_sizes: Vec<usize>,
+
+    /// Depth of the tree built in delete-recursive mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 3)]
+    pub tree_depth: u32,
+
+    /// Children per znode of the tree built in delete-recursive mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 10)]
+    pub tree_width: u32,
+
+    /// Percentage of operations that are versioned writes in hot-znode mode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100), default_value_t = 0)]
+    pub hot_writes: u32,
+}
+
+/// How many workers run how many operations, how fast and over which sessions
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Load")]
+pub struct LoadOptions {
+    /// Number of total znodes
+    #[arg(long, short = 'n', default_value_t = 1000)]
+    pub iteration: u32,
+
+    /// Number of threads, or a comma separated list of them like 1,2,4,8 in concurrency-sweep
+    /// mode
+    #[arg(
+        long,
+        short = 'j',
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..),
+        default_value = "8"
+    )]
+    pub threads: Vec<u32>,
+
+    /// Give up on an operation the server hasn't answered within this long, e.g. 500ms, counting
+    /// it as an OperationTimeout error instead of letting a stuck server hang the worker
+    /// (standard, write, read and read-only modes)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub op_timeout: Option<Duration>,
+
+    /// Fraction of each worker's operations allowed to fail before the run aborts
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long, value_parser = parse_fraction, default_value_t = 0.0)]
+    pub max_error_rate: f64,
+
+    /// Target operations per second, paced by a token bucket all workers take turns from, so the
+    /// offered load holds however fast each responds. Latencies are then also reported corrected
+    /// for coordinated omission (standard, mixed and hot-znode modes)
+    #[arg(long, value_parser = parse_rate)]
+    pub rate: Option<f64>,
+
+    /// Operations each worker runs before measuring starts, as a duration like 30s or a count;
+    /// mixed and hot-znode modes warm up with reads (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub warmup: Option<bench::Warmup>,
+
+    /// Run each phase for this long, e.g. 5m, rather than until --iteration znodes are done, or
+    /// with 0 as --forever; the read phase then cycles over what the write phase created
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// Run each phase until Ctrl-C ends it rather than for a --duration or --iteration, reporting
+    /// at --report-interval, 1m by default; Ctrl-C twice quits (standard, mixed and hot-znode
+    /// modes)
+    #[arg(long, conflicts_with = "duration")]
+    pub forever: bool,
+
+    /// Stop the whole run after this long, e.g. 30m, reporting what it measured until then and
+    /// exiting with 6; workers that don't stop within the session timeout after are left behind
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub max_runtime: Option<Duration>,
+
+    /// Start workers one after another spread over this long, e.g. 60s, rather than all at
+    /// once, to see at which concurrency the server degrades
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub ramp_up: Option<Duration>,
+
+    /// Pause between each worker's operations, as a duration like 5ms, uniform:2ms..8ms or
+    /// exponential:5ms for a mean (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub think_time: Option<bench::ThinkTime>,
+
+    /// Seed of every random choice, from values to keys to operations, so runs against different
+    /// clusters make the same operations; each worker draws its own sequence from it
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Start each worker's measured time after its session is established rather than before,
+    /// which otherwise counts towards elapsed time and throughput (standard, mixed and hot-znode
+    /// modes)
+    #[arg(long, default_value_t = false)]
+    pub exclude_connect: bool,
+
+    /// Share this many sessions between the workers, which take turns at them, rather than
+    /// giving each worker its own (standard, mixed, hot-znode and script modes)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub connections: Option<u32>,
+
+    /// Issue every worker's operations over one session, as --connections 1, to see how far a
+    /// single session's pipeline of outstanding requests goes
+    #[arg(long, default_value_t = false, conflicts_with = "connections")]
+    pub shared_session: bool,
+
+    /// Keep up to this many requests outstanding on each worker's session, issued through
+    /// zookeeper-client rather than one blocking call at a time, or a comma separated list of
+    /// them like 1,4,16,64 to rerun the write and read phases with each in standard mode
+    /// (standard, write and read modes)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["connections", "shared_session", "rate", "think_time"]
+    )]
+    pub in_flight: Vec<u32>,
+
+    /// Client library the workers' operations go through, to compare their overhead; sessions
+    /// of --connections always use zookeeper (standard, write, read and read-only modes)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Backend::Zookeeper,
+        conflicts_with_all = ["connections", "shared_session"]
+    )]
+    pub backend: Backend,
+
+    /// Retry operations failing with ConnectionLoss or OperationTimeout rather than counting
+    /// them as errors, like max=5,backoff=exp:10ms..1s for up to 5 retries waiting twice as long
+    /// each time up to 1s, or backoff=50ms to wait the same each time; latency includes the
+    /// retries. A create or delete whose lost attempt went through then fails with NodeExists or
+    /// NoNode (standard, mixed, hot-znode and script modes)
+    #[arg(long, conflicts_with = "in_flight")]
+    pub retry: Option<bench::Retry>,
+
+    /// Inject faults while the workers run: session-kill:rate=1/min closes a worker's session
+    /// about once a minute at random, and the worker connects another and re-creates the
+    /// ephemeral znodes it had; reports the time to recover and the dip in throughput
+    /// (standard, write, read and read-only modes)
+    #[arg(long, conflicts_with_all = ["connections", "shared_session", "in_flight"])]
+    pub chaos: Option<Chaos>,
+}
+
+/// What the values written are made of
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Values")]
+pub struct ValueOptions {
+    /// ZNode value size in bytes, or sizes to draw each value's from: uniform:1K..256K, or
+    /// lognormal:16K,1.0 for a median and the standard deviation of its log, capped at 1MiB
+    #[arg(long, short = 's', default_value = "128K")]
+    pub node_size: dist::ValueSize,
+
+    /// Bytes values are made of: random, zero, text, or repeat:<n> for a random block of n
+    /// bytes repeated, to test servers and proxies that compress or deduplicate data
+    #[arg(long, default_value = "random")]
+    pub payload_pattern: payload::PayloadPattern,
+
+    /// File whose contents every value takes instead of generated bytes, or a directory of
+    /// such files to take in turn, e.g. samples of production znodes (not probe mode)
+    #[arg(long, conflicts_with_all = ["node_size", "payload_pattern"])]
+    pub payload_file: Option<PathBuf>,
+}
+
+/// Whether what's read is checked against what was written
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Values")]
+pub struct VerifyOptions {
+    /// Begin each value written with a checksum of it and its path, and check those of the
+    /// values read, reporting any that don't match and exiting with 7; e.g. to smoke test
+    /// proxies, observers or migrations between a write and a read, which both need it
+    #[arg(long, default_value_t = false)]
+    pub verify: bool,
+}
+
+/// Where and how the znodes of the write phase are created
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Layout")]
+pub struct LayoutOptions {
+    /// Create ephemeral znode or not
+    #[arg(long, short, default_value_t = false)]
+    pub ephemeral: bool,
+
+    /// Spread znodes over every level of a tree of parents like depth=4,fanout=10, rather than
+    /// creating them all under one, and report latency by depth (standard mode)
+    #[arg(long)]
+    pub tree: Option<bench::Tree>,
+
+    /// Create znodes under this many parents, each worker's under its own shard-<k> one, rather
+    /// than all of them contending for the same parent (standard mode)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "tree")]
+    pub shards: Option<u32>,
+}
+
+/// What happens to the znodes below the prefix before and after the run
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Layout")]
+pub struct CleanupOptions {
+    /// Leave what's already below the prefix rather than deleting it before the run, e.g. to
+    /// add to a dataset; creates of znodes that exist then fail with NodeExists. Implies
+    /// --keep-data, as the prefix holds more than the run created
+    #[arg(long, default_value_t = false)]
+    pub no_cleanup: bool,
+
+    /// Leave the znodes the run created rather than deleting the prefix once it's done; write
+    /// and populate always do, for later runs to read
+    #[arg(long, default_value_t = false)]
+    pub keep_data: bool,
+
+    /// Continue populating a prefix an earlier run left unfinished: leave what's below it, as
+    /// --no-cleanup, and skip creating the znodes that exist rather than failing, checking
+    /// their values with --verify
+    #[arg(long, default_value_t = false)]
+    pub resume: bool,
+
+    /// Append an ID of the run to the prefix, like /zoobench-671e2f3a-4242-9c0e51d7, so runs going at
+    /// once don't delete each other's znodes; `zoobench gc` deletes what they leave behind
+    #[arg(long, default_value_t = false, conflicts_with = "resume")]
+    pub unique_prefix: bool,
+}
+
+/// Options of the standard mode's write and read phases together
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Phases")]
+pub struct PhaseOptions {
+    /// Number of threads of the write phase, instead of --threads (standard mode)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub write_threads: Option<u32>,
+
+    /// Number of threads of the read phase, instead of --threads (standard mode)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub read_threads: Option<u32>,
+
+    /// Number of znodes the write phase creates, instead of --iteration (standard mode)
+    #[arg(long)]
+    pub write_iteration: Option<u32>,
+
+    /// Number of reads of the read phase, instead of --iteration (standard mode)
+    #[arg(long)]
+    pub read_iteration: Option<u32>,
+
+    /// Value sizes like 1K,4K,16K to rerun the write and read phases with, one after another,
+    /// instead of --node-size (standard mode)
+    #[arg(
+        long,
+        value_parser = parse_human_bytes,
+        value_delimiter = ',',
+        conflicts_with_all = ["node_size", "payload_file", "in_flight"]
+    )]
+    pub node_size_sweep: Vec<usize>,
+
+    /// TOML file of phases to run in order instead of the write and read phases, each a create,
+    /// read, mixed or cleanup with its own threads, iterations or duration, rate, mix, think
+    /// time, read distribution and node size (standard mode)
+    #[arg(long, conflicts_with_all = ["node_size_sweep", "shards", "in_flight"])]
+    pub scenario: Option<PathBuf>,
+}
+
+/// Which znodes the read phase reads, and how
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Reads")]
+pub struct ReadOptions {
+    /// Register a watch with every read in the QPS benchmark
+    #[arg(long, short, default_value_t = false)]
+    pub watch: bool,
+
+    /// Order sequential reads visit the znodes in: as created, shuffled into one random order
+    /// the workers split, or shuffled by each worker on its own (standard mode)
+    #[arg(long, value_enum, default_value_t = dist::ReadOrder::Sequential)]
+    pub read_order: dist::ReadOrder,
+
+    /// Read the znodes already below this path, e.g. a long-lived dataset, rather than those the
+    /// write phase created, or those below the prefix in read mode (standard and read modes)
+    #[arg(long)]
+    pub read_prefix: Option<String>,
+}
+
+/// How reads pick their keys
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Reads")]
+pub struct KeyOptions {
+    /// Which znodes the read phase reads: sequential, where each worker cycles over its own,
+    /// or uniform, zipfian or latest over all of them, the skewed ones taking an optional theta
+    /// like zipfian:0.99; mixed mode gets and sets pick their keys by it too, uniformly if
+    /// sequential (standard and mixed modes)
+    #[arg(long, default_value = "sequential")]
+    pub read_dist: dist::ReadDist,
+}
+
+/// Operations of the mixed mode
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Mixed")]
+pub struct MixOptions {
+    /// Operation weights in mixed mode
+    #[arg(long, default_value = "get:70,set:20,create:5,delete:5")]
+    pub mix: bench::mixed::Mix,
+
+    /// Named workload after YCSB, running mixed mode with its operation weights and keys
+    #[arg(long, value_enum, conflicts_with_all = ["mix", "read_dist"])]
+    pub workload: Option<bench::mixed::Workload>,
+}
+
+/// Watchers of the watch-fanout mode
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Watches")]
+pub struct FanoutOptions {
+    /// Number of watcher sessions in watch-fanout mode
+    #[arg(long, default_value_t = 100)]
+    pub watchers: u32,
+
+    /// Notifications slower than this are counted as late in watch-fanout mode
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "100ms")]
+    pub late_threshold: Duration,
+}
+
+/// How the results are reported, and the gates they must pass
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Output")]
+pub struct OutputOptions {
+    /// How to report the final results
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// File to write the results into in the chosen format, instead of logging them or printing
+    /// them on stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// HTML or Markdown file, by its extension, to write a report with tables and charts into
+    #[arg(long, value_parser = report::parse_path)]
+    pub report: Option<PathBuf>,
+
+    /// CSV file to append a row of options and results to, one per run
+    #[arg(long)]
+    pub csv_out: Option<PathBuf>,
+
+    /// Directory to write a .hgrm latency histogram per phase into
+    #[arg(long)]
+    pub hgrm_out: Option<PathBuf>,
+
+    /// Label like ticket=ZK-1234 or arm=b for the results, their CSV row and the Prometheus
+    /// metrics, so runs can be told apart by it later, repeatable
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+    pub tags: Vec<(String, String)>,
+
+    /// Stream phase starts and ends, operation errors, interval reports and the final result on
+    /// stdout as they happen
+    #[arg(long, value_enum)]
+    pub events: Option<events::EventFormat>,
+
+    /// `--format json` output of an earlier run to compare throughput and percentiles against
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Exit with 3 when throughput or a percentile got worse than the baseline by more than this
+    /// fraction
+    #[arg(long, value_parser = parse_fraction, default_value_t = 0.1, requires = "baseline")]
+    pub regression_threshold: f64,
+
+    /// Condition the results must meet or exit with 4, e.g. 'p99<20ms' or 'tps>5000', repeatable;
+    /// a metric is a result field name or dotted path, durations compare as seconds
+    #[arg(long = "assert")]
+    pub assertions: Vec<assertion::Assertion>,
+
+    /// Snapshot each server's counters with mntr, or stat and wchs, before and after each phase
+    /// and report how they changed
+    #[arg(long, default_value_t = false)]
+    pub server_stats: bool,
+
+    /// Hide progress bars and log only warnings until the final results, e.g. in CI
+    #[arg(
+        long,
+        short,
+        visible_alias = "no-progress",
+        default_value_t = false,
+        conflicts_with = "tui"
+    )]
+    pub quiet: bool,
+
+    /// Show a live dashboard instead of progress bars, with throughput, percentiles and errors
+    /// in standard, mixed and hot-znode modes
+    #[arg(long, default_value_t = false)]
+    pub tui: bool,
+
+    /// Check the connection, the credentials and the ACL of the prefix, and print the phases,
+    /// operations and bytes the run would go through, without running it
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+}
+
+/// Live views of the operations while they run
+#[derive(Args, Clone, Debug)]
+#[command(next_help_heading = "Monitoring")]
+pub struct MonitorOptions {
+    /// CSV file to log every operation's timestamp, type, path, latency and result into
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub latency_log: Option<PathBuf>,
+
+    /// Log operations slower than this, e.g. 50ms (standard, mixed and hot-znode modes)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub slow_op_threshold: Option<Duration>,
+
+    /// Log each operation's throughput, errors and p99 at this interval, e.g. 10s
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub report_interval: Option<Duration>,
+
+    /// File to also write the interval reports to
+    #[arg(long, requires = "report_interval")]
+    pub report_log: Option<PathBuf>,
+
+    /// Address to serve live Prometheus metrics on while running, e.g. 0.0.0.0:9100
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub prometheus_listen: Option<SocketAddr>,
+
+    /// StatsD host:port to push operation timings to while running
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub statsd: Option<String>,
+
+    /// Prefix of the StatsD metric names
+    #[arg(long, default_value = "zoobench")]
+    pub statsd_prefix: String,
+
+    /// OTLP/HTTP collector to export sampled operations to as spans, e.g. http://localhost:4318
+    /// (standard, mixed and hot-znode modes)
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    /// Fraction of operations to export as spans
+    #[arg(long, value_parser = parse_fraction, default_value_t = 0.01)]
+    pub trace_sample_rate: f64,
+}
+
+/// The options of a group left off a command's line, all at their defaults. Those that fall
+/// back on an environment variable don't, so that options built in code are the same whatever
+/// the process's environment.
+fn defaults<T: Args + FromArgMatches>() -> T {
+    let cmd = T::augment_args(ClapCommand::new("zoobench")).mut_args(|arg| arg.env(None));
+    T::from_arg_matches(&cmd.get_matches_from(["zoobench"])).unwrap()
+}
+
+impl ConnectOptions {
+    /// The ensembles on the command line, followed by those of the `--clusters-file`.
+    pub fn clusters(&self) -> Result<Vec<cluster::Cluster>, anyhow::Error> {
+        let mut clusters = self.hosts.clone();
+        if let Some(path) = &self.clusters_file {
+            clusters.extend(cluster::load(path)?);
+        }
+        if clusters.is_empty() {
+            return Err(BenchError::InvalidOption(
+                "no hosts, give them or a --clusters-file".to_string(),
+            )
+            .into());
+        }
+        Ok(clusters)
+    }
+}
+
+impl RunArgs {
+    /// The same options, against `cluster` only.
+    pub fn for_cluster(&self, cluster: cluster::Cluster) -> Self {
+        let mut args = self.clone();
+        args.connect.hosts = vec![cluster];
+        args.connect.clusters_file = None;
+        args
+    }
+
+    /// The options of `run` against `cluster`, all else at their defaults.
+    pub fn new(cluster: cluster::Cluster) -> Self {
+        let connect = ConnectOptions {
+            hosts: vec![cluster],
+            ..defaults()
+        };
+        RunArgs::with_mode(connect, Mode::Standard)
+    }
+
+    /// The options of a command running `mode`, as `run` takes them.
+    fn with_mode(connect: ConnectOptions, mode: Mode) -> Self {
+        // Parsed whole, as some groups' options conflict with others'
+        let mut args: RunArgs = defaults();
+        args.connect = connect;
+        args.mode.mode = mode;
+        args
+    }
+}
+
+impl From<WriteArgs> for RunArgs {
+    fn from(a: WriteArgs) -> Self {
+        RunArgs {
+            load: a.load,
+            values: a.values,
+            verify: a.verify,
+            layout: a.layout,
+            cleanup: a.cleanup,
+            output: a.output,
+            monitor: a.monitor,
+            ..RunArgs::with_mode(a.connect, Mode::Write)
+        }
+    }
+}
+
+impl From<ReadArgs> for RunArgs {
+    fn from(a: ReadArgs) -> Self {
+        RunArgs {
+            load: a.load,
+            reads: a.reads,
+            keys: a.keys,
+            verify: a.verify,
+            output: a.output,
+            monitor: a.monitor,
+            ..RunArgs::with_mode(a.connect, Mode::Read)
+        }
+    }
+}
+
+impl From<MixedArgs> for RunArgs {
+    fn from(a: MixedArgs) -> Self {
+        RunArgs {
+            load: a.load,
+            values: a.values,
+            verify: a.verify,
+            keys: a.keys,
+            mixed: a.mixed,
+            cleanup: a.cleanup,
+            output: a.output,
+            monitor: a.monitor,
+            ..RunArgs::with_mode(a.connect, Mode::Mixed)
+        }
+    }
+}
+
+impl From<WatchArgs> for RunArgs {
+    fn from(a: WatchArgs) -> Self {
+        RunArgs {
+            load: LoadOptions {
+                iteration: a.updates,
+                ..defaults()
+            },
+            fanout: a.fanout,
+            values: a.values,
+            cleanup: a.cleanup,
+            output: a.output,
+            ..RunArgs::with_mode(a.connect, Mode::WatchFanout)
+        }
+    }
+}
+
+impl From<PopulateArgs> for RunArgs {
+    fn from(a: PopulateArgs) -> Self {
+        RunArgs {
+            load: LoadOptions {
+                iteration: a.count,
+                threads: vec![a.threads],
+                ..defaults()
+            },
+            values: a.values,
+            verify: a.verify,
+            layout: a.layout,
+            cleanup: a.cleanup,
+            ..RunArgs::with_mode(a.connect, Mode::Write)
+        }
+    }
+}
+
+impl From<CleanupArgs> for RunArgs {
+    fn from(a: CleanupArgs) -> Self {
+        RunArgs::with_mode(a.connect, Mode::Standard)
+    }
+}
+
+impl From<GcArgs> for RunArgs {
+    fn from(a: GcArgs) -> Self {
+        RunArgs::with_mode(a.connect, Mode::Standard)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Log a human-readable summary
+    Text,
+    /// Print the options and results as JSON on stdout, with durations in seconds
+    Json,
+}
+
+fn parse_human_bytes(arg: &str) -> Result<usize, String> {
+    arg.parse::<ByteSize>().map(|x| x.as_u64() as usize)
+}
+
+fn parse_fraction(arg: &str) -> Result<f64, String> {
+    match arg.parse() {
+        Ok(f) if (0.0..=1.0).contains(&f) => Ok(f),
+        _ => Err(format!("`{}` isn't a number between 0 and 1", arg)),
+    }
+}
+
+/// A `key=value` tag, whose key has to make a Prometheus label other than those of the metrics.
+fn parse_tag(arg: &str) -> Result<(String, String), String> {
+    let Some((key, value)) = arg.split_once('=') else {
+        return Err(format!("`{}` isn't like key=value", arg));
+    };
+    check_tag_key(key)?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Whether `key` makes a Prometheus label other than those of the metrics, for tags given other
+/// than by `--tag`.
+pub fn check_tag_key(key: &str) -> Result<(), String> {
+    let mut chars = key.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !key.starts_with("__");
+    if !valid {
+        return Err(format!(
+            "`{}` isn't a key of letters, digits and underscores not starting with a digit",
+            key
+        ));
+    }
+    if ["op", "result", "le"].contains(&key) {
+        return Err(format!("`{}` is a label of the metrics already", key));
+    }
+    Ok(())
+}
+
+fn parse_rate(arg: &str) -> Result<f64, String> {
+    match arg.parse() {
+        Ok(r) if r > 0.0 => Ok(r),
+        _ => Err(format!("`{}` isn't a positive number", arg)),
+    }
+}
+
+fn parse_duration(arg: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_secs(arg.parse()?))
+}
@@ -0,0 +1,71 @@
+//! The ensembles a run goes through, from the hosts on the command line and `--clusters-file`.
+
+use crate::error::BenchError;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An ensemble as the command line takes it: hosts, optionally with a chroot, and optionally
+/// named like `old=zk1:2181,zk2:2181`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cluster {
+    pub name: Option<String>,
+    pub hosts: String,
+}
+
+impl Cluster {
+    /// What results call the ensemble: its name, or its hosts if it has none.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.hosts)
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // Hosts have no = of their own, but a chroot might
+        let (name, hosts) = match s.split_once('=') {
+            Some((name, hosts)) if !name.contains([':', ',', '/']) => (Some(name.trim()), hosts),
+            _ => (None, s),
+        };
+        if name == Some("") {
+            return Err(format!("no name before = in `{}`", s));
+        }
+        let hosts = hosts.trim();
+        if hosts.is_empty() {
+            return Err(format!("no hosts in `{}`", s));
+        }
+        Ok(Cluster {
+            name: name.map(str::to_string),
+            hosts: hosts.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}={}", name, self.hosts),
+            None => f.write_str(&self.hosts),
+        }
+    }
+}
+
+/// Reads a `--clusters-file`: an ensemble per line as the command line takes them, skipping
+/// blank lines and those starting with #.
+pub fn load(path: &Path) -> Result<Vec<Cluster>, anyhow::Error> {
+    let content = fs::read_to_string(path)?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(n, line)| {
+            line.parse().map_err(|e| {
+                BenchError::InvalidOption(format!("{}:{}: {}", path.display(), n + 1, e)).into()
+            })
+        })
+        .collect()
+}
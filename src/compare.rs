@@ -0,0 +1,142 @@
+use crate::error::BenchError;
+use crate::output::flatten;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Latency percentiles compared against the baseline, lower being better
+const PERCENTILES: [&str; 5] = ["p50", "p90", "p99", "p999", "max"];
+
+/// Reads the `--format json` output of an earlier run.
+pub fn load(path: &Path) -> Result<Value, anyhow::Error> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| {
+        BenchError::InvalidOption(format!("bad results in {}: {}", path.display(), e)).into()
+    })
+}
+
+/// Whether a higher value of the flattened column is better, or `None` if it isn't compared.
+fn higher_is_better(column: &str) -> Option<bool> {
+    let name = column.rsplit('.').next().unwrap_or(column);
+    if name == "throughput" {
+        Some(true)
+    } else if PERCENTILES.contains(&name) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn numbers(value: &Value) -> Vec<(String, f64)> {
+    let mut columns = Vec::new();
+    flatten("", value, &mut columns);
+    columns
+        .into_iter()
+        .filter(|(k, _)| higher_is_better(k).is_some())
+        .filter_map(|(k, v)| v.parse().ok().map(|v| (k, v)))
+        .collect()
+}
+
+/// Logs how throughput and percentiles of the `current` results changed since `baseline`,
+/// failing if any got worse by more than `threshold`, a fraction of the baseline value.
+pub fn compare(baseline: &Value, current: &Value, threshold: f64) -> Result<(), anyhow::Error> {
+    if baseline["options"]["mode"] != current["options"]["mode"] {
+        return Err(BenchError::InvalidOption(format!(
+            "baseline ran mode {}, not {}",
+            baseline["options"]["mode"], current["options"]["mode"]
+        ))
+        .into());
+    }
+
+    let before = numbers(&baseline["result"]);
+    let mut regressions = 0;
+    for (column, now) in numbers(&current["result"]) {
+        let Some((_, base)) = before.iter().find(|(k, _)| *k == column) else {
+            continue;
+        };
+        if *base == 0.0 {
+            continue;
+        }
+        let change = (now - base) / base;
+        let worse = if higher_is_better(&column) == Some(true) {
+            -change
+        } else {
+            change
+        };
+        if worse > threshold {
+            regressions += 1;
+            log::warn!(
+                "Regressed {}: {} -> {} ({:+.2}%)",
+                column,
+                base,
+                now,
+                change * 100.0
+            );
+        } else {
+            log::info!("{}: {} -> {} ({:+.2}%)", column, base, now, change * 100.0);
+        }
+    }
+    if regressions > 0 {
+        return Err(BenchError::Regressed(regressions).into());
+    }
+    Ok(())
+}
+
+/// Lines of a table of the throughput and percentiles of the results of runs against several
+/// ensembles, a column per ensemble by its label, with how each differs from the first.
+pub fn side_by_side(runs: &[(String, Value)]) -> Vec<String> {
+    let numbers: Vec<_> = runs.iter().map(|(_, r)| numbers(&r["result"])).collect();
+    let mut columns: Vec<&str> = Vec::new();
+    for (column, _) in numbers.iter().flatten() {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+    let mut rows = vec![runs
+        .iter()
+        .map(|(label, _)| label.clone())
+        .collect::<Vec<_>>()];
+    for column in &columns {
+        let values: Vec<_> = numbers
+            .iter()
+            .map(|n| n.iter().find(|(k, _)| k == column).map(|(_, v)| *v))
+            .collect();
+        let cells = values.iter().enumerate().map(|(i, value)| {
+            let Some(value) = value else {
+                return "-".to_string();
+            };
+            let cell = match higher_is_better(column) {
+                Some(true) => format!("{:.2}", value),
+                _ => format!("{:.3}ms", value * 1000.0),
+            };
+            match values[0] {
+                Some(first) if i > 0 && first != 0.0 => {
+                    format!("{} ({:+.2}%)", cell, (value - first) / first * 100.0)
+                }
+                _ => cell,
+            }
+        });
+        rows.push(cells.collect());
+    }
+    let labels: Vec<&str> = std::iter::once("").chain(columns.iter().copied()).collect();
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..runs.len())
+        .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0))
+        .collect();
+    rows.iter()
+        .zip(labels)
+        .map(|(row, label)| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+                .collect();
+            format!(
+                "{:<width$}  {}",
+                label,
+                cells.join("  "),
+                width = label_width
+            )
+        })
+        .collect()
+}
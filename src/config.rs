@@ -0,0 +1,104 @@
+use crate::cli::Cli;
+use crate::error::BenchError;
+use clap::{ArgAction, Command, CommandFactory, FromArgMatches};
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Parses the command line, taking the options it leaves out from the `--config` file if it
+/// names one. The file's keys are long option names, with dashes or underscores, and its values
+/// are what the option would take on the command line: strings, numbers, booleans for flags and
+/// arrays for options taking several values. Options from the file stand in for defaults, so
+/// those on the command line, or from the environment, override them.
+pub fn parse() -> Result<Cli, anyhow::Error> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let mut cmd = Cli::command();
+    if let Some(path) = config_path(&args) {
+        let table = load(&path)?;
+        cmd = apply(cmd, &table).map_err(|e| {
+            BenchError::InvalidOption(format!("bad config {}, {}", path.display(), e))
+        })?;
+    }
+    let matches = cmd.get_matches_from(args);
+    Ok(Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()))
+}
+
+/// Where `--config` points, found before parsing as it decides how to parse.
+fn config_path(args: &[OsString]) -> Option<std::path::PathBuf> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(Into::into);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.into());
+        }
+    }
+    None
+}
+
+fn load(path: &Path) -> Result<Table, anyhow::Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| BenchError::InvalidOption(format!("bad config {}, {}", path.display(), e)))?;
+    Ok(text
+        .parse::<Table>()
+        .map_err(|e| BenchError::InvalidOption(format!("bad config {}, {}", path.display(), e)))?)
+}
+
+/// Makes the values of `table` the defaults of the options of every command taking them. A key
+/// no command takes is an error, while one only some take is left to those.
+fn apply(mut cmd: Command, table: &Table) -> Result<Command, String> {
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        let mut known = false;
+        let names: Vec<String> = cmd
+            .get_subcommands()
+            .map(|sc| sc.get_name().to_string())
+            .collect();
+        for name in names {
+            let Some(arg) = cmd
+                .find_subcommand(&name)
+                .and_then(|sc| sc.get_arguments().find(|a| a.get_id() == id.as_str()))
+            else {
+                continue;
+            };
+            known = true;
+            let takes_several = matches!(arg.get_action(), ArgAction::Append)
+                || arg.get_value_delimiter().is_some();
+            let values = match value {
+                Value::Array(values) if takes_several => values
+                    .iter()
+                    .map(scalar)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("{} {}", key, e))?,
+                Value::Array(_) => return Err(format!("{} takes one value", key)),
+                value => vec![scalar(value).map_err(|e| format!("{} {}", key, e))?],
+            };
+            let id = id.clone();
+            cmd = cmd.mut_subcommand(name, |sc| {
+                sc.mut_arg(id, |a| a.default_values(values).required(false))
+            });
+        }
+        if !known {
+            return Err(format!("no option {}", key));
+        }
+    }
+    Ok(cmd)
+}
+
+/// A value as it would be written on the command line.
+fn scalar(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(n) => Ok(n.to_string()),
+        Value::Float(n) => Ok(n.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Datetime(d) => Ok(d.to_string()),
+        Value::Array(_) | Value::Table(_) => Err("takes strings, numbers or booleans".to_string()),
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::BenchError;
+use bytesize::ByteSize;
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const DEFAULT_THETA: f64 = 0.99;
+/// Largest value a log-normal size is drawn up to, the default `jute.maxbuffer` of servers
+const MAX_LOGNORMAL_SIZE: usize = 1024 * 1024;
+
+/// Order sequential reads visit the znodes in with `--read-order`.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadOrder {
+    /// Ascending, as they were created
+    Sequential,
+    /// One random order of all znodes, split between the workers
+    Shuffled,
+    /// Each worker shuffles its own znodes
+    ShuffledPerThread,
+}
+
+impl ReadOrder {
+    /// Puts the znodes `own` by each worker in this order.
+    pub fn arrange(self, own: &mut [Vec<u32>], rng: &mut impl Rng) {
+        match self {
+            ReadOrder::Sequential => {}
+            ReadOrder::Shuffled => {
+                let mut all: Vec<u32> = own.iter().flatten().copied().collect();
+                all.shuffle(rng);
+                let mut all = all.into_iter();
+                for keys in own {
+                    for k in keys.iter_mut() {
+                        *k = all.next().unwrap();
+                    }
+                }
+            }
+            ReadOrder::ShuffledPerThread => {
+                for keys in own {
+                    keys.shuffle(rng);
+                }
+            }
+        }
+    }
+}
+
+/// Which znodes the read phase reads with `--read-dist`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadDist {
+    /// Every worker reads the znodes it created, in `--read-order`
+    Sequential,
+    /// Any created znode, equally likely
+    Uniform,
+    /// The first created znodes are the hottest, skewed by the given theta
+    Zipfian(f64),
+    /// The last created znodes are the hottest, skewed by the given theta
+    Latest(f64),
+}
+
+impl FromStr for ReadDist {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad read distribution `{}`", s));
+        let (name, theta) = match s.split_once(':') {
+            Some((name, theta)) => (name, Some(theta.parse().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+        if theta.is_some_and(|t: f64| !(t > 0.0 && t < 1.0)) {
+            return Err(invalid());
+        }
+        match (name, theta) {
+            ("sequential", None) => Ok(ReadDist::Sequential),
+            ("uniform", None) => Ok(ReadDist::Uniform),
+            ("zipfian", t) => Ok(ReadDist::Zipfian(t.unwrap_or(DEFAULT_THETA))),
+            ("latest", t) => Ok(ReadDist::Latest(t.unwrap_or(DEFAULT_THETA))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ReadDist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadDist::Sequential => write!(f, "sequential"),
+            ReadDist::Uniform => write!(f, "uniform"),
+            ReadDist::Zipfian(theta) => write!(f, "zipfian:{}", theta),
+            ReadDist::Latest(theta) => write!(f, "latest:{}", theta),
+        }
+    }
+}
+
+impl Serialize for ReadDist {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// Draws ranks below `n`, rank 0 being the most likely, with the method of Gray et al., "Quickly
+/// Generating Billion-Record Synthetic Databases", as YCSB does. Setting up sums over all `n`
+/// ranks once, drawing is constant time.
+#[derive(Debug)]
+pub struct Zipfian {
+    n: usize,
+    theta: f64,
+    zetan: f64,
+    alpha: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+    pub fn new(n: usize, theta: f64) -> Self {
+        let zeta = |n: usize| (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zetan = zeta(n);
+        Zipfian {
+            n,
+            theta,
+            zetan,
+            alpha: 1.0 / (1.0 - theta),
+            eta: (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta(2) / zetan),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1.min(self.n - 1);
+        }
+        let rank = self.n as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha);
+        (rank as usize).min(self.n - 1)
+    }
+}
+
+/// Picks one of the keys, ordered oldest first, as a `ReadDist` tells. Sequential reads don't
+/// pick from all keys, so there's nothing to set up for them.
+#[derive(Debug)]
+pub enum KeyChooser {
+    None,
+    Uniform,
+    Zipfian(Zipfian),
+    Latest(Zipfian),
+}
+
+impl KeyChooser {
+    /// Set up for `n` keys, though `choose` takes however many there are by then.
+    pub fn new(dist: ReadDist, n: usize) -> Self {
+        match dist {
+            _ if n == 0 => KeyChooser::None,
+            ReadDist::Sequential => KeyChooser::None,
+            ReadDist::Uniform => KeyChooser::Uniform,
+            ReadDist::Zipfian(theta) => KeyChooser::Zipfian(Zipfian::new(n, theta)),
+            ReadDist::Latest(theta) => KeyChooser::Latest(Zipfian::new(n, theta)),
+        }
+    }
+
+    /// One of `len` keys, ranks beyond them falling on the last or first.
+    pub fn choose(&self, rng: &mut impl Rng, len: usize) -> Option<usize> {
+        if len == 0 {
+            return None;
+        }
+        match self {
+            KeyChooser::None => None,
+            KeyChooser::Uniform => Some(rng.gen_range(0..len)),
+            KeyChooser::Zipfian(z) => Some(z.sample(rng).min(len - 1)),
+            KeyChooser::Latest(z) => Some(len - 1 - z.sample(rng).min(len - 1)),
+        }
+    }
+}
+
+/// Size of each value written, from `--node-size`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueSize {
+    Fixed(usize),
+    /// Between both sizes, inclusive, all equally likely
+    Uniform(usize, usize),
+    /// Log-normal around a median size, with the standard deviation of its logarithm
+    Lognormal(usize, f64),
+}
+
+impl FromStr for ValueSize {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad node size `{}`", s));
+        let bytes = |b: &str| {
+            b.trim()
+                .parse::<ByteSize>()
+                .map(|b| b.as_u64() as usize)
+                .map_err(|_| invalid())
+        };
+        match s.split_once(':') {
+            None => Ok(ValueSize::Fixed(bytes(s)?)),
+            Some(("fixed", size)) => Ok(ValueSize::Fixed(bytes(size)?)),
+            Some(("uniform", range)) => {
+                let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+                let (min, max) = (bytes(min)?, bytes(max)?);
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(ValueSize::Uniform(min, max))
+            }
+            Some(("lognormal", params)) => {
+                let (median, sigma) = params.split_once(',').ok_or_else(invalid)?;
+                match sigma.trim().parse() {
+                    Ok(sigma) if sigma >= 0.0 => Ok(ValueSize::Lognormal(bytes(median)?, sigma)),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ValueSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = |b: usize| ByteSize(b as u64).to_string().replace(' ', "");
+        match self {
+            ValueSize::Fixed(size) => write!(f, "{}", b(*size)),
+            ValueSize::Uniform(min, max) => write!(f, "uniform:{}..{}", b(*min), b(*max)),
+            ValueSize::Lognormal(median, sigma) => write!(f, "lognormal:{},{}", b(*median), sigma),
+        }
+    }
+}
+
+/// Fixed sizes stay a number of bytes, as they were before sizes could vary.
+impl Serialize for ValueSize {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ValueSize::Fixed(size) => s.serialize_u64(*size as u64),
+            _ => s.collect_str(self),
+        }
+    }
+}
+
+impl ValueSize {
+    /// The largest size `sample` returns.
+    pub fn max(&self) -> usize {
+        match *self {
+            ValueSize::Fixed(size) => size,
+            ValueSize::Uniform(_, max) => max,
+            ValueSize::Lognormal(..) => MAX_LOGNORMAL_SIZE,
+        }
+    }
+
+    /// The size `sample` returns on average, ignoring the cap on log-normal sizes.
+    pub fn mean(&self) -> f64 {
+        match *self {
+            ValueSize::Fixed(size) => size as f64,
+            ValueSize::Uniform(min, max) => (min + max) as f64 / 2.0,
+            ValueSize::Lognormal(median, sigma) => {
+                (median as f64 * (sigma * sigma / 2.0).exp()).min(MAX_LOGNORMAL_SIZE as f64)
+            }
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match *self {
+            ValueSize::Fixed(size) => size,
+            ValueSize::Uniform(min, max) => rng.gen_range(min..=max),
+            ValueSize::Lognormal(median, sigma) => {
+                // A standard normal by Box-Muller
+                let (u1, u2): (f64, f64) = (1.0 - rng.gen::<f64>(), rng.gen());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                ((median as f64 * (sigma * z).exp()) as usize).min(MAX_LOGNORMAL_SIZE)
+            }
+        }
+    }
+}
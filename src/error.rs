@@ -1,10 +1,88 @@
 use thiserror::Error;
+use zookeeper::ZkError;
 
 #[derive(Error, Debug)]
 pub enum BenchError {
-    #[error("Benchmark failed")]
-    BenchFailed(),
+    /// Workers failed, the first of them with this error
+    #[error("Benchmark failed, {0:#}")]
+    BenchFailed(anyhow::Error),
+
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+
+    #[error("{0} metrics regressed beyond the threshold")]
+    Regressed(usize),
+
+    #[error("{0} assertions failed")]
+    AssertionFailed(usize),
+
+    #[error("{0} ensemble members failed the pre-flight check")]
+    Unhealthy(usize),
+
+    #[error("Interrupted, the results cover only what ran until then")]
+    Interrupted(),
+
+    #[error("Ran out of --max-runtime {0:?}, the results cover only what ran until then")]
+    TimedOut(std::time::Duration),
+
+    #[error("{0} values read weren't what was written")]
+    Mismatched(u64),
+
+    #[error("Failed to connect to {0}: {1:?}")]
+    ConnectFailed(String, ZkError),
 
     #[error("Unknown error: `{0}`")]
     Unknown(#[from] anyhow::Error),
 }
+
+impl BenchError {
+    /// Exit code of a run failing with this error, telling gates apart from broken runs.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BenchError::InvalidOption(_) => 2,
+            BenchError::Regressed(_) => 3,
+            BenchError::AssertionFailed(_) => 4,
+            BenchError::Unhealthy(_) => 5,
+            BenchError::TimedOut(_) => 6,
+            BenchError::Mismatched(_) => 7,
+            BenchError::ConnectFailed(_, ZkError::AuthFailed | ZkError::NoAuth) => 9,
+            BenchError::ConnectFailed(..) => 8,
+            // Workers that couldn't connect or authenticate say so more precisely
+            BenchError::BenchFailed(e) => match exit_code(e) {
+                code @ (8 | 9) => code,
+                _ => 10,
+            },
+            BenchError::Interrupted() => 130,
+            BenchError::Unknown(e) => exit_code(e),
+        }
+    }
+}
+
+/// Exit code of a run failing with `e`, by the first cause in its chain that tells why: 2 for
+/// invalid options, 3 to 7 for the gates, 8 when sessions can't connect, 9 when the servers
+/// refused the credentials or the ACL, 10 when operations failed, 130 when interrupted and 1
+/// for anything else.
+pub fn exit_code(e: &anyhow::Error) -> u8 {
+    for cause in e.chain() {
+        if let Some(e) = cause.downcast_ref::<BenchError>() {
+            return e.exit_code();
+        }
+        if let Some(e) = cause.downcast_ref::<ZkError>() {
+            return zk_exit_code(e);
+        }
+        if let Some(e) = cause.downcast_ref::<zookeeper_client::Error>() {
+            return match e {
+                zookeeper_client::Error::AuthFailed | zookeeper_client::Error::NoAuth => 9,
+                _ => 10,
+            };
+        }
+    }
+    1
+}
+
+fn zk_exit_code(e: &ZkError) -> u8 {
+    match e {
+        ZkError::AuthFailed | ZkError::NoAuth => 9,
+        _ => 10,
+    }
+}
@@ -0,0 +1,43 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventFormat {
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// Streams significant events of a run on stdout for `--events`, each tagged with its kind, wall
+/// clock and seconds since the run started.
+#[derive(Debug)]
+pub struct Events {
+    start: Instant,
+}
+
+impl Events {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Events {
+            start: Instant::now(),
+        })
+    }
+
+    /// Writes `fields`, an object, as the event `kind`.
+    pub fn emit(&self, kind: &str, fields: Value) {
+        let mut event = json!({
+            "event": kind,
+            "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            "elapsed": self.start.elapsed().as_secs_f64(),
+        });
+        if let (Some(event), Value::Object(fields)) = (event.as_object_mut(), fields) {
+            event.extend(fields);
+        }
+        // Locking stdout keeps lines of concurrent workers whole
+        let mut out = io::stdout().lock();
+        if let Err(e) = writeln!(out, "{}", event).and_then(|_| out.flush()) {
+            log::warn!("Failed to emit event, {}", e);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! The benchmarks behind the `zoobench` binary, for tools that run them in-process rather
+//! than through it.
+//!
+//! [`bench::BenchOption::builder`] makes the options of a run, starting from the defaults
+//! `zoobench run` has. [`bench::run`] runs the workload they select, returning a
+//! [`bench::RunResult`], and [`bench::run_phase`] the write or read phase of the standard mode
+//! on its own, returning a [`bench::PhaseResult`]. Both serialize to the JSON `--format json`
+//! prints. [`bench::cleanup`] deletes what the runs left below the prefix. Unlike the binary,
+//! they serve no Prometheus metrics until [`bench::BenchOption::serve_metrics`] is called.
+//!
+//! Crates with workloads of their own implement [`bench::workload::Workload`] and run it with
+//! [`bench::workload::run`], which connects the workers, paces them and measures them the same
+//! way the standard mode does.
+
+pub mod acl;
+pub mod assertion;
+pub mod bench;
+pub mod chart;
+pub mod cli;
+pub mod cluster;
+pub mod compare;
+pub mod config;
+pub mod dist;
+pub mod error;
+pub mod events;
+pub mod logger;
+pub mod metrics;
+pub mod output;
+pub mod payload;
+pub mod proxy;
+pub mod recipes;
+pub mod report;
+pub mod rng;
+pub mod sasl;
+pub mod server;
+pub mod stats;
+pub mod tls;
+pub mod trace;
+pub mod tui;
+pub mod wire;
@@ -0,0 +1,112 @@
+//! The log of the `zoobench` binary: the terminal, as simple_logger prints it, and optionally a
+//! `--log-file` with every line the terminal would show even while `--quiet` or `--tui` mute it.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use simple_logger::SimpleLogger;
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+struct Logger {
+    terminal: SimpleLogger,
+    file: Mutex<Option<LineWriter<File>>>,
+    verbosity: AtomicU8,
+    /// Most verbose level the terminal shows, as a `LevelFilter`
+    terminal_level: AtomicUsize,
+}
+
+impl Logger {
+    /// Most verbose level logged for `target`: info at first, and each `-v` one level more for
+    /// zoobench and, from the second on, for the libraries it uses.
+    fn level(&self, target: &str) -> LevelFilter {
+        let verbosity = self.verbosity.load(Ordering::Relaxed) as usize;
+        let own = target == "zoobench" || target.starts_with("zoobench::");
+        let extra = match own {
+            true => verbosity,
+            false => verbosity.saturating_sub(1),
+        };
+        level_filter(LevelFilter::Info as usize + extra)
+    }
+}
+
+fn level_filter(n: usize) -> LevelFilter {
+    LevelFilter::iter().nth(n).unwrap_or(LevelFilter::Trace)
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if record.level() as usize <= self.terminal_level.load(Ordering::Relaxed) {
+            self.terminal.log(record);
+        }
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            // A log that can't be written has nowhere to report it
+            let _ = writeln!(
+                file,
+                "{} {:<5} [{}] {}",
+                humantime::format_rfc3339_millis(SystemTime::now()),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn logger() -> &'static Logger {
+    LOGGER.get_or_init(|| Logger {
+        terminal: SimpleLogger::new(),
+        file: Mutex::new(None),
+        verbosity: AtomicU8::new(0),
+        terminal_level: AtomicUsize::new(LevelFilter::Trace as usize),
+    })
+}
+
+/// Logs info messages and above on the terminal, until `configure` says otherwise.
+pub fn init() -> Result<(), log::SetLoggerError> {
+    log::set_logger(logger())?;
+    log::set_max_level(LevelFilter::Info);
+    Ok(())
+}
+
+/// Logs `verbosity` levels more than info, and every line into `file` as well, appending to it.
+pub fn configure(verbosity: u8, file: Option<&Path>) -> io::Result<()> {
+    let logger = logger();
+    if let Some(path) = file {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *logger.file.lock().unwrap() = Some(LineWriter::new(file));
+    }
+    logger.verbosity.store(verbosity, Ordering::Relaxed);
+    log::set_max_level(level_filter(
+        LevelFilter::Info as usize + verbosity as usize,
+    ));
+    Ok(())
+}
+
+/// Shows only messages up to `level` on the terminal, returning the level it showed before. The
+/// `--log-file` still gets every message.
+pub fn set_terminal_level(level: LevelFilter) -> LevelFilter {
+    level_filter(
+        logger()
+            .terminal_level
+            .swap(level as usize, Ordering::Relaxed),
+    )
+}
@@ -1,66 +1,1122 @@
-#![feature(fn_ptr_trait)]
-
-mod bench;
-mod error;
-
-use crate::bench::BenchOption;
 use bytesize::ByteSize;
-use clap::Parser;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::{self, ExitCode};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Duration;
+use zoobench::bench::plan::PlannedPhase;
+use zoobench::bench::{self, BenchOption, Interrupter, RunResult};
+use zoobench::cli::{CleanupArgs, Command, CompareArgs, Format, GcArgs, PopulateArgs, RunArgs};
+use zoobench::error::{self, BenchError};
+use zoobench::output::{self, Metadata, Report, TextOutput};
+use zoobench::{assertion, chart, compare, config, logger, report, rng, server};
+
+/// Logs a line of the text results, or writes it to the `--output` file.
+macro_rules! info {
+    ($out:expr, $($arg:tt)*) => {
+        $out.line(log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! warn {
+    ($out:expr, $($arg:tt)*) => {
+        $out.line(log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+fn format_timeline(timeline: &[u64]) -> String {
+    let seconds: Vec<String> = timeline.iter().map(|n| n.to_string()).collect();
+    seconds.join(", ")
+}
+
+fn print_timeline(out: &mut TextOutput, label: &str, timeline: &[u64]) {
+    info!(out, "{} ops/s: {}", label, format_timeline(timeline));
+    if let (Some(min), Some(max)) = (timeline.iter().min(), timeline.iter().max()) {
+        info!(
+            out,
+            "{} ops/s: {} (min: {}, max: {})",
+            label,
+            chart::sparkline(timeline),
+            min,
+            max
+        );
+    }
+}
+
+fn print_histogram(out: &mut TextOutput, label: &str, buckets: &[bench::Bucket]) {
+    info!(out, "{} latency distribution:", label);
+    for line in chart::histogram(buckets) {
+        info!(out, "  {}", line);
+    }
+}
+
+fn print_servers(
+    out: &mut TextOutput,
+    phase: &str,
+    servers: &BTreeMap<String, bench::ServerResult>,
+) {
+    for (server, r) in servers {
+        info!(
+            out,
+            "{} on {}: {:.2} ops/s, workers: {}, {}",
+            phase,
+            server,
+            r.throughput,
+            r.workers,
+            r.latency
+        );
+    }
+}
+
+fn print_bench_result(out: &mut TextOutput, b: &bench::BenchResult) {
+    info!(
+        out,
+        "TPS: {:.2}, QPS: {:.2}", b.write.throughput, b.read.throughput
+    );
+    info!(
+        out,
+        "Write elapsed: {:?}, {}", b.write.elapsed, b.write.latency
+    );
+    info!(
+        out,
+        "Read elapsed: {:?}, {}", b.read.elapsed, b.read.latency
+    );
+    if let Some(l) = &b.write.corrected_latency {
+        info!(out, "Write corrected: {}", l);
+    }
+    if let Some(l) = &b.read.corrected_latency {
+        info!(out, "Read corrected: {}", l);
+    }
+    for (phase, r) in [("Write", &b.write), ("Read", &b.read)] {
+        info!(
+            out,
+            "{} network: sent {} ({}/s), received {} ({}/s)",
+            phase,
+            ByteSize(r.bytes_sent).to_string_as(true),
+            ByteSize(r.send_bandwidth as u64).to_string_as(true),
+            ByteSize(r.bytes_received).to_string_as(true),
+            ByteSize(r.receive_bandwidth as u64).to_string_as(true)
+        );
+    }
+    for (phase, r) in [("Write", &b.write), ("Read", &b.read)] {
+        for (depth, l) in &r.depths {
+            info!(out, "{} at depth {}: {}", phase, depth, l);
+        }
+    }
+    for (phase, r) in [("Write", &b.write), ("Read", &b.read)] {
+        print_servers(out, phase, &r.servers);
+    }
+    print_timeline(out, "Write", &b.write.timeline);
+    print_histogram(out, "Write", &b.write.histogram);
+    print_timeline(out, "Read", &b.read.timeline);
+    print_histogram(out, "Read", &b.read.histogram);
+    for (phase, r) in [("Write", &b.write), ("Read", &b.read)] {
+        if !r.errors.is_empty() {
+            warn!(out, "{} errors: {}", phase, r.errors);
+        }
+        if let Some(n) = r.existing {
+            info!(out, "{} skipped {} znodes that existed", phase, n);
+        }
+        print_mismatches(out, phase, r.mismatches);
+        for (tid, w) in r.workers.iter().enumerate() {
+            info!(
+                out,
+                "{} worker #{}: {:.2} ops/s, connect: {:?}, elapsed: {:?}, p50: {:?}, p99: {:?}, max: {:?}",
+                phase,
+                tid,
+                w.throughput,
+                w.connect,
+                w.elapsed,
+                w.latency.p50,
+                w.latency.p99,
+                w.latency.max
+            );
+        }
+    }
+}
+
+/// Prints a phase run on its own, by the write or read mode, as the standard mode prints each.
+fn print_phase_result(out: &mut TextOutput, phase: &str, r: &bench::PhaseResult) {
+    info!(
+        out,
+        "{} throughput: {:.2} ops/s, elapsed: {:?}, {}", phase, r.throughput, r.elapsed, r.latency
+    );
+    if let Some(l) = &r.corrected_latency {
+        info!(out, "{} corrected: {}", phase, l);
+    }
+    info!(
+        out,
+        "{} network: sent {} ({}/s), received {} ({}/s)",
+        phase,
+        ByteSize(r.bytes_sent).to_string_as(true),
+        ByteSize(r.send_bandwidth as u64).to_string_as(true),
+        ByteSize(r.bytes_received).to_string_as(true),
+        ByteSize(r.receive_bandwidth as u64).to_string_as(true)
+    );
+    for (depth, l) in &r.depths {
+        info!(out, "{} at depth {}: {}", phase, depth, l);
+    }
+    print_servers(out, phase, &r.servers);
+    print_timeline(out, phase, &r.timeline);
+    print_histogram(out, phase, &r.histogram);
+    if !r.errors.is_empty() {
+        warn!(out, "{} errors: {}", phase, r.errors);
+    }
+    if let Some(n) = r.existing {
+        info!(out, "{} skipped {} znodes that existed", phase, n);
+    }
+    print_mismatches(out, phase, r.mismatches);
+    for (tid, w) in r.workers.iter().enumerate() {
+        info!(
+            out,
+            "{} worker #{}: {:.2} ops/s, connect: {:?}, elapsed: {:?}, p50: {:?}, p99: {:?}, max: {:?}",
+            phase,
+            tid,
+            w.throughput,
+            w.connect,
+            w.elapsed,
+            w.latency.p50,
+            w.latency.p99,
+            w.latency.max
+        );
+    }
+}
+
+fn print_fanout_result(out: &mut TextOutput, r: &bench::fanout::FanoutResult) {
+    info!(
+        out,
+        "Updates: {}, watchers: {}, update rate: {:.2}/s, elapsed: {:?}",
+        r.updates,
+        r.watchers,
+        r.updates as f32 / r.elapsed.as_secs_f32(),
+        r.elapsed
+    );
+    info!(out, "Notification latency: {}", r.latency);
+    info!(out, "Dropped: {}, late: {}", r.dropped, r.late);
+    if let Some((wid, s)) = &r.slowest_watcher {
+        info!(out, "Slowest watcher #{}: {}", wid, s);
+    }
+}
+
+fn print_consistency_result(out: &mut TextOutput, r: &bench::consistency::ConsistencyResult) {
+    info!(out, "Updates: {}, readers: {}", r.updates, r.readers);
+    for (name, s) in [("", &r.plain), (" with sync", &r.synced)] {
+        info!(out, "Staleness{}: {}", name, s.latency);
+        info!(out, "Stale reads{}: {} of {}", name, s.stale_reads, s.reads);
+    }
+    for (member, s) in &r.members {
+        info!(out, "Staleness through {}: {}", member, s);
+    }
+}
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Cli {
-    /// ZooKeeper hosts
-    hosts: String,
+fn print_lock_result(out: &mut TextOutput, r: &bench::lock::LockResult) {
+    info!(
+        out,
+        "Acquisitions: {}, locks: {}, acquisitions/s: {:.2}, elapsed: {:?}",
+        r.acquisitions,
+        r.locks,
+        r.acquisitions as f32 / r.elapsed.as_secs_f32(),
+        r.elapsed
+    );
+    info!(out, "Acquisition latency: {}", r.latency);
+}
 
-    /// Connection timeout
-    #[arg(long, short = 't', value_parser = parse_duration, default_value = "10")]
-    timeout: Duration,
+fn print_election_result(out: &mut TextOutput, r: &bench::election::ElectionResult) {
+    info!(
+        out,
+        "Elections: {}, groups: {}, elections/s: {:.2}, elapsed: {:?}",
+        r.elections,
+        r.groups,
+        r.elections as f32 / r.elapsed.as_secs_f32(),
+        r.elapsed
+    );
+    info!(out, "Convergence latency: {}", r.latency);
+}
 
-    /// Number of total znodes
-    #[arg(long, short = 'n', default_value_t = 1000)]
-    iteration: u32,
+fn print_queue_result(out: &mut TextOutput, r: &bench::queue::QueueResult) {
+    info!(
+        out,
+        "Items: {}, producers: {}, consumers: {}", r.items, r.producers, r.consumers
+    );
+    info!(
+        out,
+        "Enqueue/s: {:.2}, dequeue/s: {:.2}",
+        r.items as f32 / r.enqueue_elapsed.as_secs_f32(),
+        r.items as f32 / r.dequeue_elapsed.as_secs_f32()
+    );
+    info!(out, "Enqueue latency: {}", r.enqueue_latency);
+    info!(out, "End-to-end latency: {}", r.end_to_end_latency);
+}
 
-    /// Number of threads
-    #[arg(long, short = 'j', default_value_t = 8)]
-    threads: u32,
+fn print_cas_result(out: &mut TextOutput, r: &bench::cas::CasResult) {
+    info!(
+        out,
+        "Commits: {}, retries: {}, keys: {}, success rate: {:.2}%",
+        r.commits,
+        r.retries,
+        r.keys,
+        r.success_rate() * 100.0
+    );
+    info!(
+        out,
+        "Committed TPS: {:.2}, elapsed: {:?}",
+        r.commits as f32 / r.elapsed.as_secs_f32(),
+        r.elapsed
+    );
+    info!(out, "Commit latency: {}", r.latency);
+    if r.lost_updates != 0 {
+        warn!(out, "Counters disagree with commits by {}", r.lost_updates);
+    }
+}
 
-    /// ZNode value size in bytes
-    #[arg(long, short = 's', value_parser = parse_human_bytes, default_value = "128K")]
-    node_size: usize,
+fn print_session_result(out: &mut TextOutput, r: &bench::session::SessionResult) {
+    info!(
+        out,
+        "Sessions: {}, sessions/s: {:.2}, elapsed: {:?}",
+        r.sessions,
+        r.sessions as f32 / r.elapsed.as_secs_f32(),
+        r.elapsed
+    );
+    info!(out, "Establishment latency: {}", r.latency);
+}
 
-    /// Create ephemeral znode or not
-    #[arg(long, short, default_value_t = false)]
-    ephemeral: bool,
+fn print_op_results(out: &mut TextOutput, elapsed: Duration, ops: &[bench::mixed::OpResult]) {
+    for o in ops {
+        info!(
+            out,
+            "{:?}: {:.2} ops/s, {}",
+            o.op,
+            o.latency.count as f32 / elapsed.as_secs_f32(),
+            o.latency
+        );
+        if let Some(l) = &o.corrected_latency {
+            info!(out, "{:?} corrected: {}", o.op, l);
+        }
+        print_histogram(out, &format!("{:?}", o.op), &o.histogram);
+    }
+}
 
-    /// Test prefix
-    #[arg(long, short, default_value = "/zoobench")]
-    prefix: String,
+fn print_mixed_result(out: &mut TextOutput, r: &bench::mixed::MixedResult) {
+    info!(out, "Elapsed: {:?}", r.elapsed);
+    print_op_results(out, r.elapsed, &r.ops);
+    print_timeline(out, "All", &r.timeline);
+    if !r.errors.is_empty() {
+        warn!(out, "Errors: {}", r.errors);
+    }
+    print_mismatches(out, "Get", r.mismatches);
+}
 
-    #[arg(long, short)]
-    digest: Option<String>,
+/// Tells how many of the values `--verify` checked weren't what was written, if it checked any.
+fn print_mismatches(out: &mut TextOutput, phase: &str, mismatches: Option<u64>) {
+    match mismatches {
+        Some(0) => info!(out, "{} values verified", phase),
+        Some(n) => warn!(
+            out,
+            "{} mismatches: {} values weren't what was written", phase, n
+        ),
+        None => {}
+    }
 }
 
-fn parse_human_bytes(arg: &str) -> Result<usize, String> {
-    arg.parse::<ByteSize>().map(|x| x.as_u64() as usize)
+fn print_sweep_result(out: &mut TextOutput, r: &bench::sweep::SweepResult) {
+    for s in &r.steps {
+        info!(
+            out,
+            "{} children, create p50: {:?}, p99: {:?}, getChildren p50: {:?}, max: {:?}",
+            s.children,
+            s.create.p50,
+            s.create.p99,
+            s.get_children.p50,
+            s.get_children.max
+        );
+    }
+    if let Some(e) = &r.failure {
+        warn!(out, "Sweep stopped early: {}", e);
+    }
 }
 
-fn parse_duration(arg: &str) -> Result<Duration, std::num::ParseIntError> {
-    Ok(Duration::from_secs(arg.parse()?))
+fn print_probe_result(out: &mut TextOutput, r: &bench::probe::ProbeResult) {
+    for s in &r.steps {
+        info!(
+            out,
+            "{}: create p50: {:?}, p99: {:?}, get p50: {:?}, p99: {:?}",
+            ByteSize(s.size as u64).to_string_as(true),
+            s.create.p50,
+            s.create.p99,
+            s.get.p50,
+            s.get.p99
+        );
+    }
+    match &r.failure {
+        Some((size, e)) => warn!(
+            out,
+            "Operations fail from {}: {}",
+            ByteSize(*size as u64).to_string_as(true),
+            e
+        ),
+        None => info!(out, "All payload sizes probed succeeded"),
+    }
 }
 
-fn print_bench_result(b: &bench::BenchResult) {
-    log::info!("TPS: {:.2}, QPS: {:.2}", b.tps, b.qps);
+fn print_delete_result(out: &mut TextOutput, r: &bench::delete::DeleteResult) {
+    info!(
+        out,
+        "Tree depth: {}, width: {}, znodes: {}, built in {:?}",
+        r.depth,
+        r.width,
+        r.nodes,
+        r.build_elapsed
+    );
+    // An interrupted run deletes nothing, leaving no time to take a rate over
+    match r.delete_elapsed.is_zero() {
+        true => info!(out, "delete_recursive: not run"),
+        false => info!(
+            out,
+            "delete_recursive: {:?}, {:.2} znodes/s",
+            r.delete_elapsed,
+            r.nodes as f32 / r.delete_elapsed.as_secs_f32()
+        ),
+    }
 }
 
-fn main() -> Result<(), anyhow::Error> {
-    simple_logger::init_with_level(log::Level::Info).unwrap();
+fn print_hot_result(out: &mut TextOutput, r: &bench::hot::HotResult) {
+    info!(out, "Elapsed: {:?}, conflicts: {}", r.elapsed, r.conflicts);
+    print_op_results(out, r.elapsed, &r.ops);
+    print_timeline(out, "All", &r.timeline);
+    if !r.errors.is_empty() {
+        warn!(out, "Errors: {}", r.errors);
+    }
+}
+
+fn print_concurrency_result(out: &mut TextOutput, r: &bench::concurrency::ConcurrencyResult) {
+    for step in &r.steps {
+        let b = &step.result;
+        info!(
+            out,
+            "{} threads: TPS: {:.2}, QPS: {:.2}, write p99: {:?}, read p99: {:?}",
+            step.threads,
+            b.write.throughput,
+            b.read.throughput,
+            b.write.latency.p99,
+            b.read.latency.p99
+        );
+    }
+    for (phase, knee) in [("Write", r.write_knee), ("Read", r.read_knee)] {
+        match knee {
+            Some(threads) => info!(
+                out,
+                "{} throughput levels off past {} threads", phase, threads
+            ),
+            None => info!(out, "{} throughput shows no knee", phase),
+        }
+    }
+}
 
-    let cli = Cli::parse();
-    dbg!(&cli);
-    let option = BenchOption::from(cli);
-    let r = bench::bench(&option)?;
-    print_bench_result(&r);
+fn print_size_sweep_result(out: &mut TextOutput, r: &bench::sizes::SizeSweepResult) {
+    info!(
+        out,
+        "{:>10} {:>10} {:>10} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "size",
+        "TPS",
+        "QPS",
+        "write p50",
+        "write p99",
+        "read p50",
+        "read p99",
+        "write/s"
+    );
+    for step in &r.steps {
+        let b = &step.result;
+        info!(
+            out,
+            "{:>10} {:>10.2} {:>10.2} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            ByteSize(step.node_size as u64).to_string_as(true),
+            b.write.throughput,
+            b.read.throughput,
+            format!("{:.2?}", b.write.latency.p50),
+            format!("{:.2?}", b.write.latency.p99),
+            format!("{:.2?}", b.read.latency.p50),
+            format!("{:.2?}", b.read.latency.p99),
+            ByteSize(b.write.send_bandwidth as u64).to_string_as(true)
+        );
+    }
+}
+
+fn print_pipeline_result(out: &mut TextOutput, r: &bench::pipeline::PipelineResult) {
+    info!(
+        out,
+        "{:>10} {:>10} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "in-flight",
+        "TPS",
+        "QPS",
+        "write p50",
+        "write p99",
+        "read p50",
+        "read p99"
+    );
+    for step in &r.steps {
+        let b = &step.result;
+        info!(
+            out,
+            "{:>10} {:>10.2} {:>10.2} {:>12} {:>12} {:>12} {:>12}",
+            step.in_flight,
+            b.write.throughput,
+            b.read.throughput,
+            format!("{:.2?}", b.write.latency.p50),
+            format!("{:.2?}", b.write.latency.p99),
+            format!("{:.2?}", b.read.latency.p50),
+            format!("{:.2?}", b.read.latency.p99)
+        );
+    }
+}
+
+fn print_scenario_result(out: &mut TextOutput, r: &bench::scenario::ScenarioResult) {
+    use bench::scenario::Outcome;
+    for phase in &r.phases {
+        match &phase.result {
+            Outcome::Phase(p) => {
+                info!(
+                    out,
+                    "{}: {:.2} ops/s, elapsed: {:?}, {}",
+                    phase.name,
+                    p.throughput,
+                    p.elapsed,
+                    p.latency
+                );
+                if !p.errors.is_empty() {
+                    warn!(out, "{} errors: {}", phase.name, p.errors);
+                }
+            }
+            Outcome::Mixed(m) => {
+                info!(out, "{}:", phase.name);
+                print_op_results(out, m.elapsed, &m.ops);
+                if !m.errors.is_empty() {
+                    warn!(out, "{} errors: {}", phase.name, m.errors);
+                }
+            }
+            Outcome::Cleanup { elapsed } => info!(out, "{}: deleted in {:?}", phase.name, elapsed),
+        }
+    }
+}
+
+fn print_metadata(
+    out: &mut TextOutput,
+    meta: &Metadata,
+    option: &BenchOption,
+) -> Result<(), anyhow::Error> {
+    info!(
+        out,
+        "zoobench {} on {}, started {}, ZooKeeper {}",
+        meta.version,
+        meta.hostname.as_deref().unwrap_or("unknown host"),
+        meta.timestamp,
+        meta.server_version.as_deref().unwrap_or("unknown")
+    );
+    if !meta.tags.is_empty() {
+        let tags: Vec<String> = meta
+            .tags
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        info!(out, "Tags: {}", tags.join(", "));
+    }
+    let c = &meta.client;
+    info!(
+        out,
+        "Client: {} {}, kernel: {}, CPU: {}, cores: {}, MTU: {}",
+        c.os,
+        c.arch,
+        c.kernel.as_deref().unwrap_or("unknown"),
+        c.cpu_model.as_deref().unwrap_or("unknown"),
+        c.cores.map_or("unknown".to_string(), |n| n.to_string()),
+        match (&c.nic, c.mtu) {
+            (Some(nic), Some(mtu)) => format!("{} on {}", mtu, nic),
+            _ => "unknown".to_string(),
+        }
+    );
+    for m in &meta.ensemble {
+        let Some(mode) = &m.mode else {
+            warn!(out, "Member {}: didn't answer srvr", m.server);
+            continue;
+        };
+        let mut line = format!("Member {}: {}", m.server, mode);
+        if let Some(id) = m.server_id {
+            line += &format!(" #{}", id);
+        }
+        if let Some(n) = m.node_count {
+            line += &format!(", znodes: {}", n);
+        }
+        if !m.peers.is_empty() {
+            line += &format!(", peers: {}", m.peers.len());
+        }
+        if let Some(tick) = m.tick_time {
+            line += &format!(", tick: {}ms", tick);
+        }
+        if let (Some(min), Some(max)) = (m.min_session_timeout, m.max_session_timeout) {
+            line += &format!(", session timeouts: {}ms to {}ms", min, max);
+        }
+        if let Some(n) = m.max_client_cnxns {
+            line += &format!(", max connections: {}", n);
+        }
+        info!(out, "{}", line);
+    }
+    if let Some(negotiated) = meta.session_timeout {
+        if negotiated == option.session_timeout {
+            info!(out, "Session timeout: {:?}", negotiated);
+        } else {
+            warn!(
+                out,
+                "Session timeout: {:?}, the servers' bounds in place of {:?}",
+                negotiated,
+                option.session_timeout
+            );
+        }
+    }
+    info!(out, "Options: {}", serde_json::to_string(option)?);
     Ok(())
 }
+
+fn print_sessions(out: &mut TextOutput, s: &bench::SessionStats) {
+    info!(out, "Session establishment: {}", s.latency);
+    print_histogram(out, "Session establishment", &s.histogram);
+    if s.outages.count > 0 {
+        warn!(out, "Sessions lost and re-established: {}", s.outages);
+    }
+    if let Some(chaos) = s.chaos.as_ref().filter(|c| c.recovery.count > 0) {
+        warn!(
+            out,
+            "Sessions killed by --chaos, recovery: {}", chaos.recovery
+        );
+        if let Some(dip) = chaos.throughput_dip {
+            warn!(out, "Throughput dip while recovering: {:.1}%", dip * 100.0);
+        }
+        if chaos.recreation.count > 0 {
+            info!(
+                out,
+                "Ephemeral znodes re-created: {}, per kill: {}", chaos.recreated, chaos.recreation
+            );
+        }
+    }
+}
+
+fn print_server_stats(out: &mut TextOutput, phases: &[server::PhaseStats]) {
+    for p in phases {
+        for m in &p.members {
+            let deltas: Vec<String> = m
+                .delta
+                .iter()
+                .map(|(k, v)| format!("{}: {:+}", k, v))
+                .collect();
+            info!(
+                out,
+                "Server {} during {}: {}",
+                m.server,
+                p.phase,
+                deltas.join(", ")
+            );
+        }
+    }
+}
+
+fn print_read_only_result(out: &mut TextOutput, r: &bench::readonly::ReadOnlyResult) {
+    info!(
+        out,
+        "Reads: {}, availability: {:.2}%, throughput: {:.2}/s, elapsed: {:?}",
+        r.read.ops,
+        r.availability * 100.0,
+        r.read.throughput,
+        r.read.elapsed
+    );
+    info!(out, "Read latency: {}", r.read.latency);
+    info!(
+        out,
+        "Sessions: {}, read-only: {}", r.handshakes, r.read_only_handshakes
+    );
+    print_servers(out, "Read", &r.read.servers);
+    print_timeline(out, "Read", &r.read.timeline);
+    print_histogram(out, "Read", &r.read.histogram);
+    if !r.read.errors.is_empty() {
+        warn!(out, "Errors: {}", r.read.errors);
+    }
+}
+
+fn print_plan(out: &mut TextOutput, access: &bench::plan::Access, phases: &[PlannedPhase]) {
+    info!(out, "Connected in {:?}", access.connect);
+    for p in &access.paths {
+        info!(out, "ACL of {}: {}", p.path, p.acl.join(", "));
+        info!(out, "Granted: {}, needed: {}", p.granted, p.needed);
+        if p.undecided > 0 {
+            warn!(
+                out,
+                "{} ACL entries of schemes other than world and digest may grant more", p.undecided
+            );
+        }
+    }
+    for p in phases {
+        let ops = match (p.ops, p.duration) {
+            (Some(ops), _) => format!("{} ops", ops),
+            (None, Some(d)) => format!("for {:?}", d),
+            (None, None) => "ops as the mode decides".to_string(),
+        };
+        let bytes = match p.bytes {
+            Some(bytes) => ByteSize(bytes).to_string_as(true),
+            None => "unknown".to_string(),
+        };
+        info!(
+            out,
+            "Phase {}: {} threads, {}, writes {}", p.name, p.threads, ops, bytes
+        );
+    }
+    let ops: Option<u64> = phases.iter().map(|p| p.ops).sum();
+    let bytes: Option<u64> = phases.iter().map(|p| p.bytes).sum();
+    info!(
+        out,
+        "Total: {} ops, writes {}",
+        ops.map_or("unknown".to_string(), |n| n.to_string()),
+        bytes.map_or("unknown".to_string(), |n| ByteSize(n).to_string_as(true))
+    );
+}
+
+fn print_result(out: &mut TextOutput, result: &RunResult) {
+    match result {
+        RunResult::Standard(r) => print_bench_result(out, r),
+        RunResult::Write(r) => print_phase_result(out, "Write", r),
+        RunResult::Read(r) => print_phase_result(out, "Read", r),
+        RunResult::WatchFanout(r) => print_fanout_result(out, r),
+        RunResult::Lock(r) => print_lock_result(out, r),
+        RunResult::Election(r) => print_election_result(out, r),
+        RunResult::Queue(r) => print_queue_result(out, r),
+        RunResult::Cas(r) => print_cas_result(out, r),
+        RunResult::SessionChurn(r) => print_session_result(out, r),
+        RunResult::Mixed(r) => print_mixed_result(out, r),
+        RunResult::ChildSweep(r) => print_sweep_result(out, r),
+        RunResult::PayloadProbe(r) => print_probe_result(out, r),
+        RunResult::DeleteRecursive(r) => print_delete_result(out, r),
+        RunResult::HotZnode(r) => print_hot_result(out, r),
+        RunResult::ConcurrencySweep(r) => print_concurrency_result(out, r),
+        RunResult::SizeSweep(r) => print_size_sweep_result(out, r),
+        RunResult::Pipeline(r) => print_pipeline_result(out, r),
+        RunResult::Scenario(r) => print_scenario_result(out, r),
+        RunResult::Script(r) => print_mixed_result(out, r),
+        RunResult::ReadOnly(r) => print_read_only_result(out, r),
+        RunResult::ReadAfterWrite(r) => print_consistency_result(out, r),
+    }
+}
+
+fn main() -> ExitCode {
+    logger::init().unwrap();
+
+    let cli = match config::parse().and_then(|cli| {
+        logger::configure(cli.verbose, cli.log_file.as_deref())
+            .map_err(|e| BenchError::InvalidOption(format!("can't open the log file, {}", e)))?;
+        Ok(cli)
+    }) {
+        Ok(cli) => cli,
+        Err(e) => {
+            log::error!("{:#}", e);
+            return ExitCode::from(error::exit_code(&e));
+        }
+    };
+    if let Err(e) = catch_interrupts() {
+        log::error!("Can't catch signals, {}", e);
+        return ExitCode::FAILURE;
+    }
+    let result = match cli.command {
+        Command::Run(args) => run(*args),
+        Command::Write(args) => run((*args).into()),
+        Command::Read(args) => run((*args).into()),
+        Command::Mixed(args) => run((*args).into()),
+        Command::Watch(args) => run((*args).into()),
+        Command::Populate(args) => populate(*args),
+        Command::Cleanup(args) => cleanup(*args),
+        Command::Gc(args) => gc(*args),
+        Command::Compare(args) => compare_runs(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{:#}", e);
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+/// The run signals stop, if one is going on.
+static RUNNING: Mutex<Option<Interrupter>> = Mutex::new(None);
+
+/// Makes SIGINT and SIGTERM stop the run going on, which then reports what it measured until
+/// then. A second signal, or one while no run is going on, quits at once.
+fn catch_interrupts() -> Result<(), anyhow::Error> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let quit = match RUNNING.lock().unwrap().as_ref() {
+                Some(running) => running.interrupt(signal),
+                None => true,
+            };
+            if quit {
+                process::exit(BenchError::Interrupted().exit_code().into());
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Points signals at the run of an option until dropped, so a run against a later ensemble
+/// isn't stopped by what stopped an earlier one.
+struct Running;
+
+impl Running {
+    fn new(option: &BenchOption) -> Self {
+        *RUNNING.lock().unwrap() = Some(option.interrupter());
+        Running
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        *RUNNING.lock().unwrap() = None;
+    }
+}
+
+/// Where a run writes its results: on its own, or among those of runs against other ensembles,
+/// into the text results they share or the JSON they're collected into.
+enum Results<'a> {
+    Alone,
+    Among(Option<&'a mut TextOutput>),
+}
+
+/// What a run against one ensemble reported, and whether it passed its gates.
+struct Ran {
+    report: serde_json::Value,
+    gates: Result<(), anyhow::Error>,
+}
+
+/// Runs against each ensemble in turn, comparing their results side by side when there are
+/// several. A failed gate or run doesn't keep the rest from running, but an interrupt does,
+/// and what the runs reported until then is shown either way.
+fn run(cli: RunArgs) -> Result<(), anyhow::Error> {
+    let clusters = cli.connect.clusters()?;
+    if clusters.len() == 1 {
+        return run_on(cli, Results::Alone)?.gates;
+    }
+    if cli.output.report.is_some() {
+        return Err(BenchError::InvalidOption(
+            "--report takes the results of one ensemble, not several".to_string(),
+        )
+        .into());
+    }
+    let format = cli.output.format;
+    let output = cli.output.output.clone();
+    let dry_run = cli.output.dry_run;
+    let mut out = match format {
+        Format::Text => Some(TextOutput::new(output.as_deref())?),
+        Format::Json => None,
+    };
+    let (mut runs, mut gates) = (Vec::new(), Ok(()));
+    for cluster in clusters {
+        log::info!("Running against {}", cluster);
+        let label = cluster.label().to_string();
+        // An ensemble that couldn't be run against doesn't keep the others from being compared
+        let ran =
+            run_on(cli.for_cluster(cluster), Results::Among(out.as_mut())).unwrap_or_else(|e| {
+                if let Some(out) = out.as_mut() {
+                    warn!(out, "Failed against {}, {:#}", label, e);
+                }
+                Ran {
+                    report: serde_json::json!({ "cluster": &label, "error": format!("{:#}", e) }),
+                    gates: Err(e),
+                }
+            });
+        runs.push((label.clone(), ran.report));
+        match ran.gates {
+            Err(e) if matches!(e.downcast_ref(), Some(BenchError::Interrupted())) => {
+                gates = Err(e);
+                break;
+            }
+            Err(e) if gates.is_ok() => gates = Err(e),
+            Err(e) => log::error!("{}: {:#}", label, e),
+            Ok(()) => {}
+        }
+    }
+    match out {
+        Some(mut out) => {
+            if !dry_run {
+                info!(out, "Side by side:");
+                for line in compare::side_by_side(&runs) {
+                    info!(out, "{}", line);
+                }
+            }
+            out.finish()?;
+        }
+        None => {
+            let reports: Vec<_> = runs.into_iter().map(|(_, report)| report).collect();
+            let json = serde_json::to_string_pretty(&serde_json::json!({ "clusters": reports }))?;
+            match &output {
+                Some(path) => {
+                    fs::write(path, json + "\n")?;
+                    log::info!("Results written to {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+    gates
+}
+
+fn run_on(cli: RunArgs, results: Results) -> Result<Ran, anyhow::Error> {
+    log::debug!("{:#?}", cli);
+    let format = cli.output.format;
+    let output = cli.output.output.clone();
+    let csv_out = cli.output.csv_out.clone();
+    let report_path = cli.output.report.clone();
+    let baseline = cli
+        .output
+        .baseline
+        .as_deref()
+        .map(compare::load)
+        .transpose()?;
+    let regression_threshold = cli.output.regression_threshold;
+    let assertions = cli.output.assertions.clone();
+    let quiet = cli.output.quiet;
+    let preflight = cli.connect.preflight;
+    let dry_run = cli.output.dry_run;
+    let option = options(cli)?;
+    let _running = Running::new(&option);
+    let health = match preflight {
+        Some(policy) => server::preflight(&option.hosts, option.connect_timeout, policy)?,
+        None => Vec::new(),
+    };
+    if dry_run {
+        let access = bench::plan::check(&option)?;
+        let phases = bench::plan::plan(&option);
+        let planned = serde_json::json!({
+            "options": &option,
+            "access": access,
+            "phases": phases,
+        });
+        match (format, results) {
+            (_, Results::Among(Some(out))) => print_plan(out, &access, &phases),
+            (_, Results::Among(None)) => {}
+            (Format::Text, Results::Alone) => {
+                let mut out = TextOutput::new(output.as_deref())?;
+                print_plan(&mut out, &access, &phases);
+                out.finish()?;
+            }
+            (Format::Json, Results::Alone) => {
+                let json = serde_json::to_string_pretty(&planned)?;
+                match &output {
+                    Some(path) => fs::write(path, json + "\n")?,
+                    None => println!("{}", json),
+                }
+            }
+        }
+        return Ok(Ran {
+            report: planned,
+            gates: Ok(()),
+        });
+    }
+    let meta = Metadata::collect(&option);
+    let deadline = option.start_deadline();
+    if quiet {
+        logger::set_terminal_level(log::LevelFilter::Warn);
+    }
+    let result = bench::run(&option);
+    drop(deadline);
+    logger::set_terminal_level(log::LevelFilter::Trace);
+    let result = result?;
+    let sessions = option.session_stats()?;
+    let retries = option.retry_stats();
+    let server = option.server_phases();
+    let interrupted = option.interrupted();
+    let timed_out = option.timed_out();
+    let report = Report {
+        meta: &meta,
+        options: &option,
+        result: &result,
+        sessions: &sessions,
+        retries: &retries,
+        preflight: &health,
+        server: &server,
+        interrupted,
+        timed_out,
+    };
+    if let Some(events) = &option.events {
+        events.emit("result", serde_json::to_value(&report)?);
+    }
+    let print = |out: &mut TextOutput| -> Result<(), anyhow::Error> {
+        print_metadata(out, &meta, &option)?;
+        if timed_out {
+            warn!(out, "Ran out of --max-runtime, these results are partial");
+        } else if interrupted {
+            warn!(out, "Interrupted, these results are partial");
+        }
+        print_result(out, &result);
+        print_sessions(out, &sessions);
+        if let Some(retries) = retries.as_ref().filter(|r| !r.is_empty()) {
+            warn!(out, "Retries: {}", retries);
+        }
+        print_server_stats(out, &server);
+        Ok(())
+    };
+    match (format, results) {
+        (_, Results::Among(Some(out))) => print(out)?,
+        (_, Results::Among(None)) => {}
+        (Format::Text, Results::Alone) => {
+            let mut out = TextOutput::new(output.as_deref())?;
+            print(&mut out)?;
+            out.finish()?;
+        }
+        (Format::Json, Results::Alone) => {
+            let json = serde_json::to_string_pretty(&report)?;
+            match &output {
+                Some(path) => {
+                    fs::write(path, json + "\n")?;
+                    log::info!("Results written to {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+    // Partial results would skew the runs they're compared against
+    match &csv_out {
+        Some(path) if interrupted => {
+            log::warn!("Not appending partial results to {}", path.display())
+        }
+        Some(path) => output::append_csv(path, &report)?,
+        None => {}
+    }
+    if let Some(path) = &report_path {
+        report::write(path, &report)?;
+    }
+    option.flush_outputs()?;
+    // Once the results are safe, and after the stats were taken, which the session cleaning up
+    // would otherwise count towards
+    if !option.keep_data {
+        delete_data(&option, timed_out, interrupted);
+    }
+    let value = serde_json::to_value(&report)?;
+    let gates = match option.max_runtime() {
+        Some(max_runtime) if timed_out => Err(BenchError::TimedOut(max_runtime).into()),
+        _ if interrupted => Err(BenchError::Interrupted().into()),
+        _ if option.mismatches() > 0 => Err(BenchError::Mismatched(option.mismatches()).into()),
+        _ => {
+            // Assertions are checked even after a regression, so the log shows every failed gate
+            let compared = match &baseline {
+                Some(baseline) => compare::compare(baseline, &value, regression_threshold),
+                None => Ok(()),
+            };
+            assertion::check(&assertions, &report).and(compared)
+        }
+    };
+    Ok(Ran {
+        report: value,
+        gates,
+    })
+}
+
+/// Deletes the prefix after a run that didn't `--keep-data`, unless the run was cut short, only
+/// warning if it can't as the run itself went fine.
+fn delete_data(option: &BenchOption, timed_out: bool, interrupted: bool) {
+    if timed_out {
+        log::warn!(
+            "Leaving {} in place, as the ensemble may be too slow to delete it",
+            option.prefix
+        );
+    } else if interrupted {
+        log::warn!(
+            "Leaving {} in place, as the run was interrupted",
+            option.prefix
+        );
+    } else {
+        log::info!("Deleting {}", option.prefix);
+        if let Err(e) = bench::cleanup(option) {
+            log::warn!("Couldn't delete {}, {}", option.prefix, e);
+        }
+    }
+}
+
+/// The options of `args`, with the process set up to run them: their metrics served on
+/// `--prometheus-listen` and this thread's random numbers drawn from `--seed`.
+fn options(args: RunArgs) -> Result<BenchOption, anyhow::Error> {
+    let option = BenchOption::try_from(args)?;
+    option.serve_metrics()?;
+    rng::reseed(option.seed(), 0);
+    Ok(option)
+}
+
+/// Calls `f` with the options against each ensemble in turn, stopping at the first that fails.
+fn for_each_cluster(
+    args: RunArgs,
+    mut f: impl FnMut(BenchOption) -> Result<(), anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    let clusters = args.connect.clusters()?;
+    let several = clusters.len() > 1;
+    for cluster in clusters {
+        if several {
+            log::info!("Running against {}", cluster);
+        }
+        f(options(args.for_cluster(cluster))?)?;
+    }
+    Ok(())
+}
+
+/// Creates the znodes of `zoobench populate`, reporting only how many and how long it took.
+fn populate(args: PopulateArgs) -> Result<(), anyhow::Error> {
+    let batch = args.batch;
+    for_each_cluster(RunArgs::from(args), |option| {
+        let _running = Running::new(&option);
+        let populated = bench::populate::run(&option, batch)?;
+        log::info!(
+            "Created {} znodes below {} in {:?}, {:.2} znodes/s",
+            populated.created,
+            option.prefix,
+            populated.elapsed,
+            populated.created as f64 / populated.elapsed.as_secs_f64()
+        );
+        if populated.existing > 0 {
+            log::info!("Skipped {} znodes that existed", populated.existing);
+        }
+        if option.interrupted() {
+            return Err(BenchError::Interrupted().into());
+        }
+        if option.mismatches() > 0 {
+            return Err(BenchError::Mismatched(option.mismatches()).into());
+        }
+        Ok(())
+    })
+}
+
+fn cleanup(args: CleanupArgs) -> Result<(), anyhow::Error> {
+    for_each_cluster(RunArgs::from(args), |option| {
+        match bench::cleanup(&option)? {
+            true => log::info!("Deleted {}", option.prefix),
+            false => log::info!("{} doesn't exist", option.prefix),
+        }
+        Ok(())
+    })
+}
+
+fn gc(args: GcArgs) -> Result<(), anyhow::Error> {
+    let older_than = args.older_than;
+    for_each_cluster(RunArgs::from(args), |option| {
+        let deleted = bench::gc::collect(&option, older_than)?;
+        log::info!(
+            "Deleted {} prefixes of runs of {} older than {}",
+            deleted.len(),
+            option.prefix,
+            humantime::format_duration(older_than)
+        );
+        Ok(())
+    })
+}
+
+/// Compares the results of two earlier runs, as `--baseline` does those of the current one.
+fn compare_runs(args: CompareArgs) -> Result<(), anyhow::Error> {
+    let baseline = compare::load(&args.baseline)?;
+    let current = compare::load(&args.current)?;
+    compare::compare(&baseline, &current, args.regression_threshold)
+}
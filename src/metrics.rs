@@ -0,0 +1,332 @@
+use crate::events::Events;
+use crate::stats::Latencies;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use zookeeper::ZkError;
+
+/// Upper bounds in seconds of the operation duration histogram buckets
+const BUCKETS: [f64; 16] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+    5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct OpMetrics {
+    ok: u64,
+    errors: BTreeMap<String, u64>,
+    // Non-cumulative counts per bucket, the last one catching everything above BUCKETS
+    buckets: [u64; BUCKETS.len() + 1],
+    sum: f64,
+}
+
+/// Live operation counters and latency histograms, exposed in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    ops: Mutex<BTreeMap<&'static str, OpMetrics>>,
+    /// The `--tag` labels every series carries, rendered like `,ticket="ZK-1234"`
+    labels: String,
+}
+
+impl Metrics {
+    pub fn new(tags: &BTreeMap<String, String>) -> Self {
+        let mut labels = String::new();
+        for (key, value) in tags {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let _ = write!(labels, ",{}=\"{}\"", key, value);
+        }
+        Metrics {
+            ops: Mutex::default(),
+            labels,
+        }
+    }
+
+    pub fn observe(&self, op: &'static str, latency: Duration, result: Option<&ZkError>) {
+        let secs = latency.as_secs_f64();
+        let mut ops = self.ops.lock().unwrap();
+        let m = ops.entry(op).or_default();
+        match result {
+            None => m.ok += 1,
+            Some(e) => *m.errors.entry(format!("{:?}", e)).or_default() += 1,
+        }
+        m.buckets[BUCKETS.partition_point(|b| *b < secs)] += 1;
+        m.sum += secs;
+    }
+
+    pub fn render(&self) -> String {
+        let ops = self.ops.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP zoobench_operations_total Operations completed, by result.\n");
+        out.push_str("# TYPE zoobench_operations_total counter\n");
+        for (op, m) in ops.iter() {
+            let _ = writeln!(
+                out,
+                "zoobench_operations_total{{op=\"{}\",result=\"OK\"{}}} {}",
+                op, self.labels, m.ok
+            );
+            for (e, n) in &m.errors {
+                let _ = writeln!(
+                    out,
+                    "zoobench_operations_total{{op=\"{}\",result=\"{}\"{}}} {}",
+                    op, e, self.labels, n
+                );
+            }
+        }
+
+        out.push_str("# HELP zoobench_operation_duration_seconds Operation latency.\n");
+        out.push_str("# TYPE zoobench_operation_duration_seconds histogram\n");
+        for (op, m) in ops.iter() {
+            let mut cumulative = 0;
+            for (bound, n) in BUCKETS.iter().zip(m.buckets) {
+                cumulative += n;
+                let _ = writeln!(
+                    out,
+                    "zoobench_operation_duration_seconds_bucket{{op=\"{}\",le=\"{}\"{}}} {}",
+                    op, bound, self.labels, cumulative
+                );
+            }
+            cumulative += m.buckets[BUCKETS.len()];
+            let _ = writeln!(
+                out,
+                "zoobench_operation_duration_seconds_bucket{{op=\"{}\",le=\"+Inf\"{}}} {}",
+                op, self.labels, cumulative
+            );
+            let _ = writeln!(
+                out,
+                "zoobench_operation_duration_seconds_sum{{op=\"{}\"{}}} {}",
+                op, self.labels, m.sum
+            );
+            let _ = writeln!(
+                out,
+                "zoobench_operation_duration_seconds_count{{op=\"{}\"{}}} {}",
+                op, self.labels, cumulative
+            );
+        }
+        out
+    }
+}
+
+/// Metrics served on each address, which the run against the next ensemble takes over
+static SERVED: Mutex<BTreeMap<SocketAddr, Arc<Mutex<Arc<Metrics>>>>> = Mutex::new(BTreeMap::new());
+
+/// Serves `metrics` on every request to `addr` from a background thread for the rest of the
+/// process, or until a later run serves its own there. Binding happens up front so a bad
+/// address fails the run before it starts.
+pub fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> io::Result<()> {
+    let mut served = SERVED.lock().unwrap();
+    if let Some(current) = served.get(&addr) {
+        *current.lock().unwrap() = metrics;
+        return Ok(());
+    }
+    let listener = TcpListener::bind(addr)?;
+    log::info!("Serving Prometheus metrics on http://{}/metrics", addr);
+    let current = Arc::new(Mutex::new(metrics));
+    served.insert(addr, current.clone());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let metrics = current.lock().unwrap().clone();
+            if let Err(e) = respond(stream, &metrics) {
+                log::warn!("Failed to serve metrics, {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    // Whatever was requested, drain the headers and answer with the metrics
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let body = metrics.render();
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Keeps StatsD packets within a typical MTU
+const STATSD_PACKET_SIZE: usize = 1400;
+
+/// Pushes every operation to StatsD as a timer plus an error counter, batched into packets that
+/// are sent when full and at least once a second, leaving aggregation per flush interval to the
+/// StatsD server.
+#[derive(Debug)]
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    pending: Mutex<String>,
+}
+
+impl StatsdSink {
+    pub fn connect(addr: &str, prefix: &str) -> io::Result<Arc<Self>> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(addr)?;
+        let sink = Arc::new(StatsdSink {
+            socket,
+            prefix: prefix.to_string(),
+            pending: Mutex::new(String::new()),
+        });
+        let weak = Arc::downgrade(&sink);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            match weak.upgrade() {
+                Some(sink) => sink.flush(),
+                None => break,
+            }
+        });
+        log::info!("Sending StatsD metrics to {}", addr);
+        Ok(sink)
+    }
+
+    pub fn observe(&self, op: &'static str, latency: Duration, result: Option<&ZkError>) {
+        let mut line = format!(
+            "{}.{}:{:.3}|ms",
+            self.prefix,
+            op,
+            latency.as_secs_f64() * 1000.0
+        );
+        if let Some(e) = result {
+            let _ = write!(line, "\n{}.{}.error.{:?}:1|c", self.prefix, op, e);
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.is_empty() && pending.len() + line.len() + 1 > STATSD_PACKET_SIZE {
+            self.send(&pending);
+            pending.clear();
+        }
+        if !pending.is_empty() {
+            pending.push('\n');
+        }
+        pending.push_str(&line);
+    }
+
+    pub fn flush(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.is_empty() {
+            self.send(&pending);
+            pending.clear();
+        }
+    }
+
+    fn send(&self, packet: &str) {
+        // Like any StatsD client, losing metrics must never fail the benchmark
+        if let Err(e) = self.socket.send(packet.as_bytes()) {
+            log::debug!("Failed to send StatsD packet, {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct IntervalOp {
+    latencies: Latencies,
+    errors: u64,
+}
+
+/// Reports each operation's throughput, errors and p99 over the last interval while running.
+#[derive(Debug)]
+pub struct IntervalReporter {
+    start: Instant,
+    interval: Duration,
+    ops: Mutex<BTreeMap<&'static str, IntervalOp>>,
+    log: Option<Mutex<File>>,
+    events: Option<Arc<Events>>,
+}
+
+impl IntervalReporter {
+    pub fn start(
+        interval: Duration,
+        log: Option<&Path>,
+        events: Option<Arc<Events>>,
+    ) -> io::Result<Arc<Self>> {
+        let log = match log {
+            Some(path) => Some(Mutex::new(File::create(path)?)),
+            None => None,
+        };
+        let reporter = Arc::new(IntervalReporter {
+            start: Instant::now(),
+            interval,
+            ops: Mutex::new(BTreeMap::new()),
+            log,
+            events,
+        });
+        let weak = Arc::downgrade(&reporter);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match weak.upgrade() {
+                Some(reporter) => reporter.report(),
+                None => break,
+            }
+        });
+        Ok(reporter)
+    }
+
+    pub fn observe(&self, op: &'static str, latency: Duration, result: Option<&ZkError>) {
+        let mut ops = self.ops.lock().unwrap();
+        let o = ops.entry(op).or_default();
+        o.latencies.record(latency);
+        if result.is_some() {
+            o.errors += 1;
+        }
+    }
+
+    fn report(&self) {
+        let ops = std::mem::take(&mut *self.ops.lock().unwrap());
+        let elapsed = self.start.elapsed().as_secs();
+        let mut lines = Vec::new();
+        for (op, o) in &ops {
+            let summary = o.latencies.summary();
+            let throughput = summary.count as f64 / self.interval.as_secs_f64();
+            lines.push(format!(
+                "[{}s] {}: {:.2} ops/s, errors: {}, p99: {:?}",
+                elapsed, op, throughput, o.errors, summary.p99
+            ));
+            if let Some(events) = &self.events {
+                events.emit(
+                    "interval",
+                    json!({
+                        "op": op,
+                        "throughput": throughput,
+                        "errors": o.errors,
+                        "latency": summary,
+                    }),
+                );
+            }
+        }
+        // A stalled cluster shows up as an interval without any completed operation
+        if lines.is_empty() {
+            lines.push(format!("[{}s] No operations completed", elapsed));
+        }
+
+        for line in &lines {
+            log::info!("{}", line);
+        }
+        if let Some(log) = &self.log {
+            let mut log = log.lock().unwrap();
+            let now = humantime::format_rfc3339_seconds(SystemTime::now());
+            for line in &lines {
+                if let Err(e) = writeln!(log, "{} {}", now, line) {
+                    log::warn!("Failed to write interval report, {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::bench::{backend, BenchOption, RunResult, SessionStats};
+use crate::error::BenchError;
+use crate::server::{self, Member, MemberHealth, PhaseStats};
+use crate::stats::{serialize_opt_secs, ErrorCounts};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Where, when and against what a run happened, so results can be told apart later.
+#[derive(Serialize)]
+pub struct Metadata {
+    /// When the run started
+    pub timestamp: String,
+    pub hostname: Option<String>,
+    pub version: &'static str,
+    /// As reported by `srvr`, if any server answered it
+    pub server_version: Option<String>,
+    /// What servers granted for the `--session-timeout` asked for, if a session could be had
+    #[serde(serialize_with = "serialize_opt_secs")]
+    pub session_timeout: Option<Duration>,
+    pub client: Client,
+    /// Each member of the ensemble as it described itself when the run started
+    pub ensemble: Vec<Member>,
+    /// The `--tag` labels of the run, in one `key=value;...` column of CSV rows
+    pub tags: BTreeMap<String, String>,
+}
+
+/// The machine the client ran on, as far as it tells.
+#[derive(Serialize)]
+pub struct Client {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    /// CPUs the client could use
+    pub cores: Option<usize>,
+    /// Interface of the default route, and its MTU
+    pub nic: Option<String>,
+    pub mtu: Option<u32>,
+}
+
+impl Client {
+    fn collect() -> Self {
+        let nic = default_interface();
+        let mtu = nic.as_ref().and_then(|nic| {
+            let mtu = fs::read_to_string(format!("/sys/class/net/{}/mtu", nic)).ok()?;
+            mtu.trim().parse().ok()
+        });
+        Client {
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+            kernel: fs::read_to_string("/proc/sys/kernel/osrelease")
+                .ok()
+                .map(|k| k.trim().to_string()),
+            cpu_model: cpu_model(),
+            cores: thread::available_parallelism().ok().map(usize::from),
+            nic,
+            mtu,
+        }
+    }
+}
+
+/// Model of the first CPU in /proc/cpuinfo, which names it differently on different
+/// architectures.
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    ["model name", "Model", "Hardware", "cpu model", "cpu"]
+        .iter()
+        .find_map(|key| {
+            cpuinfo.lines().find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                (k.trim() == *key && !v.trim().is_empty()).then(|| v.trim().to_string())
+            })
+        })
+}
+
+/// Interface the default route of /proc/net/route goes through.
+fn default_interface() -> Option<String> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    routes.lines().skip(1).find_map(|l| {
+        let mut fields = l.split_whitespace();
+        let (iface, destination) = (fields.next()?, fields.next()?);
+        (destination == "00000000").then(|| iface.to_string())
+    })
+}
+
+impl Metadata {
+    pub fn collect(opt: &BenchOption) -> Self {
+        let ensemble = server::ensemble(&opt.hosts, opt.connect_timeout);
+        Metadata {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            hostname: hostname(),
+            version: env!("CARGO_PKG_VERSION"),
+            server_version: ensemble.iter().find_map(|m| m.version.clone()),
+            session_timeout: backend::negotiated_timeout(opt)
+                .map_err(|e| log::warn!("Failed to negotiate a session timeout, {}", e))
+                .ok(),
+            client: Client::collect(),
+            ensemble,
+            tags: opt.tags.clone(),
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .or_else(|_| env::var("HOSTNAME"))
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+}
+
+/// Everything a run reports, as emitted by `--format json` and `--csv-out`.
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub meta: &'a Metadata,
+    pub options: &'a BenchOption,
+    pub result: &'a RunResult,
+    pub sessions: &'a SessionStats,
+    /// Operations retried under `--retry`, by error, even if none were
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: &'a Option<ErrorCounts>,
+    /// What each member answered the `--preflight` check with
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub preflight: &'a [MemberHealth],
+    /// Server counters around each phase with `--server-stats`, left out of `--csv-out` rows
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub server: &'a [PhaseStats],
+    /// Whether a signal or `--max-runtime` ended the run early, leaving its results partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// Whether it was `--max-runtime`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+}
+
+/// Where the text results go: the log, or the `--output` file.
+pub struct TextOutput {
+    file: Option<(BufWriter<File>, PathBuf)>,
+    // The first failed write, reported once all lines were attempted
+    error: Option<io::Error>,
+}
+
+impl TextOutput {
+    pub fn new(path: Option<&Path>) -> io::Result<Self> {
+        let file = match path {
+            Some(p) => Some((BufWriter::new(File::create(p)?), p.to_path_buf())),
+            None => None,
+        };
+        Ok(TextOutput { file, error: None })
+    }
+
+    pub fn line(&mut self, level: log::Level, args: fmt::Arguments) {
+        match &mut self.file {
+            Some((f, _)) => {
+                if let Err(e) = writeln!(f, "{}", args) {
+                    self.error.get_or_insert(e);
+                }
+            }
+            None => log::log!(level, "{}", args),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if let Some((mut f, path)) = self.file {
+            f.flush()?;
+            log::info!("Results written to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Distributions and per-second, per-worker or per-member detail, left out of CSV rows so they
+/// stay comparable across runs
+const CSV_SKIPPED: [&str; 4] = ["histogram", "servers", "timeline", "workers"];
+
+/// Objects whose keys differ from run to run, like error counts by error or tags, kept in one
+/// `key=value;...` column each so rows stay appendable to each other
+const CSV_JOINED: [&str; 3] = ["errors", "retries", "tags"];
+
+/// Flattens nested objects into dotted column names, e.g. `result.write.throughput`.
+pub fn flatten(prefix: &str, value: &Value, columns: &mut Vec<(String, String)>) {
+    let key = |k: &str| {
+        if prefix.is_empty() {
+            k.to_string()
+        } else {
+            format!("{}.{}", prefix, k)
+        }
+    };
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .filter(|(k, _)| !CSV_SKIPPED.contains(&k.as_str()))
+            .for_each(|(k, v)| flatten(&key(k), v, columns)),
+        Value::Array(items) if items.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            let joined: Vec<String> = items.iter().map(scalar).collect();
+            columns.push((prefix.to_string(), joined.join(";")));
+        }
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| flatten(&key(&i.to_string()), v, columns)),
+        v => columns.push((prefix.to_string(), scalar(v))),
+    }
+}
+
+/// Replaces the objects of `CSV_JOINED` anywhere in `value` with their `key=value;...`.
+fn join_keyed(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                match v {
+                    Value::Object(keyed) if CSV_JOINED.contains(&k.as_str()) => {
+                        let joined: Vec<String> = keyed
+                            .iter()
+                            .map(|(key, v)| format!("{}={}", key, scalar(v)))
+                            .collect();
+                        *v = Value::String(joined.join(";"));
+                    }
+                    v => join_keyed(v),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(join_keyed),
+        _ => {}
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_row<'a>(fields: impl Iterator<Item = &'a String>) -> String {
+    fields.map(|f| csv_field(f)).collect::<Vec<_>>().join(",")
+}
+
+/// Appends the report as one row to `path`, writing the header first if the file is new. A file
+/// holding rows with other columns, e.g. from another mode, is refused rather than mixed.
+pub fn append_csv(path: &Path, report: &Report) -> Result<(), anyhow::Error> {
+    let mut value = serde_json::to_value(report)?;
+    join_keyed(&mut value);
+    // Its columns depend on which members answered, and whether with `mntr` or `stat`
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("server");
+    }
+    let mut columns = Vec::new();
+    flatten("", &value, &mut columns);
+    let header = csv_row(columns.iter().map(|(k, _)| k));
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let mut existing = String::new();
+    BufReader::new(&file).read_line(&mut existing)?;
+    if existing.is_empty() {
+        writeln!(file, "{}", header)?;
+    } else if existing.trim_end() != header {
+        return Err(BenchError::InvalidOption(format!(
+            "{} has different columns than this run",
+            path.display()
+        ))
+        .into());
+    }
+    file.seek(SeekFrom::End(0))?;
+    writeln!(file, "{}", csv_row(columns.iter().map(|(_, v)| v)))?;
+    log::info!("Results appended to {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::error::BenchError;
+use crate::rng;
+use rand::{Rng, RngCore};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Words `text` payloads are made of, so they compress about as well as prose
+const WORDS: [&str; 16] = [
+    "zookeeper",
+    "the",
+    "node",
+    "of",
+    "session",
+    "and",
+    "watch",
+    "to",
+    "leader",
+    "a",
+    "quorum",
+    "in",
+    "follower",
+    "is",
+    "ensemble",
+    "data",
+];
+
+/// Bytes of the values written, from `--payload-pattern`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayloadPattern {
+    /// Random bytes, which don't compress at all
+    Random,
+    Zero,
+    /// Space separated words
+    Text,
+    /// One block of random bytes of this size over and over, for deduplication
+    Repeat(usize),
+}
+
+impl FromStr for PayloadPattern {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "random" => Ok(PayloadPattern::Random),
+            None if s == "zero" => Ok(PayloadPattern::Zero),
+            None if s == "text" => Ok(PayloadPattern::Text),
+            Some(("repeat", n)) => match n.parse() {
+                Ok(n) if n > 0 => Ok(PayloadPattern::Repeat(n)),
+                _ => Err(BenchError::InvalidOption(format!(
+                    "bad repeat length `{}`",
+                    n
+                ))),
+            },
+            _ => Err(BenchError::InvalidOption(format!(
+                "bad payload pattern `{}`",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for PayloadPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadPattern::Random => write!(f, "random"),
+            PayloadPattern::Zero => write!(f, "zero"),
+            PayloadPattern::Text => write!(f, "text"),
+            PayloadPattern::Repeat(n) => write!(f, "repeat:{}", n),
+        }
+    }
+}
+
+impl Serialize for PayloadPattern {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl PayloadPattern {
+    pub fn generate(&self, size: usize) -> Vec<u8> {
+        let mut rng = rng::rng();
+        match *self {
+            PayloadPattern::Random => {
+                let mut buf = vec![0; size];
+                rng.fill_bytes(&mut buf);
+                buf
+            }
+            PayloadPattern::Zero => vec![0; size],
+            PayloadPattern::Text => {
+                let mut buf = Vec::with_capacity(size + 16);
+                while buf.len() < size {
+                    buf.extend_from_slice(WORDS[rng.gen_range(0..WORDS.len())].as_bytes());
+                    buf.push(b' ');
+                }
+                buf.truncate(size);
+                buf
+            }
+            PayloadPattern::Repeat(n) => {
+                let mut block = vec![0; n];
+                rng.fill_bytes(&mut block);
+                block.into_iter().cycle().take(size).collect()
+            }
+        }
+    }
+}
+
+/// Contents of `--payload-file`, or of every file in that directory by name, which values take
+/// in turn.
+#[derive(Debug)]
+pub struct PayloadFiles {
+    payloads: Vec<Vec<u8>>,
+    next: AtomicUsize,
+}
+
+impl PayloadFiles {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let payloads = if path.is_dir() {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    files.push(entry.path());
+                }
+            }
+            files.sort();
+            files.iter().map(fs::read).collect::<io::Result<_>>()?
+        } else {
+            vec![fs::read(path)?]
+        };
+        if payloads.is_empty() {
+            let msg = format!("no payload files in {}", path.display());
+            return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+        }
+        Ok(PayloadFiles {
+            payloads,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Size of the files on average, as values take them in turn.
+    pub fn mean_len(&self) -> f64 {
+        let total: usize = self.payloads.iter().map(Vec::len).sum();
+        total as f64 / self.payloads.len() as f64
+    }
+
+    pub fn next(&self) -> Vec<u8> {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.payloads.len();
+        self.payloads[i].clone()
+    }
+}
+
+/// What `--verify` values begin with, ahead of their checksum
+const SEAL_MAGIC: &[u8; 4] = b"zbv1";
+
+/// Bytes `seal` takes at the start of a value
+const SEAL_LEN: usize = SEAL_MAGIC.len() + 8;
+
+/// Begins `value` with a checksum of it and of the `path` it's written to, for `verify` to tell
+/// whether what's read back there is what was written. The value keeps its size, unless it's
+/// shorter than the checksum.
+pub fn seal(path: &str, mut value: Vec<u8>) -> Vec<u8> {
+    if value.len() < SEAL_LEN {
+        value.resize(SEAL_LEN, 0);
+    }
+    let sum = checksum(path, &value[SEAL_LEN..]);
+    value[..SEAL_MAGIC.len()].copy_from_slice(SEAL_MAGIC);
+    value[SEAL_MAGIC.len()..SEAL_LEN].copy_from_slice(&sum.to_be_bytes());
+    value
+}
+
+/// Whether `data` read from `path` is a value `seal` made for it, unchanged since.
+pub fn verify(path: &str, data: &[u8]) -> bool {
+    data.len() >= SEAL_LEN
+        && &data[..SEAL_MAGIC.len()] == SEAL_MAGIC
+        && data[SEAL_MAGIC.len()..SEAL_LEN] == checksum(path, &data[SEAL_LEN..]).to_be_bytes()
+}
+
+/// FNV-1a of the path and the rest of the value, which is enough to catch values lost, cut
+/// short, mixed up or corrupted on the way.
+fn checksum(path: &str, rest: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in path.as_bytes().iter().chain([0].iter()).chain(rest) {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
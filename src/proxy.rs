@@ -0,0 +1,82 @@
+use crate::server;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Handshakes the read-only proxies relayed, and how many of them servers answered in
+/// read-only mode.
+#[derive(Debug, Default)]
+pub struct Handshakes {
+    pub total: AtomicU64,
+    pub read_only: AtomicU64,
+}
+
+/// Opens a local port for every server of the connect string `hosts`, relaying whatever
+/// connects to it with the handshake marked as able to take a read-only session, and returns the
+/// connect string of the local ports. The client always asks for a read-write session, which
+/// servers that lost quorum refuse.
+pub fn read_only(hosts: &str, handshakes: Arc<Handshakes>) -> io::Result<String> {
+    let mut local = Vec::new();
+    for server in server::servers(hosts) {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        local.push(listener.local_addr()?.to_string());
+        let handshakes = handshakes.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (server, handshakes) = (server.clone(), handshakes.clone());
+                thread::spawn(move || {
+                    if let Err(e) = relay(stream, &server, &handshakes) {
+                        log::warn!("Read-only proxy to {} closed, {}", server, e);
+                    }
+                });
+            }
+        });
+    }
+    Ok(local.join(",") + server::chroot(hosts).unwrap_or_default())
+}
+
+/// Reads a length prefixed packet.
+fn packet(mut stream: &TcpStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let mut packet = vec![0; 4 + u32::from_be_bytes(len) as usize];
+    packet[..4].copy_from_slice(&len);
+    stream.read_exact(&mut packet[4..])?;
+    Ok(packet)
+}
+
+fn relay(local: TcpStream, server: &str, handshakes: &Handshakes) -> io::Result<()> {
+    let remote = TcpStream::connect(server)?;
+    local.set_nodelay(true)?;
+    remote.set_nodelay(true)?;
+
+    // The read-only flag is the last byte of both the connect request and its response
+    let mut request = packet(&local)?;
+    if let Some(flag) = request.last_mut() {
+        *flag = 1;
+    }
+    (&remote).write_all(&request)?;
+    let response = packet(&remote)?;
+    handshakes.total.fetch_add(1, Ordering::Relaxed);
+    if response.last() == Some(&1) {
+        handshakes.read_only.fetch_add(1, Ordering::Relaxed);
+    }
+    (&local).write_all(&response)?;
+
+    let receiving = {
+        let (remote, local) = (remote.try_clone()?, local.try_clone()?);
+        thread::spawn(move || {
+            let result = io::copy(&mut &remote, &mut &local);
+            let _ = local.shutdown(Shutdown::Both);
+            let _ = remote.shutdown(Shutdown::Both);
+            result
+        })
+    };
+    let result = io::copy(&mut &local, &mut &remote);
+    let _ = local.shutdown(Shutdown::Both);
+    let _ = remote.shutdown(Shutdown::Both);
+    let received = receiving.join().unwrap();
+    result.and(received).map(drop)
+}
@@ -0,0 +1,65 @@
+use super::{node_name, wait_for_turn};
+use std::time::Duration;
+use zookeeper::{Acl, CreateMode, ZkResult, ZooKeeper};
+
+const NODE_PREFIX: &str = "candidate-";
+
+/// Leader election using ephemeral sequential candidates: the lowest candidate leads and every
+/// other candidate watches its predecessor, so a resignation wakes exactly one successor.
+pub struct Election<'a> {
+    zk: &'a ZooKeeper,
+    path: String,
+    recheck: Duration,
+    acl: Vec<Acl>,
+}
+
+pub struct Candidate<'a> {
+    election: &'a Election<'a>,
+    node: String,
+}
+
+impl<'a> Election<'a> {
+    pub fn new(zk: &'a ZooKeeper, path: &str, recheck: Duration) -> Self {
+        Election {
+            zk,
+            path: path.to_string(),
+            recheck,
+            acl: Acl::open_unsafe().clone(),
+        }
+    }
+
+    /// Creates its znodes with `acl` rather than one open to anyone.
+    pub fn with_acl(self, acl: Vec<Acl>) -> Self {
+        Election { acl, ..self }
+    }
+
+    pub fn join(&'a self) -> ZkResult<Candidate<'a>> {
+        let node = self.zk.create(
+            format!("{}/{}", self.path, NODE_PREFIX).as_str(),
+            Vec::new(),
+            self.acl.clone(),
+            CreateMode::EphemeralSequential,
+        )?;
+        Ok(Candidate {
+            election: self,
+            node,
+        })
+    }
+}
+
+impl Candidate<'_> {
+    pub fn wait_for_leadership(&self) -> ZkResult<()> {
+        let e = self.election;
+        wait_for_turn(
+            e.zk,
+            e.path.as_str(),
+            NODE_PREFIX,
+            node_name(self.node.as_str()),
+            e.recheck,
+        )
+    }
+
+    pub fn resign(self) -> ZkResult<()> {
+        self.election.zk.delete(self.node.as_str(), None)
+    }
+}
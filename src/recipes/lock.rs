@@ -0,0 +1,59 @@
+use super::{node_name, wait_for_turn};
+use std::time::Duration;
+use zookeeper::{Acl, CreateMode, ZkResult, ZooKeeper};
+
+const NODE_PREFIX: &str = "lock-";
+
+/// Exclusive lock using the standard ZooKeeper recipe: every contender creates an ephemeral
+/// sequential child under the lock node and waits for its immediate predecessor to go away.
+pub struct Lock<'a> {
+    zk: &'a ZooKeeper,
+    path: String,
+    // Upper bound for a single wait on the predecessor before the children are re-checked
+    recheck: Duration,
+    acl: Vec<Acl>,
+}
+
+pub struct LockGuard<'a> {
+    zk: &'a ZooKeeper,
+    node: String,
+}
+
+impl<'a> Lock<'a> {
+    pub fn new(zk: &'a ZooKeeper, path: &str, recheck: Duration) -> Self {
+        Lock {
+            zk,
+            path: path.to_string(),
+            recheck,
+            acl: Acl::open_unsafe().clone(),
+        }
+    }
+
+    /// Creates its znodes with `acl` rather than one open to anyone.
+    pub fn with_acl(self, acl: Vec<Acl>) -> Self {
+        Lock { acl, ..self }
+    }
+
+    pub fn acquire(&self) -> ZkResult<LockGuard<'a>> {
+        let node = self.zk.create(
+            format!("{}/{}", self.path, NODE_PREFIX).as_str(),
+            Vec::new(),
+            self.acl.clone(),
+            CreateMode::EphemeralSequential,
+        )?;
+        wait_for_turn(
+            self.zk,
+            self.path.as_str(),
+            NODE_PREFIX,
+            node_name(node.as_str()),
+            self.recheck,
+        )?;
+        Ok(LockGuard { zk: self.zk, node })
+    }
+}
+
+impl LockGuard<'_> {
+    pub fn release(self) -> ZkResult<()> {
+        self.zk.delete(self.node.as_str(), None)
+    }
+}
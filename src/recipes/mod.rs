@@ -0,0 +1,42 @@
+pub mod election;
+pub mod lock;
+pub mod queue;
+
+use std::sync::mpsc;
+use std::time::Duration;
+use zookeeper::{ZkError, ZkResult, ZooKeeper};
+
+/// Blocks until `name` is the lowest sequential child of `parent` among those starting with
+/// `prefix`, watching only the immediate predecessor to avoid a herd effect.
+fn wait_for_turn(
+    zk: &ZooKeeper,
+    parent: &str,
+    prefix: &str,
+    name: &str,
+    recheck: Duration,
+) -> ZkResult<()> {
+    loop {
+        let mut children = zk.get_children(parent, false)?;
+        children.retain(|c| c.starts_with(prefix));
+        children.sort_unstable();
+
+        // Our own node can only disappear together with the session
+        let predecessor = match children.iter().position(|c| c == name) {
+            Some(0) => return Ok(()),
+            Some(i) => format!("{}/{}", parent, children[i - 1]),
+            None => return Err(ZkError::NoNode),
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let exists = zk.exists_w(predecessor.as_str(), move |_| {
+            let _ = tx.send(());
+        })?;
+        if exists.is_some() {
+            let _ = rx.recv_timeout(recheck);
+        }
+    }
+}
+
+fn node_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or_default()
+}
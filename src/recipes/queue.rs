@@ -0,0 +1,67 @@
+use std::sync::mpsc;
+use std::time::Duration;
+use zookeeper::{Acl, CreateMode, ZkError, ZkResult, ZooKeeper};
+
+const NODE_PREFIX: &str = "item-";
+
+/// FIFO queue using persistent sequential children; consumers claim an item by deleting it.
+pub struct Queue<'a> {
+    zk: &'a ZooKeeper,
+    path: String,
+    acl: Vec<Acl>,
+}
+
+impl<'a> Queue<'a> {
+    pub fn new(zk: &'a ZooKeeper, path: &str) -> Self {
+        Queue {
+            zk,
+            path: path.to_string(),
+            acl: Acl::open_unsafe().clone(),
+        }
+    }
+
+    /// Creates its znodes with `acl` rather than one open to anyone.
+    pub fn with_acl(self, acl: Vec<Acl>) -> Self {
+        Queue { acl, ..self }
+    }
+
+    pub fn offer(&self, data: Vec<u8>) -> ZkResult<String> {
+        self.zk.create(
+            format!("{}/{}", self.path, NODE_PREFIX).as_str(),
+            data,
+            self.acl.clone(),
+            CreateMode::PersistentSequential,
+        )
+    }
+
+    /// Removes the head of the queue, waiting up to `timeout` for an item to show up.
+    pub fn take(&self, timeout: Duration) -> ZkResult<Option<Vec<u8>>> {
+        loop {
+            let (tx, rx) = mpsc::channel();
+            let mut children = self.zk.get_children_w(self.path.as_str(), move |_| {
+                let _ = tx.send(());
+            })?;
+            children.retain(|c| c.starts_with(NODE_PREFIX));
+            children.sort_unstable();
+
+            // Items already claimed by another consumer fail with NoNode, try the next one
+            for child in children {
+                let path = format!("{}/{}", self.path, child);
+                let data = match self.zk.get_data(path.as_str(), false) {
+                    Ok((data, _)) => data,
+                    Err(ZkError::NoNode) => continue,
+                    Err(e) => return Err(e),
+                };
+                match self.zk.delete(path.as_str(), None) {
+                    Ok(_) => return Ok(Some(data)),
+                    Err(ZkError::NoNode) => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if rx.recv_timeout(timeout).is_err() {
+                return Ok(None);
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::chart;
+use crate::error::BenchError;
+use crate::output::{flatten, Report};
+use crate::stats::Bucket;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SVG_WIDTH: usize = 640;
+const SVG_HEIGHT: usize = 160;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Html,
+    Markdown,
+}
+
+fn kind(path: &Path) -> Option<Kind> {
+    match path.extension()?.to_str()? {
+        "html" | "htm" => Some(Kind::Html),
+        "md" | "markdown" => Some(Kind::Markdown),
+        _ => None,
+    }
+}
+
+/// Accepts `--report` paths whose extension tells the format.
+pub fn parse_path(arg: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(arg);
+    match kind(&path) {
+        Some(_) => Ok(path),
+        None => Err(format!("`{}` doesn't end in .html or .md", arg)),
+    }
+}
+
+/// Per-second throughput or a latency distribution found in the results.
+enum Chart {
+    Timeline(String, Vec<u64>),
+    Histogram(String, Vec<Bucket>),
+}
+
+/// Finds every `timeline` and `histogram` of the results, titled after where they are.
+fn charts(prefix: &str, value: &Value, out: &mut Vec<Chart>) {
+    let title = |k: &str| match prefix {
+        "" => k.to_string(),
+        p => format!("{} {}", p, k),
+    };
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                match (k.as_str(), v) {
+                    ("timeline", Value::Array(points)) => out.push(Chart::Timeline(
+                        title("throughput (ops/s)"),
+                        points.iter().filter_map(Value::as_u64).collect(),
+                    )),
+                    ("histogram", Value::Array(buckets)) => out.push(Chart::Histogram(
+                        title("latency distribution"),
+                        buckets
+                            .iter()
+                            .map(|b| Bucket {
+                                le: Duration::from_secs_f64(b["le"].as_f64().unwrap_or(0.0)),
+                                count: b["count"].as_u64().unwrap_or(0),
+                            })
+                            .collect(),
+                    )),
+                    // Per-worker detail would only repeat the phase charts many times over
+                    ("workers", _) => {}
+                    (k, v) => charts(&title(k), v, out),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, v) in items.iter().enumerate() {
+                // Mixed mode lists results per operation, named by their `op`
+                let name = v["op"].as_str().map_or(i.to_string(), str::to_string);
+                charts(&title(&name), v, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_table(out: &mut String, columns: &[(String, String)]) {
+    out.push_str("<table>\n");
+    for (k, v) in columns {
+        let _ = writeln!(
+            out,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            html_escape(k),
+            html_escape(v)
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn svg_timeline(out: &mut String, timeline: &[u64]) {
+    let max = timeline.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let step = SVG_WIDTH as f64 / timeline.len().saturating_sub(1).max(1) as f64;
+    let points: Vec<String> = timeline
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let y = SVG_HEIGHT as f64 * (1.0 - *n as f64 / max);
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect();
+    let _ = writeln!(
+        out,
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><polyline fill=\"none\" stroke=\"steelblue\" stroke-width=\"2\" points=\"{}\"/></svg>",
+        points.join(" "),
+        w = SVG_WIDTH,
+        h = SVG_HEIGHT
+    );
+    let _ = writeln!(
+        out,
+        "<p>{} seconds, peak {} ops/s</p>",
+        timeline.len(),
+        max as u64
+    );
+}
+
+fn svg_histogram(out: &mut String, buckets: &[Bucket]) {
+    let max = buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1) as f64;
+    let row = 18;
+    let label = 100;
+    let _ = writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\">",
+        SVG_WIDTH,
+        row * buckets.len()
+    );
+    for (i, b) in buckets.iter().enumerate() {
+        let width = (SVG_WIDTH - label - 80) as f64 * b.count as f64 / max;
+        let y = i * row;
+        let _ = writeln!(
+            out,
+            "<text x=\"0\" y=\"{}\" font-size=\"12\">&lt;= {:?}</text><rect x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"steelblue\"/><text x=\"{:.1}\" y=\"{}\" font-size=\"12\">{}</text>",
+            y + 13,
+            b.le,
+            label,
+            y + 2,
+            width,
+            row - 4,
+            label as f64 + width + 4.0,
+            y + 13,
+            b.count
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+fn render_html(
+    meta: &[(String, String)],
+    options: &[(String, String)],
+    results: &[(String, String)],
+    charts: &[Chart],
+) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>zoobench report</title>\n");
+    out.push_str("<style>body{font-family:sans-serif}table{border-collapse:collapse}th,td{border:1px solid #ccc;padding:2px 8px;text-align:left}</style>\n");
+    out.push_str("</head>\n<body>\n<h1>zoobench report</h1>\n");
+    html_table(&mut out, meta);
+    out.push_str("<h2>Configuration</h2>\n");
+    html_table(&mut out, options);
+    out.push_str("<h2>Results</h2>\n");
+    html_table(&mut out, results);
+    for c in charts {
+        match c {
+            Chart::Timeline(title, timeline) if !timeline.is_empty() => {
+                let _ = writeln!(out, "<h3>{}</h3>", html_escape(title));
+                svg_timeline(&mut out, timeline);
+            }
+            Chart::Histogram(title, buckets) if !buckets.is_empty() => {
+                let _ = writeln!(out, "<h3>{}</h3>", html_escape(title));
+                svg_histogram(&mut out, buckets);
+            }
+            _ => {}
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn md_table(out: &mut String, header: &str, columns: &[(String, String)]) {
+    let _ = writeln!(out, "| {} | Value |\n| --- | --- |", header);
+    for (k, v) in columns {
+        let _ = writeln!(out, "| {} | {} |", k, v.replace('|', "\\|"));
+    }
+    out.push('\n');
+}
+
+fn render_markdown(
+    meta: &[(String, String)],
+    options: &[(String, String)],
+    results: &[(String, String)],
+    charts: &[Chart],
+) -> String {
+    let mut out = String::from("# zoobench report\n\n");
+    md_table(&mut out, "Run", meta);
+    out.push_str("## Configuration\n\n");
+    md_table(&mut out, "Option", options);
+    out.push_str("## Results\n\n");
+    md_table(&mut out, "Metric", results);
+    for c in charts {
+        match c {
+            Chart::Timeline(title, timeline) if !timeline.is_empty() => {
+                let max = timeline.iter().max().unwrap_or(&0);
+                let _ = writeln!(
+                    out,
+                    "### {}\n\n```text\n{}\n```\n\n{} seconds, peak {} ops/s\n",
+                    title,
+                    chart::sparkline(timeline),
+                    timeline.len(),
+                    max
+                );
+            }
+            Chart::Histogram(title, buckets) if !buckets.is_empty() => {
+                let _ = writeln!(
+                    out,
+                    "### {}\n\n```text\n{}\n```\n",
+                    title,
+                    chart::histogram(buckets).join("\n")
+                );
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Writes the run's metadata, options, results and charts as HTML or Markdown, as told by the
+/// extension of `path`.
+pub fn write(path: &Path, report: &Report) -> Result<(), anyhow::Error> {
+    let kind = kind(path).ok_or_else(|| {
+        BenchError::InvalidOption(format!("unknown report format of {}", path.display()))
+    })?;
+    let mut sections = [Vec::new(), Vec::new(), Vec::new()];
+    flatten("", &serde_json::to_value(report.meta)?, &mut sections[0]);
+    flatten("", &serde_json::to_value(report.options)?, &mut sections[1]);
+    let result = serde_json::to_value(report.result)?;
+    flatten("", &result, &mut sections[2]);
+    let sessions = serde_json::to_value(report.sessions)?;
+    flatten("sessions", &sessions, &mut sections[2]);
+    if !report.server.is_empty() {
+        flatten(
+            "server",
+            &serde_json::to_value(report.server)?,
+            &mut sections[2],
+        );
+    }
+    let mut found = Vec::new();
+    charts("", &result, &mut found);
+    charts("session establishment", &sessions, &mut found);
+
+    let [meta, options, results] = &sections;
+    let content = match kind {
+        Kind::Html => render_html(meta, options, results, &found),
+        Kind::Markdown => render_markdown(meta, options, results, &found),
+    };
+    fs::write(path, content)?;
+    log::info!("Report written to {}", path.display());
+    Ok(())
+}
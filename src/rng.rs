@@ -0,0 +1,43 @@
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+thread_local! {
+    static RNG: Rc<RefCell<StdRng>> = Rc::new(RefCell::new(StdRng::from_entropy()));
+}
+
+/// Random numbers of the calling thread, like `rand::thread_rng` but reseedable for `--seed`.
+#[derive(Clone, Debug)]
+pub struct BenchRng(Rc<RefCell<StdRng>>);
+
+pub fn rng() -> BenchRng {
+    BenchRng(RNG.with(Rc::clone))
+}
+
+/// Restarts the calling thread's random numbers from `seed`, if given, in a stream of its own
+/// for every worker, so seeded runs draw the same numbers in each.
+pub fn reseed(seed: Option<u64>, stream: u64) {
+    if let Some(seed) = seed {
+        let seeded = StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        RNG.with(|r| *r.borrow_mut() = seeded);
+    }
+}
+
+impl RngCore for BenchRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.borrow_mut().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.borrow_mut().try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,88 @@
+//! The SASL mechanism sessions authenticate with, from `--sasl`.
+
+use crate::error::BenchError;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use zookeeper_client::SaslOptions;
+
+/// A mechanism as `--sasl` takes it: `digest-md5:user:password`, or `gssapi` optionally followed
+/// by the service principal's name, like `gssapi:zookeeper`, which needs a build with the
+/// `kerberos` feature.
+#[derive(Clone, Debug)]
+pub enum Sasl {
+    DigestMd5 { user: String, password: String },
+    Gssapi { service: Option<String> },
+}
+
+impl Sasl {
+    /// The mechanism as the async client takes it.
+    pub fn options(&self) -> SaslOptions {
+        match self {
+            Sasl::DigestMd5 { user, password } => {
+                SaslOptions::digest_md5(user.clone(), password.clone()).into()
+            }
+            #[cfg(feature = "kerberos")]
+            Sasl::Gssapi { service } => {
+                let options = SaslOptions::gssapi();
+                match service {
+                    Some(service) => options.with_username(service.clone()).into(),
+                    None => options.into(),
+                }
+            }
+            #[cfg(not(feature = "kerberos"))]
+            Sasl::Gssapi { .. } => unreachable!("gssapi is rejected when parsed"),
+        }
+    }
+}
+
+impl FromStr for Sasl {
+    type Err = BenchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BenchError::InvalidOption(format!("bad SASL mechanism `{}`", s));
+        let (mechanism, rest) = match s.split_once(':') {
+            Some((mechanism, rest)) => (mechanism, Some(rest)),
+            None => (s, None),
+        };
+        match (mechanism, rest) {
+            ("digest-md5", Some(credentials)) => {
+                let (user, password) = credentials.split_once(':').ok_or_else(invalid)?;
+                if user.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(Sasl::DigestMd5 {
+                    user: user.to_string(),
+                    password: password.to_string(),
+                })
+            }
+            ("gssapi", _) if cfg!(not(feature = "kerberos")) => Err(BenchError::InvalidOption(
+                "--sasl gssapi needs zoobench built with the kerberos feature".to_string(),
+            )),
+            ("gssapi", None) => Ok(Sasl::Gssapi { service: None }),
+            ("gssapi", Some(service)) if !service.is_empty() => Ok(Sasl::Gssapi {
+                service: Some(service.to_string()),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The mechanism without the password, as the options print it.
+impl fmt::Display for Sasl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sasl::DigestMd5 { user, .. } => write!(f, "digest-md5:{}", user),
+            Sasl::Gssapi { service: None } => f.write_str("gssapi"),
+            Sasl::Gssapi {
+                service: Some(service),
+            } => write!(f, "gssapi:{}", service),
+        }
+    }
+}
+
+impl Serialize for Sasl {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
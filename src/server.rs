@@ -0,0 +1,374 @@
+use crate::error::BenchError;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 2181;
+
+/// Servers of a connect string like `zk1:2181,zk2:2181/chroot`, as `host:port`.
+pub fn servers(hosts: &str) -> Vec<String> {
+    let hosts = hosts.split('/').next().unwrap_or_default();
+    hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .map(|h| match h.contains(':') {
+            true => h.to_string(),
+            false => format!("{}:{}", h, DEFAULT_PORT),
+        })
+        .collect()
+}
+
+/// Chroot suffix of a connect string like `zk1:2181,zk2:2181/app/ns`, if it has one.
+pub fn chroot(hosts: &str) -> Option<&str> {
+    hosts.find('/').map(|i| &hosts[i..]).filter(|c| *c != "/")
+}
+
+/// Sends a four letter word like `srvr` to `server` and returns its whole answer. Servers only
+/// answer the commands in their `4lw.commands.whitelist`.
+pub fn four_letter_word(server: &str, cmd: &str, timeout: Duration) -> io::Result<String> {
+    let addr = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, server.to_string()))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.write_all(cmd.as_bytes())?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    Ok(answer)
+}
+
+/// Whether a connection to `server` goes through within `timeout`.
+pub fn reachable(server: &str, timeout: Duration) -> bool {
+    let addr = server.to_socket_addrs().ok().and_then(|mut a| a.next());
+    addr.is_some_and(|addr| TcpStream::connect_timeout(&addr, timeout).is_ok())
+}
+
+/// Value of the `name: value` line of a `srvr` answer.
+fn srvr_field(srvr: &str, name: &str) -> Option<String> {
+    srvr.lines()
+        .find_map(|l| l.strip_prefix(name)?.strip_prefix(": "))
+        .map(|v| v.trim().to_string())
+}
+
+/// Value of the `name=value` line of a `conf` answer.
+fn conf_field(conf: &str, name: &str) -> Option<String> {
+    conf.lines()
+        .find_map(|l| l.strip_prefix(name)?.strip_prefix('='))
+        .map(|v| v.trim().to_string())
+}
+
+/// A member of the ensemble as it described itself in `srvr` and `conf`, where it answered them.
+#[derive(Serialize, Debug)]
+pub struct Member {
+    pub server: String,
+    pub version: Option<String>,
+    /// leader, follower, observer or standalone
+    pub mode: Option<String>,
+    pub node_count: Option<u64>,
+    /// Its `myid`
+    pub server_id: Option<u64>,
+    /// Milliseconds
+    pub tick_time: Option<u64>,
+    /// Bounds of the session timeouts it grants, in milliseconds
+    pub min_session_timeout: Option<u64>,
+    pub max_session_timeout: Option<u64>,
+    pub max_client_cnxns: Option<u64>,
+    /// The `server.N=...` lines of its quorum configuration, as it knows them
+    pub peers: Vec<String>,
+}
+
+/// Each member of `hosts` as it describes itself, or with what it didn't answer left out.
+pub fn ensemble(hosts: &str, timeout: Duration) -> Vec<Member> {
+    servers(hosts)
+        .into_iter()
+        .map(|server| {
+            let srvr = four_letter_word(&server, "srvr", timeout)
+                .map_err(|e| log::warn!("Failed to query srvr of {}, {}", server, e))
+                .unwrap_or_default();
+            if !srvr.is_empty() && srvr_field(&srvr, "Zookeeper version").is_none() {
+                log::warn!("No version in srvr of {}, is it whitelisted?", server);
+            }
+            // A server that didn't answer srvr won't answer conf either
+            let conf = match srvr.is_empty() {
+                true => String::new(),
+                false => four_letter_word(&server, "conf", timeout).unwrap_or_default(),
+            };
+            let number = |field: Option<String>| field.and_then(|v| v.parse().ok());
+            Member {
+                version: srvr_field(&srvr, "Zookeeper version"),
+                mode: srvr_field(&srvr, "Mode"),
+                node_count: number(srvr_field(&srvr, "Node count")),
+                server_id: number(conf_field(&conf, "serverId")),
+                tick_time: number(conf_field(&conf, "tickTime")),
+                min_session_timeout: number(conf_field(&conf, "minSessionTimeout")),
+                max_session_timeout: number(conf_field(&conf, "maxSessionTimeout")),
+                max_client_cnxns: number(conf_field(&conf, "maxClientCnxns")),
+                peers: conf
+                    .lines()
+                    .filter(|l| l.starts_with("server."))
+                    .map(str::to_string)
+                    .collect(),
+                server,
+            }
+        })
+        .collect()
+}
+
+/// What `--preflight` does about members that fail the check.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preflight {
+    /// Log them and run anyway
+    Warn,
+    /// Refuse to run
+    Require,
+}
+
+/// How a member answered the pre-flight check.
+#[derive(Serialize, Debug)]
+pub struct MemberHealth {
+    pub server: String,
+    /// Whether it answered and serves requests, as far as the commands it answers tell
+    pub healthy: bool,
+    pub version: Option<String>,
+    /// leader, follower, observer or standalone, from `srvr`
+    pub mode: Option<String>,
+    /// Why it failed the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Asks `server` `ruok` and `srvr`. A server without quorum still answers `ruok` with `imok`,
+/// so it only settles the matter when `srvr` isn't whitelisted.
+fn check(server: &str, timeout: Duration) -> MemberHealth {
+    let mut health = MemberHealth {
+        server: server.to_string(),
+        healthy: false,
+        version: None,
+        mode: None,
+        error: None,
+    };
+    let ruok = match four_letter_word(server, "ruok", timeout) {
+        Ok(answer) => answer,
+        Err(e) => {
+            health.error = Some(e.to_string());
+            return health;
+        }
+    };
+    let srvr = four_letter_word(server, "srvr", timeout).unwrap_or_default();
+    health.version = srvr_field(&srvr, "Zookeeper version");
+    health.mode = srvr_field(&srvr, "Mode");
+    if health.mode.is_some() {
+        health.healthy = true;
+    } else if srvr.contains("not currently serving requests") {
+        health.error = Some("not serving requests".to_string());
+    } else {
+        // Answering at all is all there is to go by
+        if ruok.trim() != "imok" {
+            log::warn!(
+                "{} answers neither ruok nor srvr, are they whitelisted?",
+                server
+            );
+        }
+        health.healthy = true;
+    }
+    health
+}
+
+/// Checks every member of `hosts` before a run, logging what each is. Under
+/// `Preflight::Require` a member failing the check fails the run before it starts.
+pub fn preflight(
+    hosts: &str,
+    timeout: Duration,
+    policy: Preflight,
+) -> Result<Vec<MemberHealth>, BenchError> {
+    let members: Vec<MemberHealth> = servers(hosts)
+        .iter()
+        .map(|server| check(server, timeout))
+        .collect();
+    for m in &members {
+        match &m.error {
+            None => log::info!(
+                "{}: {}, {}",
+                m.server,
+                m.mode.as_deref().unwrap_or("unknown role"),
+                m.version.as_deref().unwrap_or("unknown version")
+            ),
+            Some(e) => log::warn!("{} failed the pre-flight check, {}", m.server, e),
+        }
+    }
+    let failed = members.iter().filter(|m| !m.healthy).count();
+    if failed > 0 && policy == Preflight::Require {
+        return Err(BenchError::Unhealthy(failed));
+    }
+    Ok(members)
+}
+
+/// `mntr` keys kept in snapshots, by the name they're reported under
+const MNTR_KEYS: [(&str, &str); 9] = [
+    ("zk_znode_count", "znode_count"),
+    ("zk_watch_count", "watch_count"),
+    ("zk_outstanding_requests", "outstanding_requests"),
+    ("zk_min_latency", "min_latency"),
+    ("zk_avg_latency", "avg_latency"),
+    ("zk_max_latency", "max_latency"),
+    ("zk_packets_received", "packets_received"),
+    ("zk_packets_sent", "packets_sent"),
+    ("zk_num_alive_connections", "connections"),
+];
+
+/// The same counters from `stat`, for servers that don't answer `mntr`
+const STAT_KEYS: [(&str, &str); 5] = [
+    ("Node count", "znode_count"),
+    ("Outstanding", "outstanding_requests"),
+    ("Received", "packets_received"),
+    ("Sent", "packets_sent"),
+    ("Connections", "connections"),
+];
+
+type Counters = BTreeMap<&'static str, f64>;
+
+fn parse_mntr(answer: &str, counters: &mut Counters) {
+    for line in answer.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Some((_, name)) = MNTR_KEYS.iter().find(|(k, _)| *k == key) {
+            if let Ok(v) = value.parse() {
+                counters.insert(name, v);
+            }
+        }
+    }
+}
+
+fn parse_stat(answer: &str, counters: &mut Counters) {
+    for line in answer.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if key == "Latency min/avg/max" {
+            for (name, v) in ["min_latency", "avg_latency", "max_latency"]
+                .into_iter()
+                .zip(value.split('/'))
+            {
+                if let Ok(v) = v.parse() {
+                    counters.insert(name, v);
+                }
+            }
+        } else if let Some((_, name)) = STAT_KEYS.iter().find(|(k, _)| *k == key) {
+            if let Ok(v) = value.parse() {
+                counters.insert(name, v);
+            }
+        }
+    }
+}
+
+/// Key counters of one server from `mntr`, or `stat` and `wchs` where `mntr` isn't allowed.
+fn snapshot(server: &str, timeout: Duration) -> io::Result<Counters> {
+    let mut counters = Counters::new();
+    parse_mntr(&four_letter_word(server, "mntr", timeout)?, &mut counters);
+    if counters.is_empty() {
+        parse_stat(&four_letter_word(server, "stat", timeout)?, &mut counters);
+    }
+    if !counters.contains_key("watch_count") {
+        let wchs = four_letter_word(server, "wchs", timeout)?;
+        if let Some(total) = wchs.lines().find_map(|l| l.strip_prefix("Total watches:")) {
+            if let Ok(v) = total.trim().parse() {
+                counters.insert("watch_count", v);
+            }
+        }
+    }
+    Ok(counters)
+}
+
+/// What one server's counters were before and after a phase.
+#[derive(Serialize, Debug)]
+pub struct MemberStats {
+    pub server: String,
+    pub before: Counters,
+    pub after: Counters,
+    /// After minus before, for counters both snapshots have
+    pub delta: Counters,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PhaseStats {
+    pub phase: String,
+    pub members: Vec<MemberStats>,
+}
+
+/// Snapshots every ensemble member around each phase for `--server-stats`.
+#[derive(Debug)]
+pub struct ServerStats {
+    servers: Vec<String>,
+    timeout: Duration,
+    phases: Mutex<Vec<PhaseStats>>,
+}
+
+impl ServerStats {
+    pub fn new(hosts: &str, timeout: Duration) -> Self {
+        ServerStats {
+            servers: servers(hosts),
+            timeout,
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Snapshots of every member that answered, leaving out those that didn't.
+    fn snapshot_all(&self) -> Vec<Option<Counters>> {
+        self.servers
+            .iter()
+            .map(|server| match snapshot(server, self.timeout) {
+                Ok(c) => Some(c),
+                Err(e) => {
+                    log::warn!("Failed to snapshot {}, {}", server, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Runs `phase`, recording how each member's counters changed meanwhile.
+    pub fn around<R>(
+        &self,
+        phase: &str,
+        f: impl FnOnce() -> Result<R, anyhow::Error>,
+    ) -> Result<R, anyhow::Error> {
+        let before = self.snapshot_all();
+        let result = f()?;
+        let after = self.snapshot_all();
+
+        let mut members = Vec::new();
+        for ((server, before), after) in self.servers.iter().zip(before).zip(after) {
+            let (Some(before), Some(after)) = (before, after) else {
+                continue;
+            };
+            let delta = after
+                .iter()
+                .filter_map(|(k, a)| before.get(k).map(|b| (*k, a - b)))
+                .collect();
+            members.push(MemberStats {
+                server: server.clone(),
+                before,
+                after,
+                delta,
+            });
+        }
+        self.phases.lock().unwrap().push(PhaseStats {
+            phase: phase.to_string(),
+            members,
+        });
+        Ok(result)
+    }
+
+    pub fn take(&self) -> Vec<PhaseStats> {
+        std::mem::take(&mut *self.phases.lock().unwrap())
+    }
+}
@@ -0,0 +1,326 @@
+use hdrhistogram::Histogram;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zookeeper::ZkError;
+
+/// Operation latencies in nanoseconds, at three significant digits.
+#[derive(Clone, Debug)]
+pub struct Latencies {
+    histogram: Histogram<u64>,
+}
+
+impl Default for Latencies {
+    fn default() -> Self {
+        Latencies {
+            histogram: Histogram::new(3).unwrap(),
+        }
+    }
+}
+
+impl Latencies {
+    // The histogram auto-resizes up to i64::MAX / 2, far beyond any latency we can observe
+    pub fn record(&mut self, latency: Duration) {
+        self.histogram.record(latency.as_nanos() as u64).unwrap();
+    }
+
+    pub fn merge(&mut self, other: &Latencies) {
+        self.histogram.add(&other.histogram).unwrap();
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let h = &self.histogram;
+        let at = |q: f64| Duration::from_nanos(h.value_at_quantile(q));
+        LatencySummary {
+            count: h.len() as usize,
+            min: Duration::from_nanos(h.min()),
+            mean: Duration::from_nanos(h.mean() as u64),
+            stddev: Duration::from_nanos(h.stdev() as u64),
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: Duration::from_nanos(h.max()),
+        }
+    }
+
+    /// Counts per latency bucket with 1-2-5 bounds from 1µs, trimmed to the buckets in use.
+    pub fn buckets(&self) -> Vec<Bucket> {
+        let mut bounds = Vec::new();
+        let mut decade = 1_000;
+        while bounds.len() < 3 * 8 {
+            bounds.extend([decade, 2 * decade, 5 * decade]);
+            decade *= 10;
+        }
+        let mut counts = vec![0; bounds.len() + 1];
+        for v in self.histogram.iter_recorded() {
+            let i = bounds.partition_point(|b| *b < v.value_iterated_to());
+            counts[i] += v.count_at_value();
+        }
+
+        let (Some(first), Some(last)) = (
+            counts.iter().position(|n| *n > 0),
+            counts.iter().rposition(|n| *n > 0),
+        ) else {
+            return Vec::new();
+        };
+        (first..=last)
+            .map(|i| Bucket {
+                // Whatever exceeds the largest bound lands in a last bucket up to the max
+                le: Duration::from_nanos(bounds.get(i).copied().unwrap_or(self.histogram.max())),
+                count: counts[i],
+            })
+            .collect()
+    }
+
+    /// Writes the percentile distribution in the `.hgrm` text format produced by
+    /// HdrHistogram's `outputPercentileDistribution`, with values in milliseconds.
+    pub fn write_hgrm(&self, path: &Path) -> io::Result<()> {
+        const SCALE: f64 = 1_000_000.0;
+        let h = &self.histogram;
+        let mut w = BufWriter::new(File::create(path)?);
+
+        writeln!(
+            w,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut total = 0;
+        for v in h.iter_quantiles(5) {
+            total += v.count_since_last_iteration();
+            let value = v.value_iterated_to() as f64 / SCALE;
+            let q = v.quantile_iterated_to();
+            if q < 1.0 {
+                writeln!(
+                    w,
+                    "{:12.3} {:2.12} {:10} {:14.2}",
+                    value,
+                    q,
+                    total,
+                    1.0 / (1.0 - q)
+                )?;
+            } else {
+                writeln!(w, "{:12.3} {:2.12} {:10}", value, q, total)?;
+            }
+        }
+        writeln!(
+            w,
+            "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]",
+            h.mean() / SCALE,
+            h.stdev() / SCALE
+        )?;
+        writeln!(
+            w,
+            "#[Max     = {:12.3}, Total count    = {:12}]",
+            h.max() as f64 / SCALE,
+            h.len()
+        )?;
+        w.flush()
+    }
+}
+
+/// Operations completed in each second since a worker started.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    start: Instant,
+    counts: Vec<u64>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline {
+            start: Instant::now(),
+            counts: Vec::new(),
+        }
+    }
+}
+
+impl Timeline {
+    pub fn record(&mut self) {
+        let second = self.start.elapsed().as_secs() as usize;
+        if self.counts.len() <= second {
+            self.counts.resize(second + 1, 0);
+        }
+        self.counts[second] += 1;
+    }
+
+    /// Second of the timeline `t` falls in.
+    pub fn second(&self, t: Instant) -> usize {
+        t.saturating_duration_since(self.start).as_secs() as usize
+    }
+
+    /// Operations completed in `second`.
+    pub fn count(&self, second: usize) -> u64 {
+        self.counts.get(second).copied().unwrap_or(0)
+    }
+
+    /// Sums worker timelines, aligned on the earliest start. Workers of a phase start within
+    /// milliseconds of each other, so aligning them to whole seconds is precise enough.
+    pub fn merge(timelines: &[Timeline]) -> Vec<u64> {
+        let Some(base) = timelines.iter().map(|t| t.start).min() else {
+            return Vec::new();
+        };
+        let mut merged = Vec::new();
+        for t in timelines {
+            let offset = t.start.duration_since(base).as_secs_f64().round() as usize;
+            if merged.len() < offset + t.counts.len() {
+                merged.resize(offset + t.counts.len(), 0);
+            }
+            for (i, n) in t.counts.iter().enumerate() {
+                merged[offset + i] += n;
+            }
+        }
+        merged
+    }
+}
+
+/// Failed operations by error.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct ErrorCounts(BTreeMap<String, u64>);
+
+impl ErrorCounts {
+    pub fn record(&mut self, e: &ZkError) {
+        *self.0.entry(format!("{:?}", e)).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &ErrorCounts) {
+        for (e, n) in &other.0 {
+            *self.0.entry(e.clone()).or_default() += n;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for ErrorCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        for (e, n) in &self.0 {
+            write!(f, "{}{}: {}", sep, e, n)?;
+            sep = ", ";
+        }
+        Ok(())
+    }
+}
+
+/// Operations that took at most `le`, but longer than the previous bucket's bound.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Bucket {
+    #[serde(serialize_with = "serialize_secs")]
+    pub le: Duration,
+    pub count: u64,
+}
+
+/// Serializes a duration as fractional seconds.
+pub fn serialize_secs<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+pub fn serialize_opt_secs<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => s.serialize_some(&d.as_secs_f64()),
+        None => s.serialize_none(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    #[serde(serialize_with = "serialize_secs")]
+    pub min: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub mean: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub stddev: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub p50: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub p90: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub p99: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub p999: Duration,
+    #[serde(serialize_with = "serialize_secs")]
+    pub max: Duration,
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "count: {}, min: {:?}, mean: {:?}, stddev: {:?}, p50: {:?}, p90: {:?}, p99: {:?}, p99.9: {:?}, max: {:?}",
+            self.count,
+            self.min,
+            self.mean,
+            self.stddev,
+            self.p50,
+            self.p90,
+            self.p99,
+            self.p999,
+            self.max
+        )
+    }
+}
+
+/// CSV log of every operation, shared by all workers of a run.
+#[derive(Debug)]
+pub struct LatencyLog {
+    // Wall clock at `epoch`, so per-op timestamps only need a monotonic reading
+    wall: SystemTime,
+    epoch: Instant,
+    out: Mutex<BufWriter<File>>,
+}
+
+impl LatencyLog {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "timestamp_ns,op,path,latency_ns,result")?;
+        Ok(LatencyLog {
+            wall: SystemTime::now(),
+            epoch: Instant::now(),
+            out: Mutex::new(out),
+        })
+    }
+
+    /// Appends one operation that started at `start`. `result` is the error it failed with, if any.
+    pub fn write(
+        &self,
+        op: &str,
+        path: &str,
+        start: Instant,
+        latency: Duration,
+        result: Option<&ZkError>,
+    ) -> io::Result<()> {
+        let timestamp = self.wall + start.duration_since(self.epoch);
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut out = self.out.lock().unwrap();
+        write!(
+            out,
+            "{},{},{},{},",
+            timestamp.as_nanos(),
+            op,
+            path,
+            latency.as_nanos()
+        )?;
+        match result {
+            None => writeln!(out, "OK"),
+            Some(e) => writeln!(out, "{:?}", e),
+        }
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.out.lock().unwrap().flush()
+    }
+}
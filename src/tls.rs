@@ -0,0 +1,179 @@
+use crate::server;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore};
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Client config trusting the `--tls-ca` certificates, authenticating with `--tls-cert` and
+/// `--tls-key` if given.
+pub fn config(
+    ca: &Path,
+    cert: Option<&Path>,
+    key: Option<&Path>,
+) -> Result<Arc<ClientConfig>, anyhow::Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(ca)? {
+        roots.add(cert?)?;
+    }
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+    let config = match (cert, key) {
+        (Some(cert), Some(key)) => {
+            let chain = CertificateDer::pem_file_iter(cert)?.collect::<Result<Vec<_>, _>>()?;
+            builder.with_client_auth_cert(chain, PrivateKeyDer::from_pem_file(key)?)?
+        }
+        _ => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(config))
+}
+
+/// Client certificates and keys of `--tls-cert-dir`: every `<name>.pem` with a `<name>.key`
+/// next to it, by name.
+pub fn identities(dir: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut identities = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let cert = entry?.path();
+        let key = cert.with_extension("key");
+        if cert.extension().is_some_and(|e| e == "pem") && key.is_file() {
+            identities.push((cert, key));
+        }
+    }
+    identities.sort();
+    if identities.is_empty() {
+        let msg = format!("no certificates with keys in {}", dir.display());
+        return Err(io::Error::new(ErrorKind::NotFound, msg));
+    }
+    Ok(identities)
+}
+
+/// Opens a local plain text port for every server of the connect string `hosts`, tunnelling
+/// whatever connects to it over TLS to the server's secure client port, and returns the connect
+/// string of the local ports. The client can't speak TLS itself, so it connects to these.
+pub fn tunnel(hosts: &str, config: Arc<ClientConfig>) -> Result<String, anyhow::Error> {
+    let mut local = Vec::new();
+    for server in server::servers(hosts) {
+        let host = server.rsplit_once(':').map_or(server.as_str(), |(h, _)| h);
+        let name = ServerName::try_from(host.to_string())?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        local.push(listener.local_addr()?.to_string());
+        log::debug!("Tunnelling {} over TLS", server);
+        let config = config.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (server, name, config) = (server.clone(), name.clone(), config.clone());
+                thread::spawn(move || {
+                    if let Err(e) = bridge(stream, &server, name, config) {
+                        log::warn!("TLS tunnel to {} closed, {}", server, e);
+                    }
+                });
+            }
+        });
+    }
+    Ok(local.join(",") + server::chroot(hosts).unwrap_or_default())
+}
+
+/// Relays one client connection to `server` until either side closes it. Each direction has a
+/// thread of its own blocking on its socket, and only takes the TLS state to process what it
+/// read, so neither waits for the other.
+fn bridge(
+    local: TcpStream,
+    server: &str,
+    name: ServerName<'static>,
+    config: Arc<ClientConfig>,
+) -> io::Result<()> {
+    let mut remote = TcpStream::connect(server)?;
+    local.set_nodelay(true)?;
+    remote.set_nodelay(true)?;
+    let mut conn = ClientConnection::new(config, name).map_err(io::Error::other)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut remote)?;
+    }
+    let conn = Arc::new(Mutex::new(conn));
+
+    let receiving = {
+        let (conn, remote, local) = (conn.clone(), remote.try_clone()?, local.try_clone()?);
+        thread::spawn(move || {
+            let result = receive(&conn, &remote, &local);
+            let _ = local.shutdown(Shutdown::Both);
+            let _ = remote.shutdown(Shutdown::Both);
+            result
+        })
+    };
+    let result = send(&conn, &local, &remote);
+    let _ = local.shutdown(Shutdown::Both);
+    let _ = remote.shutdown(Shutdown::Both);
+    let received = receiving.join().unwrap();
+    result.and(received)
+}
+
+/// Encrypts what the client sends to the server.
+fn send(
+    conn: &Mutex<ClientConnection>,
+    mut local: &TcpStream,
+    mut remote: &TcpStream,
+) -> io::Result<()> {
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let n = local.read(&mut buf)?;
+        let mut conn = conn.lock().unwrap();
+        if n == 0 {
+            conn.send_close_notify();
+        } else {
+            conn.writer().write_all(&buf[..n])?;
+        }
+        while conn.wants_write() {
+            conn.write_tls(&mut remote)?;
+        }
+        if n == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Decrypts what the server sends to the client.
+fn receive(
+    conn: &Mutex<ClientConnection>,
+    mut remote: &TcpStream,
+    mut local: &TcpStream,
+) -> io::Result<()> {
+    let mut buf = vec![0; BUFFER_SIZE];
+    let mut plain = Vec::new();
+    loop {
+        let n = remote.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        let mut closed = false;
+        {
+            let mut conn = conn.lock().unwrap();
+            let mut data = &buf[..n];
+            while !data.is_empty() && !closed {
+                conn.read_tls(&mut data)?;
+                conn.process_new_packets().map_err(io::Error::other)?;
+                // Drained after every record, as the plain text buffer is bounded
+                match conn.reader().read_to_end(&mut plain) {
+                    Ok(_) => closed = true,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            while conn.wants_write() {
+                conn.write_tls(&mut remote)?;
+            }
+        }
+        local.write_all(&plain)?;
+        plain.clear();
+        if closed {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use rand::Rng;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zookeeper::ZkError;
+
+/// Spans per export request
+const BATCH_SIZE: usize = 512;
+/// Spans beyond this many waiting for the next export are dropped
+const MAX_PENDING: usize = 65536;
+
+/// Exports a sample of operations as OTLP/HTTP JSON spans, one trace per operation. Exports run
+/// on a background thread once a second, so workers never wait on the collector.
+#[derive(Debug)]
+pub struct Tracer {
+    url: String,
+    sample_rate: f64,
+    // Wall clock at `epoch`, so spans only need the monotonic start of each operation
+    wall: SystemTime,
+    epoch: Instant,
+    pending: Mutex<Vec<Value>>,
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
+}
+
+fn string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+impl Tracer {
+    /// `endpoint` is the collector's OTLP/HTTP base URL, e.g. `http://localhost:4318`.
+    pub fn new(endpoint: &str, sample_rate: f64) -> Arc<Self> {
+        let tracer = Arc::new(Tracer {
+            url: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+            sample_rate,
+            wall: SystemTime::now(),
+            epoch: Instant::now(),
+            pending: Mutex::new(Vec::new()),
+        });
+        let weak = Arc::downgrade(&tracer);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            match weak.upgrade() {
+                Some(tracer) => tracer.flush(),
+                None => break,
+            }
+        });
+        log::info!(
+            "Exporting {}% of operations as spans to {}",
+            sample_rate * 100.0,
+            tracer.url
+        );
+        tracer
+    }
+
+    pub fn observe(
+        &self,
+        op: &'static str,
+        path: &str,
+        start: Instant,
+        latency: Duration,
+        result: Option<&ZkError>,
+    ) {
+        if !rand::thread_rng().gen_bool(self.sample_rate) {
+            return;
+        }
+
+        let start = self.wall + start.duration_since(self.epoch);
+        let start = start.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let status = match result {
+            None => json!({ "code": 0 }),
+            Some(e) => json!({ "code": 2, "message": format!("{:?}", e) }),
+        };
+        let span = json!({
+            "traceId": random_hex(16),
+            "spanId": random_hex(8),
+            "name": op,
+            // SPAN_KIND_CLIENT
+            "kind": 3,
+            "startTimeUnixNano": start.as_nanos().to_string(),
+            "endTimeUnixNano": (start + latency).as_nanos().to_string(),
+            "attributes": [
+                string_attr("db.system", "zookeeper"),
+                string_attr("db.operation", op),
+                string_attr("zk.path", path),
+            ],
+            "status": status,
+        });
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() < MAX_PENDING {
+            pending.push(span);
+        }
+    }
+
+    pub fn flush(&self) {
+        let spans = std::mem::take(&mut *self.pending.lock().unwrap());
+        for batch in spans.chunks(BATCH_SIZE) {
+            self.export(batch);
+        }
+    }
+
+    fn export(&self, spans: &[Value]) {
+        let body = json!({
+            "resourceSpans": [{
+                "resource": { "attributes": [string_attr("service.name", "zoobench")] },
+                "scopeSpans": [{
+                    "scope": { "name": "zoobench", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        });
+        // Losing spans must never fail the benchmark
+        if let Err(e) = ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+        {
+            log::warn!("Failed to export spans, {}", e);
+        }
+    }
+}
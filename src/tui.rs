@@ -0,0 +1,270 @@
+use crate::bench::Mode;
+use crate::logger;
+use crate::stats::{ErrorCounts, Latencies};
+use indicatif::ProgressBar;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::Frame;
+use signal_hook::consts::SIGINT;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use zookeeper::ZkError;
+
+/// Seconds of operations the rolling percentiles cover
+const ROLLING_SECS: u64 = 10;
+const REFRESH: Duration = Duration::from_millis(250);
+/// Narrowest column of the worker list before workers get cut off
+const WORKER_COLUMN_WIDTH: u16 = 36;
+
+#[derive(Debug, Default)]
+struct State {
+    /// Operations completed in each second since the dashboard started
+    throughput: Vec<u64>,
+    /// Latencies per operation of the most recent seconds, oldest first
+    recent: VecDeque<(u64, BTreeMap<&'static str, Latencies>)>,
+    errors: ErrorCounts,
+    /// Progress bars of the running phase, which the dashboard draws in place of the terminal
+    workers: Vec<ProgressBar>,
+}
+
+/// Live dashboard of `--tui` mode: aggregate throughput, rolling percentiles, errors and the
+/// status of every worker.
+#[derive(Debug)]
+pub struct Dashboard {
+    mode: Mode,
+    start: Instant,
+    state: Mutex<State>,
+}
+
+impl Dashboard {
+    pub fn new(mode: Mode) -> Self {
+        Dashboard {
+            mode,
+            start: Instant::now(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn observe(&self, op: &'static str, latency: Duration, result: Option<&ZkError>) {
+        let second = self.start.elapsed().as_secs();
+        let mut state = self.state.lock().unwrap();
+        match result {
+            None => {
+                let i = second as usize;
+                if state.throughput.len() <= i {
+                    state.throughput.resize(i + 1, 0);
+                }
+                state.throughput[i] += 1;
+            }
+            Some(e) => state.errors.record(e),
+        }
+
+        if state.recent.back().map(|(s, _)| *s) != Some(second) {
+            state.recent.push_back((second, BTreeMap::new()));
+        }
+        while state
+            .recent
+            .front()
+            .is_some_and(|(s, _)| s + ROLLING_SECS <= second)
+        {
+            state.recent.pop_front();
+        }
+        let (_, ops) = state.recent.back_mut().unwrap();
+        ops.entry(op).or_default().record(latency);
+    }
+
+    /// Shows the progress bars of a new phase instead of the previous one's.
+    pub fn set_workers(&self, workers: Vec<ProgressBar>) {
+        self.state.lock().unwrap().workers = workers;
+    }
+
+    /// Takes over the terminal until the returned screen is dropped. Logging below errors is
+    /// muted meanwhile, as it would scroll the dashboard away.
+    pub fn show(self: &Arc<Self>) -> io::Result<Screen> {
+        let mut terminal = ratatui::try_init()?;
+        let level = logger::set_terminal_level(log::LevelFilter::Error);
+        let running = Arc::new(AtomicBool::new(true));
+        let (dashboard, still_running) = (self.clone(), running.clone());
+        let render = thread::spawn(move || {
+            let mut pressed = false;
+            while still_running.load(Ordering::Relaxed) {
+                if let Err(e) = terminal.draw(|f| dashboard.draw(f)) {
+                    log::error!("Failed to draw dashboard, {}", e);
+                    break;
+                }
+                if !interrupted() {
+                    continue;
+                }
+                // Raw mode swallows the SIGINT of Ctrl-C, so raise it as the terminal would
+                // have, which stops the workers, and quit at once the second time
+                if !pressed {
+                    pressed = true;
+                    let _ = signal_hook::low_level::raise(SIGINT);
+                } else {
+                    let _ = ratatui::try_restore();
+                    logger::set_terminal_level(level);
+                    log::error!("Interrupted");
+                    std::process::exit(130);
+                }
+            }
+        });
+        Ok(Screen {
+            running,
+            render: Some(render),
+            level,
+        })
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        let state = self.state.lock().unwrap();
+        let [header, graph, stats, workers] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ])
+        .areas(f.area());
+        let [percentiles, errors] =
+            Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)]).areas(stats);
+
+        let finished = state.workers.iter().filter(|pb| pb.is_finished()).count();
+        let total: u64 = state.throughput.iter().sum();
+        f.render_widget(
+            Paragraph::new(format!(
+                "Mode: {:?}, elapsed: {}s, ops: {}, errors: {}, workers done: {}/{}",
+                self.mode,
+                self.start.elapsed().as_secs(),
+                total,
+                state.errors.total(),
+                finished,
+                state.workers.len()
+            ))
+            .block(Block::bordered().title(" zoobench (q to quit) ")),
+            header,
+        );
+
+        // The second in progress is still filling up, so the graph ends at the last full one
+        let full = state
+            .throughput
+            .len()
+            .min(self.start.elapsed().as_secs() as usize);
+        let shown = &state.throughput[..full];
+        let width = graph.width.saturating_sub(2) as usize;
+        let shown = &shown[shown.len().saturating_sub(width)..];
+        f.render_widget(
+            Sparkline::default()
+                .data(shown)
+                .block(Block::bordered().title(format!(
+                    " Throughput, last: {} ops/s, peak: {} ops/s ",
+                    shown.last().copied().unwrap_or(0),
+                    shown.iter().max().copied().unwrap_or(0)
+                ))),
+            graph,
+        );
+
+        let mut merged: BTreeMap<&str, Latencies> = BTreeMap::new();
+        let now = self.start.elapsed().as_secs();
+        for (_, ops) in state.recent.iter().filter(|(s, _)| s + ROLLING_SECS > now) {
+            for (op, l) in ops {
+                merged.entry(op).or_default().merge(l);
+            }
+        }
+        let rows = merged.iter().map(|(op, l)| {
+            let s = l.summary();
+            Row::new([
+                op.to_string(),
+                format!("{:.1}", s.count as f64 / ROLLING_SECS as f64),
+                format!("{:.2?}", s.p50),
+                format!("{:.2?}", s.p99),
+                format!("{:.2?}", s.p999),
+                format!("{:.2?}", s.max),
+            ])
+        });
+        f.render_widget(
+            Table::new(rows, [Constraint::Fill(1); 6])
+                .header(Row::new(["op", "ops/s", "p50", "p99", "p99.9", "max"]))
+                .block(Block::bordered().title(format!(" Last {}s ", ROLLING_SECS))),
+            percentiles,
+        );
+
+        let errors_text = state.errors.to_string().replace(", ", "\n");
+        f.render_widget(
+            Paragraph::new(errors_text).block(Block::bordered().title(" Errors ")),
+            errors,
+        );
+
+        draw_workers(f, workers, &state.workers);
+    }
+}
+
+/// Lists workers in as many columns as it takes to fit them, cutting off those that still don't.
+fn draw_workers(f: &mut Frame, area: Rect, workers: &[ProgressBar]) {
+    let block = Block::bordered().title(" Workers ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height == 0 || workers.is_empty() {
+        return;
+    }
+
+    let per_column = inner.height as usize;
+    let max_columns = (inner.width / WORKER_COLUMN_WIDTH).max(1) as usize;
+    let columns = workers.len().div_ceil(per_column).min(max_columns);
+    let areas = Layout::horizontal(vec![Constraint::Fill(1); columns]).split(inner);
+    for (c, (chunk, area)) in workers.chunks(per_column).zip(areas.iter()).enumerate() {
+        let rows = chunk.iter().enumerate().map(|(i, pb)| {
+            let progress = match pb.length() {
+                Some(len) if len > 0 => format!("{:>3}%", pb.position() * 100 / len),
+                _ => pb.position().to_string(),
+            };
+            Row::new([format!("#{}", c * per_column + i), progress, pb.message()])
+        });
+        f.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Length(5),
+                    Constraint::Length(5),
+                    Constraint::Fill(1),
+                ],
+            ),
+            *area,
+        );
+    }
+}
+
+/// Whether q or Ctrl-C was pressed, waiting up to the next refresh for a key.
+fn interrupted() -> bool {
+    let Ok(true) = event::poll(REFRESH) else {
+        return false;
+    };
+    match event::read() {
+        Ok(Event::Key(k)) => {
+            k.code == KeyCode::Char('q')
+                || (k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL))
+        }
+        _ => false,
+    }
+}
+
+/// The terminal while the dashboard is shown, given back when dropped.
+pub struct Screen {
+    running: Arc<AtomicBool>,
+    render: Option<JoinHandle<()>>,
+    level: log::LevelFilter,
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(render) = self.render.take() {
+            let _ = render.join();
+        }
+        let _ = ratatui::try_restore();
+        logger::set_terminal_level(self.level);
+    }
+}
@@ -0,0 +1,74 @@
+/// Length prefix of every packet
+const FRAME: u64 = 4;
+/// xid and op type
+const REQUEST_HEADER: u64 = 8;
+/// xid, zxid and error code
+const REPLY_HEADER: u64 = 16;
+/// Every field of a `Stat`
+const STAT: u64 = 68;
+/// The single world:anyone ACL every znode is created with
+const OPEN_ACL: u64 = 4 + 4 + (4 + 5) + (4 + 6);
+
+fn string(s: &str) -> u64 {
+    4 + s.len() as u64
+}
+
+fn buffer(len: usize) -> u64 {
+    4 + len as u64
+}
+
+pub fn create_request(path: &str, data_len: usize) -> u64 {
+    FRAME + REQUEST_HEADER + string(path) + buffer(data_len) + OPEN_ACL + 4
+}
+
+pub fn create_reply(path: &str) -> u64 {
+    FRAME + REPLY_HEADER + string(path)
+}
+
+pub fn get_data_request(path: &str) -> u64 {
+    FRAME + REQUEST_HEADER + string(path) + 1
+}
+
+pub fn get_data_reply(data_len: usize) -> u64 {
+    FRAME + REPLY_HEADER + buffer(data_len) + STAT
+}
+
+pub fn set_data_request(path: &str, data_len: usize) -> u64 {
+    FRAME + REQUEST_HEADER + string(path) + buffer(data_len) + 4
+}
+
+pub fn set_data_reply() -> u64 {
+    FRAME + REPLY_HEADER + STAT
+}
+
+pub fn delete_request(path: &str) -> u64 {
+    FRAME + REQUEST_HEADER + string(path) + 4
+}
+
+pub fn delete_reply() -> u64 {
+    FRAME + REPLY_HEADER
+}
+
+/// A reply carrying nothing but an error code.
+pub fn error_reply() -> u64 {
+    FRAME + REPLY_HEADER
+}
+
+/// Bytes a client sent and received, estimated from the jute encoding of the operations it ran
+/// rather than captured, so TCP/IP overhead and pings aren't included.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Traffic {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl Traffic {
+    pub fn record(&mut self, sent: u64, received: u64) {
+        self.sent += sent;
+        self.received += received;
+    }
+
+    pub fn merge(&mut self, other: &Traffic) {
+        self.record(other.sent, other.received);
+    }
+}